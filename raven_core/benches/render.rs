@@ -0,0 +1,111 @@
+//! Measures how long it takes to render a frame of a scene with 10,000 cubes, with and without instancing. Without
+//! instancing, every cube is drawn with its own draw call in scene order, the way meshes were drawn before batching.
+//!
+//! Run with `cargo bench -p raven_core`. Rendering happens in a headless context, so this works on machines without a
+//! display but, when there's no GPU, it mostly measures Mesa's software rasterizer.
+
+#![feature(test)]
+
+extern crate test;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use test::Bencher;
+
+use raven_core::component::{CameraComponent, HierarchyComponent, MeshComponent, TransformComponent};
+use raven_core::database::AssetRef;
+use raven_core::glam::{Mat4, Vec3};
+use raven_core::headless::HeadlessContext;
+use raven_core::io::Serializable;
+use raven_core::resource::{Material, Scene, Texture};
+use raven_core::Processor;
+
+use common::{save_cube, Result};
+
+// Shared with the integration tests
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+// Small, so that the time isn't dominated by filling pixels
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+
+// 100x100 grid of cubes
+const GRID_SIDE: usize = 100;
+
+#[bench]
+fn cubes_10k_instanced(b: &mut Bencher) {
+    bench_cubes(b, true);
+}
+
+#[bench]
+fn cubes_10k_not_instanced(b: &mut Bencher) {
+    bench_cubes(b, false);
+}
+
+fn bench_cubes(b: &mut Bencher, instancing: bool) {
+    let _context = HeadlessContext::new(WIDTH, HEIGHT).expect("couldn't create a headless context");
+
+    let project_root = build_project().expect("couldn't build the project");
+
+    let mut processor = Processor::new(&project_root).unwrap();
+    processor.set_canvas_size(WIDTH, HEIGHT);
+    processor.set_instancing(instancing);
+    // So that the first frame loads all the assets
+    processor.set_streaming(false);
+    processor.load_scene("$/main.scn").unwrap();
+
+    processor.do_frame().unwrap();
+
+    b.iter(|| {
+        processor.do_frame().unwrap();
+        // Reading the frame back waits for the GPU to be done with it
+        processor.capture_frame()
+    });
+}
+
+/// Builds a project with a grid of cubes that all fit in the camera's view, half of them textured.
+fn build_project() -> Result<PathBuf> {
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join("bench").join("cubes_10k");
+    if root.exists() {
+        fs::remove_dir_all(&root)?;
+    }
+    fs::create_dir_all(&root)?;
+
+    save_cube(&root)?;
+    Texture::new(vec![200, 120, 40, 255], [1, 1]).save(root.join("orange.tex"))?;
+    Material::default().save(root.join("plain.mat"))?;
+    Material { tex: Some(AssetRef::from("$/orange.tex")), ..Material::default() }.save(root.join("orange.mat"))?;
+
+    let mut scene = Scene::default();
+
+    let camera = scene.create();
+    let eye = Vec3::new(0.0, 0.0, GRID_SIDE as f32 * 0.6);
+    scene.attach(camera, TransformComponent(Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y).inverse()));
+    scene.attach(camera, HierarchyComponent::default());
+    scene.attach(camera, CameraComponent {
+        skybox: false,
+        ..CameraComponent::default()
+    });
+
+    for i in 0..GRID_SIDE * GRID_SIDE {
+        let (x, y) = (i % GRID_SIDE, i / GRID_SIDE);
+        let position = Vec3::new(x as f32, y as f32, 0.0) - Vec3::new(GRID_SIDE as f32, GRID_SIDE as f32, 0.0) / 2.0;
+
+        let mat = if i % 2 == 0 { "$/plain.mat" } else { "$/orange.mat" };
+
+        let cube = scene.create();
+        scene.attach(cube, TransformComponent(Mat4::from_scale_rotation_translation(
+            Vec3::splat(0.5),
+            Default::default(),
+            position,
+        )));
+        scene.attach(cube, HierarchyComponent::default());
+        scene.attach(cube, MeshComponent::new(PathBuf::from("$/cube.mesh"), PathBuf::from(mat)));
+    }
+
+    scene.save(root.join("main.scn"))?;
+
+    Ok(root)
+}
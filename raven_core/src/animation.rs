@@ -0,0 +1,537 @@
+use std::ops::{Add, Mul, Sub};
+
+use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::component::{
+    AnimatorComponent, CameraComponent, HierarchyComponent, LightComponent, NameComponent, Projection, TransformComponent,
+};
+use crate::ecs::Entity;
+use crate::resource::{AnimatedProperty, AnimationClip, JointTransform, KeyframeClip, Scene, Skeleton};
+
+/// Values of something over time, as keyframes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Track<T> {
+    /// In seconds, ascending
+    pub times: Vec<f32>,
+    /// One per time
+    pub values: Vec<T>,
+    pub interpolation: Interpolation,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Track {
+            times: Vec::new(),
+            values: Vec::new(),
+            interpolation: Interpolation::Linear,
+        }
+    }
+}
+
+/// How the value of a track goes from one keyframe to the next.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    /// Holds the value of a keyframe until the next one
+    Step,
+    /// Smooth through the keyframes, with tangents that point from the keyframe before to the one after
+    Cubic,
+}
+
+/// Values that can be blended, `t` going from 0 for `self` to 1 for `to`.
+pub trait Interpolate: Copy {
+    fn interpolate(self, to: Self, t: f32) -> Self;
+
+    /// Goes from the second value to the third along a cubic curve, the first and the last being the keyframes around
+    /// them. `times` are the ones of the four keyframes.
+    fn cubic(values: [Self; 4], times: [f32; 4], t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+
+    fn cubic(values: [Self; 4], times: [f32; 4], t: f32) -> Self {
+        hermite(values, times, t)
+    }
+}
+
+impl Interpolate for Vec3 {
+    fn interpolate(self, to: Self, t: f32) -> Self {
+        self.lerp(to, t)
+    }
+
+    fn cubic(values: [Self; 4], times: [f32; 4], t: f32) -> Self {
+        hermite(values, times, t)
+    }
+}
+
+impl Interpolate for Quat {
+    fn interpolate(self, to: Self, t: f32) -> Self {
+        // `q` and `-q` are the same rotation, the one closer to `self` takes the shortest way there
+        let to = if self.dot(to) < 0.0 { -to } else { to };
+        self.slerp(to, t).normalize()
+    }
+
+    fn cubic(mut values: [Self; 4], times: [f32; 4], t: f32) -> Self {
+        // Each one on the same side as the one before, then the curve is bent back onto rotations
+        for i in 1..4 {
+            if values[i - 1].dot(values[i]) < 0.0 {
+                values[i] = -values[i];
+            }
+        }
+
+        hermite(values, times, t).normalize()
+    }
+}
+
+impl Interpolate for JointTransform {
+    fn interpolate(self, to: Self, t: f32) -> Self {
+        JointTransform {
+            translation: self.translation.interpolate(to.translation, t),
+            rotation: self.rotation.interpolate(to.rotation, t),
+            scale: self.scale.interpolate(to.scale, t),
+        }
+    }
+
+    fn cubic(values: [Self; 4], times: [f32; 4], t: f32) -> Self {
+        JointTransform {
+            translation: Vec3::cubic(values.map(|value| value.translation), times, t),
+            rotation: Quat::cubic(values.map(|value| value.rotation), times, t),
+            scale: Vec3::cubic(values.map(|value| value.scale), times, t),
+        }
+    }
+}
+
+/// Cubic Hermite spline between `values[1]` and `values[2]`, with the tangents of a Catmull-Rom spline that respect
+/// how far apart the keyframes are.
+fn hermite<T>(values: [T; 4], times: [f32; 4], t: f32) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    let [p0, p1, p2, p3] = values;
+    let [t0, t1, t2, t3] = times;
+
+    // Tangents scaled to the span of the segment, since `t` goes from 0 to 1 over it
+    let span = t2 - t1;
+    let m1 = (p2 - p0) * (span / (t2 - t0));
+    let m2 = (p3 - p1) * (span / (t3 - t1));
+
+    let t2_ = t * t;
+    let t3_ = t2_ * t;
+
+    p1 * (2.0 * t3_ - 3.0 * t2_ + 1.0)
+        + m1 * (t3_ - 2.0 * t2_ + t)
+        + p2 * (-2.0 * t3_ + 3.0 * t2_)
+        + m2 * (t3_ - t2_)
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new(times: Vec<f32>, values: Vec<T>, interpolation: Interpolation) -> Track<T> {
+        Track {
+            times,
+            values,
+            interpolation,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty() || self.values.is_empty()
+    }
+
+    /// The value at the given time, which is the first or the last one outside of the keyframes. `None` if there are
+    /// no keyframes.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let last = self.times.len().min(self.values.len()).checked_sub(1)?;
+
+        if time <= self.times[0] {
+            return Some(self.values[0]);
+        }
+
+        if time >= self.times[last] {
+            return Some(self.values[last]);
+        }
+
+        // The first keyframe after the time, there's one before it too
+        let next = self.times[..=last].partition_point(|&key_time| key_time <= time);
+        let prev = next - 1;
+
+        let span = self.times[next] - self.times[prev];
+        let t = if span > 0.0 { (time - self.times[prev]) / span } else { 0.0 };
+
+        Some(match self.interpolation {
+            Interpolation::Step => self.values[prev],
+            Interpolation::Linear => self.values[prev].interpolate(self.values[next], t),
+            Interpolation::Cubic => {
+                if span <= 0.0 {
+                    return Some(self.values[prev]);
+                }
+
+                // The first and the last keyframes stand in for the ones before and after them
+                let before = prev.saturating_sub(1);
+                let after = (next + 1).min(last);
+
+                T::cubic(
+                    [self.values[before], self.values[prev], self.values[next], self.values[after]],
+                    [
+                        if before < prev { self.times[before] } else { self.times[prev] - span },
+                        self.times[prev],
+                        self.times[next],
+                        if after > next { self.times[after] } else { self.times[next] + span },
+                    ],
+                    t,
+                )
+            }
+        })
+    }
+}
+
+/// Where in a clip of the given duration playback is after the given amount of seconds: it starts over when looping,
+/// otherwise it stays on the last frame.
+pub fn clip_time(time: f32, duration: f32, looping: bool) -> f32 {
+    if duration <= 0.0 {
+        0.0
+    } else if looping {
+        time.rem_euclid(duration)
+    } else {
+        time.clamp(0.0, duration)
+    }
+}
+
+/// A numeric field of a component that keyframe clips can animate.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ComponentField {
+    /// Of perspective cameras, in degrees
+    CameraFov,
+    /// Of orthographic cameras
+    CameraHeight,
+    CameraNear,
+    CameraFar,
+    /// One of the four channels
+    CameraClearColor(usize),
+    /// One of the three channels
+    LightColor(usize),
+    LightIntensity,
+    LightRange,
+    AnimatorSpeed,
+    /// Of the layer at the given index, to fade skeletal animations in and out
+    AnimatorLayerWeight(usize),
+}
+
+impl ComponentField {
+    /// Sets the field of the first component of its kind on the entity, if it has one.
+    pub fn set(self, scene: &mut Scene, entity: Entity, value: f32) {
+        match self {
+            ComponentField::CameraFov
+            | ComponentField::CameraHeight
+            | ComponentField::CameraNear
+            | ComponentField::CameraFar
+            | ComponentField::CameraClearColor(_) => {
+                let mut camera_comp = match scene.get_one_mut::<CameraComponent>(entity) {
+                    Some(camera_comp) => camera_comp,
+                    None => return,
+                };
+
+                match (self, &mut camera_comp.projection) {
+                    (ComponentField::CameraFov, Projection::Perspective { fov, .. }) => *fov = value,
+                    (ComponentField::CameraHeight, Projection::Orthographic { height, .. }) => *height = value,
+                    (ComponentField::CameraNear, Projection::Perspective { near, .. })
+                    | (ComponentField::CameraNear, Projection::Orthographic { near, .. }) => *near = value,
+                    (ComponentField::CameraFar, Projection::Perspective { far, .. })
+                    | (ComponentField::CameraFar, Projection::Orthographic { far, .. }) => *far = value,
+                    (ComponentField::CameraClearColor(i), _) => {
+                        if let Some(channel) = camera_comp.clear_color.get_mut(i) {
+                            *channel = value;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            ComponentField::LightColor(_) | ComponentField::LightIntensity | ComponentField::LightRange => {
+                let mut light_comp = match scene.get_one_mut::<LightComponent>(entity) {
+                    Some(light_comp) => light_comp,
+                    None => return,
+                };
+
+                match self {
+                    ComponentField::LightColor(i) => {
+                        if let Some(channel) = light_comp.color.get_mut(i) {
+                            *channel = value;
+                        }
+                    }
+                    ComponentField::LightIntensity => light_comp.intensity = value,
+                    ComponentField::LightRange => light_comp.range = Some(value),
+                    _ => {}
+                }
+            }
+            ComponentField::AnimatorSpeed | ComponentField::AnimatorLayerWeight(_) => {
+                let mut animator_comp = match scene.get_one_mut::<AnimatorComponent>(entity) {
+                    Some(animator_comp) => animator_comp,
+                    None => return,
+                };
+
+                match self {
+                    ComponentField::AnimatorSpeed => animator_comp.speed = value,
+                    ComponentField::AnimatorLayerWeight(i) => {
+                        if let Some(layer) = animator_comp.layers.get_mut(i) {
+                            layer.weight = value;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Sets what the clip animates to its values at the given time, in seconds, on the entity playing it and its
+/// descendants. Channels whose entity can't be found are skipped.
+pub fn apply_clip(scene: &mut Scene, entity: Entity, clip: &KeyframeClip, time: f32) {
+    for channel in &clip.channels {
+        let target = match find_descendant(scene, entity, &channel.target) {
+            Some(target) => target,
+            None => continue,
+        };
+
+        if let AnimatedProperty::Field(field, track) = &channel.property {
+            if let Some(value) = track.sample(time) {
+                field.set(scene, target, value);
+            }
+
+            continue;
+        }
+
+        let mut transform_comp = match scene.get_one_mut::<TransformComponent>(target) {
+            Some(transform_comp) => transform_comp,
+            None => continue,
+        };
+
+        let mut transform = JointTransform::from_mat4(&transform_comp.0);
+
+        match &channel.property {
+            AnimatedProperty::Translation(track) => {
+                transform.translation = track.sample(time).unwrap_or(transform.translation);
+            }
+            AnimatedProperty::Rotation(track) => {
+                transform.rotation = track.sample(time).unwrap_or(transform.rotation);
+            }
+            AnimatedProperty::Scale(track) => {
+                transform.scale = track.sample(time).unwrap_or(transform.scale);
+            }
+            AnimatedProperty::Field(..) => unreachable!(),
+        }
+
+        transform_comp.0 = transform.to_mat4();
+    }
+}
+
+/// Follows the names down the hierarchy from the entity, taking the first child with each name.
+fn find_descendant(scene: &Scene, mut entity: Entity, names: &[String]) -> Option<Entity> {
+    for name in names {
+        let children = scene.get_one::<HierarchyComponent>(entity)?.children.clone();
+
+        entity = children.into_iter().find(|&child| {
+            matches!(scene.get_one::<NameComponent>(child), Some(name_comp) if &name_comp.0 == name)
+        })?;
+    }
+
+    Some(entity)
+}
+
+/// The transforms of the joints of a skeleton, each relative to its parent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pose(pub Vec<JointTransform>);
+
+impl Pose {
+    /// Every joint at its rest transform.
+    pub fn rest(skeleton: &Skeleton) -> Pose {
+        Pose(skeleton.joints.iter().map(|joint| joint.rest).collect())
+    }
+
+    /// The skeleton as posed by the clip at the given time, in seconds. Joints the clip doesn't animate stay at rest.
+    pub fn sample(skeleton: &Skeleton, clip: &AnimationClip, time: f32) -> Pose {
+        let mut pose = Pose::rest(skeleton);
+
+        for channel in &clip.channels {
+            let joint = match skeleton.joint_index(&channel.joint) {
+                Some(joint) => &mut pose.0[joint],
+                None => continue,
+            };
+
+            if let Some(translation) = channel.translation.sample(time) {
+                joint.translation = translation;
+            }
+
+            if let Some(rotation) = channel.rotation.sample(time) {
+                joint.rotation = rotation;
+            }
+
+            if let Some(scale) = channel.scale.sample(time) {
+                joint.scale = scale;
+            }
+        }
+
+        pose
+    }
+
+    /// Moves every joint toward where it is in the other pose, all the way when `t` is 1.
+    pub fn blend(&mut self, other: &Pose, t: f32) {
+        for (joint, other_joint) in self.0.iter_mut().zip(&other.0) {
+            *joint = joint.interpolate(*other_joint, t);
+        }
+    }
+
+    /// The matrices vertices are skinned with, one per joint: from the space of the meshes to the one of the entity
+    /// with the `AnimatorComponent`, through the joint as posed.
+    pub fn skinning_matrices(&self, skeleton: &Skeleton) -> Vec<Mat4> {
+        let mut globals: Vec<Mat4> = Vec::with_capacity(self.0.len());
+
+        for (joint, transform) in skeleton.joints.iter().zip(&self.0) {
+            // Parents come first, so theirs is already known
+            let parent = joint.parent.and_then(|parent| globals.get(parent)).copied().unwrap_or(Mat4::IDENTITY);
+            globals.push(parent * transform.to_mat4());
+        }
+
+        globals
+            .iter()
+            .zip(&skeleton.joints)
+            .map(|(global, joint)| *global * joint.inverse_bind)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{Mat4, Quat, Vec3};
+
+    use crate::component::{HierarchyComponent, LightComponent, LightKind, NameComponent, TransformComponent};
+    use crate::resource::{
+        AnimatedProperty, AnimationClip, Joint, JointChannel, JointTransform, KeyframeChannel, KeyframeClip, Scene,
+        Skeleton,
+    };
+
+    use super::{apply_clip, ComponentField, Interpolation, Pose, Track};
+
+    #[test]
+    fn samples_tracks() {
+        let track = Track::new(vec![0.0, 1.0, 3.0], vec![0.0, 10.0, 30.0], Interpolation::Linear);
+
+        assert_eq!(track.sample(-1.0), Some(0.0));
+        assert_eq!(track.sample(0.5), Some(5.0));
+        assert_eq!(track.sample(2.0), Some(20.0));
+        assert_eq!(track.sample(5.0), Some(30.0));
+
+        let track = Track { interpolation: Interpolation::Step, ..track };
+        assert_eq!(track.sample(2.9), Some(10.0));
+
+        assert_eq!(Track::<f32>::default().sample(1.0), None);
+
+        // Through the keyframes, but coming out of the flat stretch slower than a straight line would
+        let track = Track::new(vec![0.0, 1.0, 2.0, 3.0], vec![0.0, 0.0, 10.0, 20.0], Interpolation::Cubic);
+        assert_eq!(track.sample(1.0), Some(0.0));
+        assert_eq!(track.sample(2.0), Some(10.0));
+
+        let value = track.sample(1.5).unwrap();
+        assert!(value > 0.0 && value < 5.0);
+    }
+
+    #[test]
+    fn applies_keyframe_clips() {
+        let mut scene = Scene::default();
+
+        let root = scene.create();
+        let lamp = scene.create();
+
+        scene.attach(root, HierarchyComponent { parent: None, children: vec![lamp] });
+        scene.attach(root, TransformComponent(Mat4::from_scale(Vec3::splat(2.0))));
+
+        scene.attach(lamp, NameComponent("lamp".to_owned()));
+        scene.attach(lamp, HierarchyComponent { parent: Some(root), children: vec![] });
+        scene.attach(lamp, LightComponent { kind: LightKind::Point, color: [1.0; 3], intensity: 1.0, range: None });
+
+        let clip = KeyframeClip {
+            duration: 2.0,
+            channels: vec![
+                KeyframeChannel {
+                    target: vec![],
+                    property: AnimatedProperty::Translation(Track::new(
+                        vec![0.0, 2.0],
+                        vec![Vec3::ZERO, Vec3::new(0.0, 4.0, 0.0)],
+                        Interpolation::Linear,
+                    )),
+                },
+                KeyframeChannel {
+                    target: vec!["lamp".to_owned()],
+                    property: AnimatedProperty::Field(
+                        ComponentField::LightIntensity,
+                        Track::new(vec![0.0, 1.0], vec![0.0, 5.0], Interpolation::Step),
+                    ),
+                },
+                KeyframeChannel {
+                    target: vec!["missing".to_owned()],
+                    property: AnimatedProperty::Scale(Track::new(vec![0.0], vec![Vec3::ZERO], Interpolation::Linear)),
+                },
+            ],
+        };
+
+        apply_clip(&mut scene, root, &clip, 1.0);
+
+        // The scale that isn't animated is kept
+        let transform = scene.get_one::<TransformComponent>(root).unwrap().0;
+        let (scale, _, translation) = transform.to_scale_rotation_translation();
+        assert!(translation.abs_diff_eq(Vec3::new(0.0, 2.0, 0.0), 1e-5));
+        assert!(scale.abs_diff_eq(Vec3::splat(2.0), 1e-5));
+
+        assert_eq!(scene.get_one::<LightComponent>(lamp).unwrap().intensity, 5.0);
+    }
+
+    #[test]
+    fn skins_with_the_posed_joints() {
+        // An arm one unit long with the elbow at the end of the shoulder, bound where it rests
+        let shoulder = JointTransform::default();
+        let elbow = JointTransform { translation: Vec3::X, ..JointTransform::default() };
+
+        let skeleton = Skeleton {
+            joints: vec![
+                Joint { name: "shoulder".to_owned(), parent: None, rest: shoulder, inverse_bind: Mat4::IDENTITY },
+                Joint {
+                    name: "elbow".to_owned(),
+                    parent: Some(0),
+                    rest: elbow,
+                    inverse_bind: elbow.to_mat4().inverse(),
+                },
+            ],
+        };
+
+        // At rest nothing moves
+        for mat in Pose::rest(&skeleton).skinning_matrices(&skeleton) {
+            assert!(mat.abs_diff_eq(Mat4::IDENTITY, 1e-6));
+        }
+
+        // Raising the shoulder carries the elbow along
+        let raise = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+
+        let clip = AnimationClip {
+            duration: 1.0,
+            channels: vec![JointChannel {
+                joint: "shoulder".to_owned(),
+                rotation: Track::new(vec![0.0, 1.0], vec![Quat::IDENTITY, raise], Interpolation::Linear),
+                ..JointChannel::default()
+            }],
+        };
+
+        let mats = Pose::sample(&skeleton, &clip, 1.0).skinning_matrices(&skeleton);
+
+        let hand = Vec3::new(2.0, 0.0, 0.0);
+        assert!(mats[1].transform_point3(hand).abs_diff_eq(Vec3::new(0.0, 2.0, 0.0), 1e-5));
+
+        // Half way through blending, the rotation is halved too
+        let mut pose = Pose::rest(&skeleton);
+        pose.blend(&Pose::sample(&skeleton, &clip, 1.0), 0.5);
+
+        let expected = Quat::from_rotation_z(std::f32::consts::FRAC_PI_4);
+        assert!(pose.0[0].rotation.abs_diff_eq(expected, 1e-5));
+    }
+}
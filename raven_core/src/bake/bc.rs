@@ -0,0 +1,217 @@
+use super::{distance_squared, Block};
+
+/// Encodes a block as BC1, ignoring alpha.
+pub(super) fn encode_bc1(block: &Block, out: &mut Vec<u8>) {
+    encode_color(block, out);
+}
+
+/// Encodes a block as BC3, alpha first and then the colours the same way as BC1.
+pub(super) fn encode_bc3(block: &Block, out: &mut Vec<u8>) {
+    encode_alpha(block, out);
+    encode_color(block, out);
+}
+
+fn encode_color(block: &Block, out: &mut Vec<u8>) {
+    let (min, max) = endpoints(block);
+
+    let mut color0 = to_565(max);
+    let mut color1 = to_565(min);
+
+    // The first endpoint must be the greater one to get 4 colours rather than 3 and a transparent black
+    if color0 < color1 {
+        std::mem::swap(&mut color0, &mut color1);
+    }
+
+    let mut indices = 0u32;
+
+    if color0 != color1 {
+        let c0 = from_565(color0);
+        let c1 = from_565(color1);
+
+        let palette = [
+            c0,
+            c1,
+            lerp(c0, c1, 1, 3),
+            lerp(c0, c1, 2, 3),
+        ];
+
+        for (i, pixel) in block.iter().enumerate() {
+            let rgb = [pixel[0] as i32, pixel[1] as i32, pixel[2] as i32];
+            indices |= nearest(&palette, rgb) << (i * 2);
+        }
+    }
+
+    out.extend_from_slice(&color0.to_le_bytes());
+    out.extend_from_slice(&color1.to_le_bytes());
+    out.extend_from_slice(&indices.to_le_bytes());
+}
+
+/// Colours at the two ends of the line that best fits the block, found along its principal axis.
+fn endpoints(block: &Block) -> ([f32; 3], [f32; 3]) {
+    let mut mean = [0.0f32; 3];
+
+    for pixel in block {
+        for c in 0..3 {
+            mean[c] += pixel[c] as f32 / 16.0;
+        }
+    }
+
+    let mut covariance = [[0.0f32; 3]; 3];
+
+    for pixel in block {
+        let d = [pixel[0] as f32 - mean[0], pixel[1] as f32 - mean[1], pixel[2] as f32 - mean[2]];
+
+        for i in 0..3 {
+            for j in 0..3 {
+                covariance[i][j] += d[i] * d[j];
+            }
+        }
+    }
+
+    // Power iteration converges to the eigenvector with the largest eigenvalue
+    let mut axis = [1.0f32, 1.0, 1.0];
+
+    for _ in 0..8 {
+        let next = [
+            covariance[0][0] * axis[0] + covariance[0][1] * axis[1] + covariance[0][2] * axis[2],
+            covariance[1][0] * axis[0] + covariance[1][1] * axis[1] + covariance[1][2] * axis[2],
+            covariance[2][0] * axis[0] + covariance[2][1] * axis[1] + covariance[2][2] * axis[2],
+        ];
+
+        let length = (next[0] * next[0] + next[1] * next[1] + next[2] * next[2]).sqrt();
+
+        if length < f32::EPSILON {
+            // All the pixels are the same colour
+            return (mean, mean);
+        }
+
+        axis = [next[0] / length, next[1] / length, next[2] / length];
+    }
+
+    let mut min_t = f32::MAX;
+    let mut max_t = f32::MIN;
+
+    for pixel in block {
+        let t = (0..3).map(|c| (pixel[c] as f32 - mean[c]) * axis[c]).sum::<f32>();
+        min_t = min_t.min(t);
+        max_t = max_t.max(t);
+    }
+
+    let at = |t: f32| [mean[0] + axis[0] * t, mean[1] + axis[1] * t, mean[2] + axis[2] * t];
+
+    (at(min_t), at(max_t))
+}
+
+fn to_565(color: [f32; 3]) -> u16 {
+    let quantize = |value: f32, max: f32| (value / 255.0 * max).round().clamp(0.0, max) as u16;
+
+    quantize(color[0], 31.0) << 11 | quantize(color[1], 63.0) << 5 | quantize(color[2], 31.0)
+}
+
+fn from_565(color: u16) -> [i32; 3] {
+    let r = (color >> 11 & 31) as i32;
+    let g = (color >> 5 & 63) as i32;
+    let b = (color & 31) as i32;
+
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+fn lerp(a: [i32; 3], b: [i32; 3], num: i32, den: i32) -> [i32; 3] {
+    let mut result = [0; 3];
+
+    for c in 0..3 {
+        result[c] = (a[c] * (den - num) + b[c] * num) / den;
+    }
+
+    result
+}
+
+fn nearest(palette: &[[i32; 3]], rgb: [i32; 3]) -> u32 {
+    (0..palette.len())
+        .min_by_key(|&i| distance_squared(palette[i], rgb))
+        .unwrap() as u32
+}
+
+fn encode_alpha(block: &Block, out: &mut Vec<u8>) {
+    let min = block.iter().map(|pixel| pixel[3]).min().unwrap();
+    let max = block.iter().map(|pixel| pixel[3]).max().unwrap();
+
+    let mut indices = 0u64;
+
+    // With the first endpoint greater, the other 6 values are interpolated between the two
+    if max > min {
+        let (a0, a1) = (max as i32, min as i32);
+
+        let mut palette = [a0, a1, 0, 0, 0, 0, 0, 0];
+
+        for i in 1..7 {
+            palette[i + 1] = (a0 * (7 - i as i32) + a1 * i as i32) / 7;
+        }
+
+        for (i, pixel) in block.iter().enumerate() {
+            let alpha = pixel[3] as i32;
+            let index = (0..8).min_by_key(|&j| (palette[j] - alpha).abs()).unwrap() as u64;
+
+            indices |= index << (i * 3);
+        }
+    }
+
+    out.push(max);
+    out.push(min);
+    out.extend_from_slice(&indices.to_le_bytes()[..6]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode_bc1, encode_bc3, from_565};
+
+    fn decode_bc1(bytes: &[u8]) -> Vec<[i32; 3]> {
+        let c0 = from_565(u16::from_le_bytes([bytes[0], bytes[1]]));
+        let c1 = from_565(u16::from_le_bytes([bytes[2], bytes[3]]));
+        let indices = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+        let palette = [c0, c1, super::lerp(c0, c1, 1, 3), super::lerp(c0, c1, 2, 3)];
+
+        (0..16).map(|i| palette[(indices >> (i * 2) & 3) as usize]).collect()
+    }
+
+    #[test]
+    fn encodes_gradient() {
+        let mut block = [[0; 4]; 16];
+
+        // Exactly the endpoints and the two colours in between them
+        for (i, pixel) in block.iter_mut().enumerate() {
+            let v = (i % 4 * 85) as u8;
+            *pixel = [v, v, v, 255];
+        }
+
+        let mut bytes = Vec::new();
+        encode_bc1(&block, &mut bytes);
+
+        assert_eq!(bytes.len(), 8);
+
+        for (decoded, pixel) in decode_bc1(&bytes).iter().zip(block.iter()) {
+            for c in 0..3 {
+                assert!((decoded[c] - pixel[c] as i32).abs() <= 8, "{:?} vs {:?}", decoded, pixel);
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_alpha_endpoints() {
+        let mut block = [[0, 0, 0, 255]; 16];
+        block[5][3] = 0;
+
+        let mut bytes = Vec::new();
+        encode_bc3(&block, &mut bytes);
+
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(&bytes[..2], &[255, 0]);
+
+        let indices = u64::from_le_bytes([bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7], 0, 0]);
+        // Only the transparent pixel uses the second endpoint
+        for i in 0..16 {
+            assert_eq!(indices >> (i * 3) & 7, if i == 5 { 1 } else { 0 });
+        }
+    }
+}
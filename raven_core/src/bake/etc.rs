@@ -0,0 +1,374 @@
+use super::{distance_squared, Block};
+
+// Intensity modifiers of the colour blocks, each index also has the negated values
+const COLOR_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const ALPHA_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+// Has a 0 modifier, so it reproduces flat blocks exactly
+const FLAT_ALPHA_TABLE: usize = 13;
+const FLAT_ALPHA_INDEX: u64 = 4;
+
+/// Encodes a block as ETC2 RGB, ignoring alpha.
+///
+/// Only the individual and differential modes, the ones ETC2 inherited from ETC1, are used. The differential colours
+/// never overflow, so decoders never mistake them for one of the modes ETC2 added.
+pub(super) fn encode_etc2_rgb(block: &Block, out: &mut Vec<u8>) {
+    let mut best = (i64::MAX, 0u64);
+
+    for flip in [false, true] {
+        let (first, second) = split(block, flip);
+        let (avg1, avg2) = (average(&first), average(&second));
+
+        let q1 = avg1.map(|c| quantize(c, 31));
+        let q2 = avg2.map(|c| quantize(c, 31));
+        let delta = [q2[0] - q1[0], q2[1] - q1[1], q2[2] - q1[2]];
+
+        let mut candidates = vec![encode_individual(&first, &second, avg1, avg2, flip)];
+
+        if delta.iter().all(|d| (-4..=3).contains(d)) {
+            candidates.push(encode_differential(&first, &second, q1, delta, flip));
+        }
+
+        for candidate in candidates {
+            if candidate.0 < best.0 {
+                best = candidate;
+            }
+        }
+    }
+
+    out.extend_from_slice(&best.1.to_be_bytes());
+}
+
+/// Encodes a block as ETC2 RGBA, EAC alpha first and then the colours the same way as ETC2 RGB.
+pub(super) fn encode_etc2_rgba(block: &Block, out: &mut Vec<u8>) {
+    encode_alpha(block, out);
+    encode_etc2_rgb(block, out);
+}
+
+// Pixels of a sub-block along with their position in the block, in the column by column order ETC uses
+type SubBlock = [(usize, [i32; 3]); 8];
+
+/// Splits the block into two halves, left and right, or top and bottom if flipped.
+fn split(block: &Block, flip: bool) -> (SubBlock, SubBlock) {
+    let mut first = [(0, [0; 3]); 8];
+    let mut second = [(0, [0; 3]); 8];
+    let (mut n_first, mut n_second) = (0, 0);
+
+    for x in 0..4 {
+        for y in 0..4 {
+            let pixel = block[y * 4 + x];
+            let entry = (x * 4 + y, [pixel[0] as i32, pixel[1] as i32, pixel[2] as i32]);
+
+            if (if flip { y } else { x }) < 2 {
+                first[n_first] = entry;
+                n_first += 1;
+            } else {
+                second[n_second] = entry;
+                n_second += 1;
+            }
+        }
+    }
+
+    (first, second)
+}
+
+fn average(pixels: &SubBlock) -> [f32; 3] {
+    let mut sum = [0.0; 3];
+
+    for (_, rgb) in pixels {
+        for c in 0..3 {
+            sum[c] += rgb[c] as f32 / 8.0;
+        }
+    }
+
+    sum
+}
+
+fn quantize(value: f32, max: i32) -> i32 {
+    ((value / 255.0 * max as f32).round() as i32).clamp(0, max)
+}
+
+fn encode_individual(first: &SubBlock, second: &SubBlock, avg1: [f32; 3], avg2: [f32; 3], flip: bool) -> (i64, u64) {
+    let q1 = avg1.map(|c| quantize(c, 15));
+    let q2 = avg2.map(|c| quantize(c, 15));
+
+    let expand = |q: [i32; 3]| q.map(|c| c << 4 | c);
+
+    let (error1, table1, pixels1) = fit_sub_block(first, expand(q1));
+    let (error2, table2, pixels2) = fit_sub_block(second, expand(q2));
+
+    let mut bits = 0u64;
+
+    for c in 0..3 {
+        let shift = 60 - c * 8;
+        bits |= (q1[c] as u64) << shift;
+        bits |= (q2[c] as u64) << (shift - 4);
+    }
+
+    bits |= header(table1, table2, false, flip) | pixels1 | pixels2;
+
+    (error1 + error2, bits)
+}
+
+fn encode_differential(first: &SubBlock, second: &SubBlock, q1: [i32; 3], delta: [i32; 3], flip: bool) -> (i64, u64) {
+    let expand = |q: [i32; 3]| q.map(|c| c << 3 | c >> 2);
+    let q2 = [q1[0] + delta[0], q1[1] + delta[1], q1[2] + delta[2]];
+
+    let (error1, table1, pixels1) = fit_sub_block(first, expand(q1));
+    let (error2, table2, pixels2) = fit_sub_block(second, expand(q2));
+
+    let mut bits = 0u64;
+
+    for c in 0..3 {
+        let shift = 59 - c * 8;
+        bits |= (q1[c] as u64) << shift;
+        // 3 bit two's complement
+        bits |= ((delta[c] & 7) as u64) << (shift - 3);
+    }
+
+    bits |= header(table1, table2, true, flip) | pixels1 | pixels2;
+
+    (error1 + error2, bits)
+}
+
+fn header(table1: usize, table2: usize, differential: bool, flip: bool) -> u64 {
+    (table1 as u64) << 37 | (table2 as u64) << 34 | (differential as u64) << 33 | (flip as u64) << 32
+}
+
+/// Finds the modifier table that best fits the pixels around `base`, returning the error, the table and the bits of
+/// the pixel indices.
+fn fit_sub_block(pixels: &SubBlock, base: [i32; 3]) -> (i64, usize, u64) {
+    let mut best = (i64::MAX, 0, 0);
+
+    for (table, [small, large]) in COLOR_MODIFIERS.iter().enumerate() {
+        // In the order of the index values, 0b00 to 0b11
+        let modifiers = [*small, *large, -small, -large];
+
+        let mut error = 0i64;
+        let mut bits = 0u64;
+
+        for (position, rgb) in pixels {
+            let (index, index_error) = modifiers.iter().enumerate()
+                .map(|(index, modifier)| {
+                    let color = base.map(|c| (c + modifier).clamp(0, 255));
+                    (index as u64, distance_squared(color, *rgb))
+                })
+                .min_by_key(|(_, error)| *error)
+                .unwrap();
+
+            error += index_error as i64;
+            // Most significant bits of the indices in the upper half, least significant ones in the lower half
+            bits |= (index >> 1) << (position + 16) | (index & 1) << position;
+        }
+
+        if error < best.0 {
+            best = (error, table, bits);
+        }
+    }
+
+    best
+}
+
+fn encode_alpha(block: &Block, out: &mut Vec<u8>) {
+    // Column by column, like the colour indices
+    let alphas: Vec<i32> = (0..16).map(|i| block[(i % 4) * 4 + i / 4][3] as i32).collect();
+
+    let min = *alphas.iter().min().unwrap();
+    let max = *alphas.iter().max().unwrap();
+
+    let mut best = (i64::MAX, 0u64);
+
+    if min == max {
+        best.1 = (max as u64) << 56 | 1 << 52 | (FLAT_ALPHA_TABLE as u64) << 48;
+
+        for i in 0..16 {
+            best.1 |= FLAT_ALPHA_INDEX << (45 - i * 3);
+        }
+    } else {
+        for (table, modifiers) in ALPHA_MODIFIERS.iter().enumerate() {
+            let table_min = modifiers[3];
+            let table_max = modifiers[7];
+            let spread = (max - min) as f32 / (table_max - table_min) as f32;
+
+            // Only the multipliers that stretch the table close to the range of the block are worth trying
+            let guess = spread.round() as i32;
+
+            for multiplier in (guess - 1).max(1)..=(guess + 1).min(15) {
+                let center = (min + max) as f32 / 2.0 - (table_min + table_max) as f32 * multiplier as f32 / 2.0;
+                let base = (center.round() as i32).clamp(0, 255);
+
+                let mut error = 0i64;
+                let mut bits = (base as u64) << 56 | (multiplier as u64) << 52 | (table as u64) << 48;
+
+                for (i, alpha) in alphas.iter().enumerate() {
+                    let (index, index_error) = modifiers.iter().enumerate()
+                        .map(|(index, modifier)| {
+                            let value = (base + modifier * multiplier).clamp(0, 255);
+                            (index as u64, (value - alpha).pow(2))
+                        })
+                        .min_by_key(|(_, error)| *error)
+                        .unwrap();
+
+                    error += index_error as i64;
+                    bits |= index << (45 - i * 3);
+                }
+
+                if error < best.0 {
+                    best = (error, bits);
+                }
+            }
+        }
+    }
+
+    out.extend_from_slice(&best.1.to_be_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use super::{encode_etc2_rgb, encode_etc2_rgba, ALPHA_MODIFIERS, COLOR_MODIFIERS};
+
+    // Decodes blocks in individual or differential mode, the only ones the encoder produces
+    fn decode_rgb(bytes: &[u8]) -> Vec<[i32; 3]> {
+        let bits = u64::from_be_bytes(bytes.try_into().unwrap());
+        let differential = bits >> 33 & 1 == 1;
+        let flip = bits >> 32 & 1 == 1;
+
+        let (base1, base2) = if differential {
+            let mut base1 = [0; 3];
+            let mut base2 = [0; 3];
+
+            for c in 0..3 {
+                let shift = 59 - c * 8;
+                let q1 = (bits >> shift & 31) as i32;
+                let delta = ((bits >> (shift - 3) & 7) as i32) << 29 >> 29;
+                let q2 = q1 + delta;
+
+                base1[c] = q1 << 3 | q1 >> 2;
+                base2[c] = q2 << 3 | q2 >> 2;
+            }
+
+            (base1, base2)
+        } else {
+            let mut base1 = [0; 3];
+            let mut base2 = [0; 3];
+
+            for c in 0..3 {
+                let shift = 60 - c * 8;
+                let q1 = (bits >> shift & 15) as i32;
+                let q2 = (bits >> (shift - 4) & 15) as i32;
+
+                base1[c] = q1 << 4 | q1;
+                base2[c] = q2 << 4 | q2;
+            }
+
+            (base1, base2)
+        };
+
+        let tables = [(bits >> 37 & 7) as usize, (bits >> 34 & 7) as usize];
+
+        (0..16).map(|i| {
+            let (x, y) = (i % 4, i / 4);
+            let position = x * 4 + y;
+            let second = if flip { y >= 2 } else { x >= 2 };
+
+            let index = (bits >> (position + 16) & 1) << 1 | bits >> position & 1;
+            let [small, large] = COLOR_MODIFIERS[tables[second as usize]];
+            let modifier = [small, large, -small, -large][index as usize];
+
+            let base = if second { base2 } else { base1 };
+            base.map(|c| (c + modifier).clamp(0, 255))
+        }).collect()
+    }
+
+    fn decode_alpha(bytes: &[u8]) -> Vec<i32> {
+        let bits = u64::from_be_bytes(bytes.try_into().unwrap());
+        let base = (bits >> 56) as i32;
+        let multiplier = (bits >> 52 & 15) as i32;
+        let modifiers = ALPHA_MODIFIERS[(bits >> 48 & 15) as usize];
+
+        (0..16).map(|i| {
+            let (x, y) = (i % 4, i / 4);
+            let index = bits >> (45 - (x * 4 + y) * 3) & 7;
+            (base + modifiers[index as usize] * multiplier).clamp(0, 255)
+        }).collect()
+    }
+
+    #[test]
+    fn encodes_two_colors() {
+        let mut block = [[200, 40, 40, 255]; 16];
+
+        for y in 2..4 {
+            for x in 0..4 {
+                block[y * 4 + x] = [30, 30, 220, 255];
+            }
+        }
+
+        let mut bytes = Vec::new();
+        encode_etc2_rgb(&block, &mut bytes);
+
+        assert_eq!(bytes.len(), 8);
+
+        for (decoded, pixel) in decode_rgb(&bytes).iter().zip(block.iter()) {
+            for c in 0..3 {
+                assert!((decoded[c] - pixel[c] as i32).abs() <= 16, "{:?} vs {:?}", decoded, pixel);
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_alpha() {
+        let mut block = [[0, 0, 0, 255]; 16];
+
+        for (i, pixel) in block.iter_mut().enumerate() {
+            pixel[3] = (i * 16) as u8;
+        }
+
+        let mut bytes = Vec::new();
+        encode_etc2_rgba(&block, &mut bytes);
+
+        assert_eq!(bytes.len(), 16);
+
+        for (decoded, pixel) in decode_alpha(&bytes[..8]).iter().zip(block.iter()) {
+            assert!((decoded - pixel[3] as i32).abs() <= 24, "{} vs {}", decoded, pixel[3]);
+        }
+    }
+
+    #[test]
+    fn encodes_flat_alpha_exactly() {
+        let block = [[0, 0, 0, 77]; 16];
+
+        let mut bytes = Vec::new();
+        encode_etc2_rgba(&block, &mut bytes);
+
+        assert!(decode_alpha(&bytes[..8]).iter().all(|alpha| *alpha == 77));
+    }
+}
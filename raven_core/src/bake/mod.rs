@@ -0,0 +1,295 @@
+use std::f32::consts::FRAC_PI_2;
+
+use glam::{Mat4, Quat, Vec3};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::resource::{ColorSpace, Sampler, Texture, TextureFormat};
+
+mod bc;
+mod etc;
+mod simplify;
+
+pub use simplify::{generate_lods, simplify};
+
+/// How an image is turned into a `Texture` when it's imported.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TextureSettings {
+    pub color_space: ColorSpace,
+    pub sampler: Sampler,
+    /// Images bigger than this, on either side, are scaled down to fit while keeping their aspect ratio
+    pub max_size: Option<u32>,
+    /// Whether to compute mipmaps now rather than when the texture is loaded. Compressed textures always have them
+    /// baked, if the sampler uses mipmaps, since the GPU can't generate them
+    pub bake_mips: bool,
+    pub compression: Compression,
+    /// Whether to keep the pixels in memory after uploading them to the GPU
+    pub readable: bool,
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        TextureSettings {
+            color_space: ColorSpace::default(),
+            sampler: Sampler::default(),
+            max_size: None,
+            bake_mips: false,
+            compression: Compression::None,
+            readable: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    None,
+    /// BC1 for opaque images and BC3 for ones with transparency, supported by desktop GPUs
+    Bc,
+    /// ETC2, with EAC alpha for images with transparency, supported by mobile GPUs
+    Etc2,
+}
+
+/// How an external file is imported, stored in its meta file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum ImportSettings {
+    Texture(TextureSettings),
+    Scene(SceneSettings),
+}
+
+/// How a 3D scene, such as an FBX or OBJ file, is turned into a `Scene` and its meshes and materials when it's imported.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SceneSettings {
+    /// Applied to the root of the scene. Models are often authored in centimeters
+    pub scale: f32,
+    /// The axis pointing up in the file. Scenes are rotated so that it becomes Y
+    pub up_axis: UpAxis,
+    pub normals: Normals,
+    /// Computes tangents and bitangents for the meshes with UVs, for normal mapping
+    pub generate_tangents: bool,
+    /// Merges the vertices that are the same, so that they're shared by the triangles using them
+    pub join_identical_vertices: bool,
+    /// Merges small meshes with the same material and reorders vertices to make better use of the GPU cache
+    pub optimize_meshes: bool,
+    /// How many LODs to generate for each mesh, each with half the triangles of the one before. Meshes that come with
+    /// their own LODs keep them instead
+    pub lod_levels: u32,
+}
+
+impl Default for SceneSettings {
+    fn default() -> Self {
+        SceneSettings {
+            scale: 0.01,
+            up_axis: UpAxis::Y,
+            normals: Normals::Import,
+            generate_tangents: false,
+            join_identical_vertices: false,
+            optimize_meshes: false,
+            lod_levels: 0,
+        }
+    }
+}
+
+impl SceneSettings {
+    /// The transform applied to the root of the imported scene.
+    pub fn root_transform(&self) -> Mat4 {
+        let rotation = match self.up_axis {
+            UpAxis::Y => Quat::IDENTITY,
+            // Z up, Y forward becomes Y up, -Z forward
+            UpAxis::Z => Quat::from_rotation_x(-FRAC_PI_2),
+        };
+
+        Mat4::from_scale_rotation_translation(Vec3::ONE * self.scale, rotation, Vec3::ZERO)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum UpAxis {
+    Y,
+    Z,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Normals {
+    /// The ones in the file, with flat normals computed for the meshes that have none
+    Import,
+    /// Computed again, averaged between the faces sharing a vertex
+    Smooth,
+    /// Computed again, perpendicular to each face
+    Flat,
+}
+
+/// Bakes an image into a texture according to `settings`.
+pub fn bake_texture(image: &RgbaImage, settings: &TextureSettings) -> Texture {
+    let image = fit(image, settings.max_size);
+    let has_alpha = image.pixels().any(|pixel| pixel[3] < 255);
+
+    let format = match (settings.compression, has_alpha) {
+        (Compression::None, _) => TextureFormat::Rgba8,
+        (Compression::Bc, false) => TextureFormat::Bc1,
+        (Compression::Bc, true) => TextureFormat::Bc3,
+        (Compression::Etc2, false) => TextureFormat::Etc2Rgb8,
+        (Compression::Etc2, true) => TextureFormat::Etc2Rgba8,
+    };
+
+    let bake_mips = settings.sampler.mipmaps && (settings.bake_mips || format.is_compressed());
+
+    let mut mips = Vec::new();
+
+    if bake_mips {
+        let mut level = image.clone();
+
+        while level.width() > 1 || level.height() > 1 {
+            let width = (level.width() / 2).max(1);
+            let height = (level.height() / 2).max(1);
+
+            level = imageops::resize(&level, width, height, FilterType::Triangle);
+            mips.push(encode(&level, format));
+        }
+    }
+
+    let mut texture = Texture::new(encode(&image, format), [image.width(), image.height()]);
+    texture.format = format;
+    texture.mips = mips;
+    texture.color_space = settings.color_space;
+    texture.sampler = settings.sampler;
+    texture.readable = settings.readable;
+
+    texture
+}
+
+fn fit(image: &RgbaImage, max_size: Option<u32>) -> RgbaImage {
+    let (width, height) = image.dimensions();
+
+    match max_size {
+        Some(max_size) if width > max_size || height > max_size => {
+            let scale = max_size as f32 / width.max(height) as f32;
+            let width = ((width as f32 * scale).round() as u32).clamp(1, max_size);
+            let height = ((height as f32 * scale).round() as u32).clamp(1, max_size);
+
+            imageops::resize(image, width, height, FilterType::Lanczos3)
+        }
+        _ => image.clone(),
+    }
+}
+
+fn encode(image: &RgbaImage, format: TextureFormat) -> Vec<u8> {
+    let encode_block: fn(&Block, &mut Vec<u8>) = match format {
+        TextureFormat::Rgba8 => return image.as_raw().clone(),
+        TextureFormat::Bc1 => bc::encode_bc1,
+        TextureFormat::Bc3 => bc::encode_bc3,
+        TextureFormat::Etc2Rgb8 => etc::encode_etc2_rgb,
+        TextureFormat::Etc2Rgba8 => etc::encode_etc2_rgba,
+    };
+
+    let mut bytes = Vec::with_capacity(format.level_bytes([image.width(), image.height()]));
+
+    for block_y in (0..image.height()).step_by(4) {
+        for block_x in (0..image.width()).step_by(4) {
+            encode_block(&get_block(image, block_x, block_y), &mut bytes);
+        }
+    }
+
+    bytes
+}
+
+/// The 16 pixels of a 4x4 block, row by row.
+type Block = [[u8; 4]; 16];
+
+fn get_block(image: &RgbaImage, block_x: u32, block_y: u32) -> Block {
+    let mut block = [[0; 4]; 16];
+
+    for y in 0..4 {
+        for x in 0..4 {
+            // Blocks that go past the edge of the image repeat its last row and column
+            let pixel = image.get_pixel((block_x + x).min(image.width() - 1), (block_y + y).min(image.height() - 1));
+            block[(y * 4 + x) as usize] = pixel.0;
+        }
+    }
+
+    block
+}
+
+fn distance_squared(a: [i32; 3], b: [i32; 3]) -> i32 {
+    (a[0] - b[0]).pow(2) + (a[1] - b[1]).pow(2) + (a[2] - b[2]).pow(2)
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+    use image::{Rgba, RgbaImage};
+
+    use crate::resource::{Sampler, TextureFormat};
+
+    use super::{bake_texture, Compression, SceneSettings, TextureSettings, UpAxis};
+
+    #[test]
+    fn converts_z_up_scenes() {
+        let settings = SceneSettings {
+            scale: 2.0,
+            up_axis: UpAxis::Z,
+            ..SceneSettings::default()
+        };
+
+        let up = settings.root_transform().transform_point3(Vec3::Z);
+        assert!(up.abs_diff_eq(Vec3::new(0.0, 2.0, 0.0), 1e-6));
+
+        let forward = settings.root_transform().transform_point3(Vec3::Y);
+        assert!(forward.abs_diff_eq(Vec3::new(0.0, 0.0, -2.0), 1e-6));
+    }
+
+    #[test]
+    fn scales_down_to_max_size() {
+        let image = RgbaImage::new(400, 100);
+
+        let texture = bake_texture(&image, &TextureSettings {
+            max_size: Some(200),
+            ..TextureSettings::default()
+        });
+
+        assert_eq!(texture.size, [200, 50]);
+        assert_eq!(texture.raw.len(), 200 * 50 * 4);
+    }
+
+    #[test]
+    fn bakes_mip_chain() {
+        let image = RgbaImage::from_pixel(8, 2, Rgba([255, 0, 0, 255]));
+
+        let texture = bake_texture(&image, &TextureSettings {
+            bake_mips: true,
+            sampler: Sampler { mipmaps: true, ..Sampler::default() },
+            ..TextureSettings::default()
+        });
+
+        let sizes: Vec<_> = texture.mips.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![4 * 1 * 4, 2 * 1 * 4, 1 * 1 * 4]);
+    }
+
+    #[test]
+    fn picks_compressed_format_by_alpha() {
+        let opaque = RgbaImage::from_pixel(6, 6, Rgba([10, 20, 30, 255]));
+        let transparent = RgbaImage::from_pixel(6, 6, Rgba([10, 20, 30, 128]));
+
+        let sampler = Sampler { mipmaps: true, ..Sampler::default() };
+        let bc = TextureSettings { compression: Compression::Bc, sampler, ..TextureSettings::default() };
+        let etc2 = TextureSettings { compression: Compression::Etc2, sampler, ..TextureSettings::default() };
+
+        for (image, settings, format) in [
+            (&opaque, &bc, TextureFormat::Bc1),
+            (&transparent, &bc, TextureFormat::Bc3),
+            (&opaque, &etc2, TextureFormat::Etc2Rgb8),
+            (&transparent, &etc2, TextureFormat::Etc2Rgba8),
+        ] {
+            let texture = bake_texture(image, settings);
+
+            assert_eq!(texture.format, format);
+            assert_eq!(texture.raw.len(), format.level_bytes([6, 6]));
+            // Compressed textures always get their mipmaps baked, 3x3, 1x1
+            assert_eq!(texture.mips.len(), 2);
+            assert_eq!(texture.mips[1].len(), format.level_bytes([1, 1]));
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use glam::DVec3;
+
+use crate::resource::Mesh;
+
+/// How much more moving a border costs than moving a surface by the same amount, so that the outline of open meshes
+/// is kept.
+const BORDER_WEIGHT: f64 = 1000.0;
+
+/// How thin a collapse can leave a triangle, as twice its area over its longest edge squared. Thinner ones are slivers
+/// whose facing is down to rounding.
+const MIN_THICKNESS: f64 = 1e-4;
+
+/// Simplifies the mesh down to about `ratio` of its triangles, collapsing first the edges whose removal changes its
+/// shape the least (Garland and Heckbert's quadric error metric).
+///
+/// Vertices are never moved, one end of each collapsed edge takes the place of the other, so that every attribute of
+/// the vertices that are left stays valid. Vertices at the same position, split by UV seams or hard edges, are collapsed
+/// together.
+pub fn simplify(mesh: &Mesh, ratio: f32) -> Mesh {
+    let mut simplifier = Simplifier::new(mesh);
+
+    // Rounded up, so that a single triangle isn't simplified away
+    let target = (mesh.indices.len() / 3) as f32 * ratio.clamp(0.0, 1.0);
+    simplifier.run(target.ceil() as usize);
+
+    simplifier.build(mesh)
+}
+
+/// Simplifies the mesh again and again, halving its triangles every time, to make the given number of LODs.
+pub fn generate_lods(mesh: &Mesh, levels: u32) -> Vec<Mesh> {
+    let mut lods: Vec<Mesh> = Vec::new();
+
+    for _ in 0..levels {
+        let lod = simplify(lods.last().unwrap_or(mesh), 0.5);
+        lods.push(lod);
+    }
+
+    lods
+}
+
+/// The squared distance from a set of planes, as a symmetric 4x4 matrix of which only the upper triangle is kept.
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    /// Of the plane through `point` perpendicular to `normal`, which has to be normalized, scaled by `weight`.
+    fn plane(normal: DVec3, point: DVec3, weight: f64) -> Quadric {
+        let [a, b, c] = normal.to_array();
+        let d = -normal.dot(point);
+
+        Quadric([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d]).scale(weight)
+    }
+
+    fn scale(self, weight: f64) -> Quadric {
+        Quadric(self.0.map(|value| value * weight))
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.0.iter_mut().zip(&other.0) {
+            *value += other;
+        }
+    }
+
+    fn error(&self, point: DVec3) -> f64 {
+        let [a2, ab, ac, ad, b2, bc, bd, c2, cd, d2] = self.0;
+        let [x, y, z] = point.to_array();
+
+        a2 * x * x + 2.0 * ab * x * y + 2.0 * ac * x * z + 2.0 * ad * x
+            + b2 * y * y + 2.0 * bc * y * z + 2.0 * bd * y
+            + c2 * z * z + 2.0 * cd * z
+            + d2
+    }
+}
+
+/// Moving the position `from` onto `to`, when both were last changed at the given stamps.
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    stamps: (u32, u32),
+}
+
+// Ordered so that the cheapest collapse is at the top of the heap
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+struct Simplifier {
+    /// Position of every vertex, indexing the ones below
+    position_of: Vec<usize>,
+    positions: Vec<DVec3>,
+    /// Vertices at each position
+    vertices_at: Vec<Vec<u32>>,
+    /// Triangles around each position, some of which may have been removed since
+    triangles_at: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    /// Bumped whenever a position changes, to tell apart the collapses in the heap that are out of date
+    stamps: Vec<u32>,
+    removed: Vec<bool>,
+
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    alive_count: usize,
+
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Simplifier {
+    fn new(mesh: &Mesh) -> Simplifier {
+        let mut ids = HashMap::new();
+        let mut positions = Vec::new();
+        let mut vertices_at: Vec<Vec<u32>> = Vec::new();
+
+        let position_of: Vec<usize> = mesh
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(i, vertex)| {
+                let id = *ids.entry(vertex.position.to_array().map(f32::to_bits)).or_insert_with(|| {
+                    positions.push(DVec3::new(vertex.position.x as f64, vertex.position.y as f64, vertex.position.z as f64));
+                    vertices_at.push(Vec::new());
+                    positions.len() - 1
+                });
+
+                vertices_at[id].push(i as u32);
+                id
+            })
+            .collect();
+
+        let triangles: Vec<[u32; 3]> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+
+        let mut simplifier = Simplifier {
+            stamps: vec![0; positions.len()],
+            removed: vec![false; positions.len()],
+            triangles_at: vec![Vec::new(); positions.len()],
+            quadrics: vec![Quadric::default(); positions.len()],
+            position_of,
+            positions,
+            vertices_at,
+
+            alive: vec![true; triangles.len()],
+            alive_count: triangles.len(),
+            triangles,
+
+            heap: BinaryHeap::new(),
+        };
+
+        simplifier.init();
+        simplifier
+    }
+
+    fn init(&mut self) {
+        // How many triangles share each edge, the ones with a single one are on a border
+        let mut edges: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+
+        for t in 0..self.triangles.len() {
+            let [a, b, c] = self.corners(t);
+
+            if a == b || b == c || c == a {
+                self.alive[t] = false;
+                self.alive_count -= 1;
+                continue;
+            }
+
+            let (normal, area) = self.normal(t);
+
+            // Larger triangles weigh more, they show more of the surface
+            let quadric = Quadric::plane(normal, self.positions[a], area);
+
+            for p in [a, b, c] {
+                self.quadrics[p].add(&quadric);
+                self.triangles_at[p].push(t);
+            }
+
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                edges.entry((from.min(to), from.max(to))).or_insert((0, t)).0 += 1;
+            }
+        }
+
+        for (&(a, b), &(count, t)) in &edges {
+            if count != 1 {
+                continue;
+            }
+
+            // A plane along the border, perpendicular to the triangle, keeps it from moving sideways
+            let (normal, _) = self.normal(t);
+            let edge = self.positions[b] - self.positions[a];
+            let border_normal = edge.cross(normal).normalize_or_zero();
+
+            let quadric = Quadric::plane(border_normal, self.positions[a], BORDER_WEIGHT * edge.length_squared());
+
+            self.quadrics[a].add(&quadric);
+            self.quadrics[b].add(&quadric);
+        }
+
+        for &(a, b) in edges.keys() {
+            self.push(a, b);
+        }
+    }
+
+    /// Positions of the corners of a triangle.
+    fn corners(&self, t: usize) -> [usize; 3] {
+        self.triangles[t].map(|vertex| self.position_of[vertex as usize])
+    }
+
+    /// Unit normal and area of a triangle.
+    fn normal(&self, t: usize) -> (DVec3, f64) {
+        let [a, b, c] = self.corners(t).map(|p| self.positions[p]);
+        let cross = (b - a).cross(c - a);
+
+        (cross.normalize_or_zero(), cross.length() / 2.0)
+    }
+
+    /// Queues the cheapest way of collapsing the edge between two positions.
+    fn push(&mut self, a: usize, b: usize) {
+        let mut quadric = self.quadrics[a];
+        quadric.add(&self.quadrics[b]);
+
+        let (from, to) = if quadric.error(self.positions[a]) <= quadric.error(self.positions[b]) { (b, a) } else { (a, b) };
+
+        self.heap.push(Collapse {
+            cost: quadric.error(self.positions[to]),
+            from,
+            to,
+            stamps: (self.stamps[from], self.stamps[to]),
+        });
+    }
+
+    fn run(&mut self, target: usize) {
+        while self.alive_count > target {
+            let collapse = match self.heap.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+
+            let Collapse { from, to, .. } = collapse;
+
+            let up_to_date = !self.removed[from]
+                && !self.removed[to]
+                && collapse.stamps == (self.stamps[from], self.stamps[to]);
+
+            if up_to_date && !self.flips(from, to) {
+                self.collapse(from, to);
+            }
+        }
+    }
+
+    /// Whether moving `from` onto `to` would turn any of the triangles that are left around it upside down, or squash
+    /// one of them flat.
+    fn flips(&self, from: usize, to: usize) -> bool {
+        self.triangles_at[from].iter().any(|&t| {
+            let corners = self.corners(t);
+
+            if !self.alive[t] || corners.contains(&to) {
+                return false;
+            }
+
+            let before = self.normal(t).0;
+
+            let moved = corners.map(|p| if p == from { self.positions[to] } else { self.positions[p] });
+            let after = (moved[1] - moved[0]).cross(moved[2] - moved[0]);
+
+            let longest = [(0, 1), (1, 2), (2, 0)]
+                .iter()
+                .map(|&(i, j)| moved[i].distance_squared(moved[j]))
+                .fold(0.0, f64::max);
+
+            before.dot(after) <= MIN_THICKNESS * longest
+        })
+    }
+
+    fn collapse(&mut self, from: usize, to: usize) {
+        // Each vertex at `from` is replaced by one at `to` it shares a triangle with, so that attributes continue across
+        // the surface. The ones that share none, across a seam, take any
+        let mut replacements = HashMap::new();
+
+        for &t in &self.triangles_at[from] {
+            if !self.alive[t] {
+                continue;
+            }
+
+            let triangle = self.triangles[t];
+            let corners = self.corners(t);
+
+            if let (Some(i), Some(j)) = (corners.iter().position(|&p| p == from), corners.iter().position(|&p| p == to)) {
+                replacements.entry(triangle[i]).or_insert(triangle[j]);
+            }
+        }
+
+        let fallback = self.vertices_at[to][0];
+
+        for t in std::mem::take(&mut self.triangles_at[from]) {
+            if !self.alive[t] {
+                continue;
+            }
+
+            if self.corners(t).contains(&to) {
+                self.alive[t] = false;
+                self.alive_count -= 1;
+                continue;
+            }
+
+            for vertex in &mut self.triangles[t] {
+                if self.position_of[*vertex as usize] == from {
+                    *vertex = *replacements.get(vertex).unwrap_or(&fallback);
+                }
+            }
+
+            self.triangles_at[to].push(t);
+        }
+
+        let quadric = self.quadrics[from];
+        self.quadrics[to].add(&quadric);
+
+        self.removed[from] = true;
+        self.stamps[to] += 1;
+
+        // Every edge around `to` changed cost
+        let alive = &self.alive;
+        self.triangles_at[to].retain(|&t| alive[t]);
+
+        let mut neighbours: Vec<usize> = self.triangles_at[to]
+            .iter()
+            .flat_map(|&t| self.corners(t))
+            .filter(|&p| p != to)
+            .collect();
+
+        neighbours.sort_unstable();
+        neighbours.dedup();
+
+        for neighbour in neighbours {
+            self.push(to, neighbour);
+        }
+    }
+
+    /// The mesh made of the triangles that are left, with only the vertices they use.
+    fn build(&self, mesh: &Mesh) -> Mesh {
+        let mut new_index = HashMap::new();
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for (triangle, _) in self.triangles.iter().zip(&self.alive).filter(|(_, &alive)| alive) {
+            for &vertex in triangle {
+                let index = *new_index.entry(vertex).or_insert_with(|| {
+                    vertices.push(mesh.vertices[vertex as usize]);
+                    vertices.len() as u32 - 1
+                });
+
+                indices.push(index);
+            }
+        }
+
+        Mesh::with_layout(vertices, indices, mesh.layout)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{Vec2, Vec3};
+
+    use crate::resource::{Mesh, Vertex};
+
+    use super::simplify;
+
+    /// A flat square grid on the XY plane, with `n` quads per side and its vertices shared.
+    fn grid(n: u32) -> Mesh {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for y in 0..=n {
+            for x in 0..=n {
+                let uv = Vec2::new(x as f32, y as f32) / n as f32;
+                vertices.push(Vertex::new(uv.extend(0.0), Vec3::Z, uv));
+            }
+        }
+
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                indices.extend_from_slice(&[i, i + 1, i + n + 2, i, i + n + 2, i + n + 1]);
+            }
+        }
+
+        Mesh::new(vertices, indices)
+    }
+
+    #[test]
+    fn simplifies_keeping_the_shape() {
+        let mesh = grid(10);
+        let simplified = simplify(&mesh, 0.25);
+
+        assert!(simplified.indices.len() / 3 <= 50);
+        assert!(simplified.indices.len() >= 3);
+
+        // Still flat and facing the same way, with its outline in place
+        assert!(simplified.vertices.iter().all(|vertex| vertex.position.z == 0.0));
+        assert_eq!(simplified.aabb, mesh.aabb);
+
+        for triangle in simplified.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| simplified.vertices[triangle[i] as usize].position);
+            assert!((b - a).cross(c - a).z > 0.0);
+        }
+
+        // Every vertex that's left is one of the originals, with its attributes
+        for vertex in &simplified.vertices {
+            assert!(mesh.vertices.contains(vertex));
+        }
+    }
+
+    #[test]
+    fn keeps_everything_at_full_ratio() {
+        let mesh = grid(4);
+        let simplified = simplify(&mesh, 1.0);
+
+        assert_eq!(simplified.indices.len(), mesh.indices.len());
+    }
+}
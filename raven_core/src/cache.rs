@@ -0,0 +1,466 @@
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+
+use crate::error::{Error, Result};
+use crate::framebuffer::Framebuffer;
+use crate::loader::{self, AssetKind, Decoded, Done, Job, Loader};
+use crate::path;
+use crate::resource::{AnimationClip, KeyframeClip, Material, Script, Skeleton, Texture};
+use crate::vao::Vao;
+
+/// Assets loaded from the project, shared by every component that uses them and keyed by their `$/` path.
+///
+/// The cache only keeps weak references, the components own the assets. An asset is freed, from the GPU too, as soon as
+/// the last component using it goes away, for example when its scene is unloaded, and is loaded again from disk the
+/// next time it's needed.
+///
+/// When streaming, assets are read and decoded on background threads and uploaded to the GPU by `update`, a few per
+/// frame. Until then, asking for them returns `None`.
+pub(crate) struct AssetCache {
+    project_root: PathBuf,
+
+    vaos: HashMap<PathBuf, Slot<Vao>>,
+    textures: HashMap<PathBuf, Slot<Texture>>,
+    materials: HashMap<PathBuf, Slot<Material>>,
+    skeletons: HashMap<PathBuf, Slot<Skeleton>>,
+    clips: HashMap<PathBuf, Slot<AnimationClip>>,
+    keyframe_clips: HashMap<PathBuf, Slot<KeyframeClip>>,
+    scripts: HashMap<PathBuf, Slot<Script>>,
+
+    loader: Loader,
+    streaming: bool,
+    // Decoded on the loader's threads and waiting to be uploaded
+    uploads: VecDeque<Done>,
+    upload_budget: usize,
+    next_job_id: u64,
+    // Since the last purge
+    loaded: u32,
+}
+
+enum Slot<T> {
+    /// Being read and decoded, or waiting to be uploaded, by the job with the given id
+    Pending(u64),
+    /// Uploaded but not taken by any component yet, kept alive until one asks for it
+    Ready(Rc<T>),
+    Loaded(Weak<T>),
+    /// Asking for the asset again returns the same error, until the cache is purged
+    Failed(Error),
+}
+
+/// How far along the loading of the assets asked for since the last scene was loaded is.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LoadProgress {
+    /// Loaded, or that failed to load
+    pub loaded: u32,
+    pub pending: u32,
+}
+
+impl LoadProgress {
+    pub fn is_done(&self) -> bool {
+        self.pending == 0
+    }
+
+    /// Between 0 and 1, 1 when there's nothing left to load.
+    pub fn fraction(&self) -> f32 {
+        if self.is_done() {
+            1.0
+        } else {
+            self.loaded as f32 / (self.loaded + self.pending) as f32
+        }
+    }
+}
+
+/// GPU memory taken up by the assets currently in use, in bytes.
+///
+/// These are estimates computed from the size of the data that was uploaded, drivers may pad or compress it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemoryStats {
+    pub meshes: u32,
+    /// Vertex and index buffers of all the loaded meshes
+    pub mesh_bytes: usize,
+    pub textures: u32,
+    /// All the loaded textures, including their mipmaps
+    pub texture_bytes: usize,
+    pub render_targets: u32,
+    /// Colour and depth attachments of the framebuffers of all the render textures
+    pub render_target_bytes: usize,
+    /// Framebuffers cameras draw into in HDR and the ones used by post-processing effects
+    pub post_targets: u32,
+    pub post_target_bytes: usize,
+}
+
+impl MemoryStats {
+    pub fn total_bytes(&self) -> usize {
+        self.mesh_bytes + self.texture_bytes + self.render_target_bytes + self.post_target_bytes
+    }
+}
+
+// Enough for a couple of big textures per frame
+const DEFAULT_UPLOAD_BUDGET: usize = 8 * 1024 * 1024;
+
+impl AssetCache {
+    pub(crate) fn new<R: AsRef<Path>>(project_root: R) -> AssetCache {
+        AssetCache {
+            project_root: project_root.as_ref().to_owned(),
+
+            vaos: HashMap::new(),
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            skeletons: HashMap::new(),
+            clips: HashMap::new(),
+            keyframe_clips: HashMap::new(),
+            scripts: HashMap::new(),
+
+            loader: Loader::new(),
+            streaming: true,
+            uploads: VecDeque::new(),
+            upload_budget: DEFAULT_UPLOAD_BUDGET,
+            next_job_id: 0,
+            loaded: 0,
+        }
+    }
+
+    /// With streaming disabled, assets are loaded and uploaded as soon as they're asked for.
+    pub(crate) fn set_streaming(&mut self, streaming: bool) {
+        self.streaming = streaming;
+    }
+
+    pub(crate) fn set_upload_budget(&mut self, bytes: usize) {
+        self.upload_budget = bytes;
+    }
+
+    pub(crate) fn request_vao(&mut self, mesh_path: &Path) -> Result<Option<Rc<Vao>>> {
+        if needs_load(&self.vaos, mesh_path) {
+            self.start(AssetKind::Mesh, mesh_path);
+        }
+
+        take(&mut self.vaos, mesh_path)
+    }
+
+    pub(crate) fn request_texture(&mut self, tex_path: &Path) -> Result<Option<Rc<Texture>>> {
+        if needs_load(&self.textures, tex_path) {
+            self.start(AssetKind::Texture, tex_path);
+        }
+
+        take(&mut self.textures, tex_path)
+    }
+
+    pub(crate) fn request_material(&mut self, mat_path: &Path) -> Result<Option<Rc<Material>>> {
+        if needs_load(&self.materials, mat_path) {
+            self.start(AssetKind::Material, mat_path);
+        }
+
+        take(&mut self.materials, mat_path)
+    }
+
+    pub(crate) fn request_skeleton(&mut self, skeleton_path: &Path) -> Result<Option<Rc<Skeleton>>> {
+        if needs_load(&self.skeletons, skeleton_path) {
+            self.start(AssetKind::Skeleton, skeleton_path);
+        }
+
+        take(&mut self.skeletons, skeleton_path)
+    }
+
+    pub(crate) fn request_clip(&mut self, clip_path: &Path) -> Result<Option<Rc<AnimationClip>>> {
+        if needs_load(&self.clips, clip_path) {
+            self.start(AssetKind::Clip, clip_path);
+        }
+
+        take(&mut self.clips, clip_path)
+    }
+
+    pub(crate) fn request_keyframe_clip(&mut self, clip_path: &Path) -> Result<Option<Rc<KeyframeClip>>> {
+        if needs_load(&self.keyframe_clips, clip_path) {
+            self.start(AssetKind::KeyframeClip, clip_path);
+        }
+
+        take(&mut self.keyframe_clips, clip_path)
+    }
+
+    pub(crate) fn request_script(&mut self, script_path: &Path) -> Result<Option<Rc<Script>>> {
+        if needs_load(&self.scripts, script_path) {
+            self.start(AssetKind::Script, script_path);
+        }
+
+        take(&mut self.scripts, script_path)
+    }
+
+    fn start(&mut self, kind: AssetKind, path: &Path) {
+        let id = self.next_job_id;
+        self.next_job_id += 1;
+
+        match kind {
+            AssetKind::Mesh => {
+                self.vaos.insert(path.to_owned(), Slot::Pending(id));
+            }
+            AssetKind::Texture => {
+                self.textures.insert(path.to_owned(), Slot::Pending(id));
+            }
+            AssetKind::Material => {
+                self.materials.insert(path.to_owned(), Slot::Pending(id));
+            }
+            AssetKind::Skeleton => {
+                self.skeletons.insert(path.to_owned(), Slot::Pending(id));
+            }
+            AssetKind::Clip => {
+                self.clips.insert(path.to_owned(), Slot::Pending(id));
+            }
+            AssetKind::KeyframeClip => {
+                self.keyframe_clips.insert(path.to_owned(), Slot::Pending(id));
+            }
+            AssetKind::Script => {
+                self.scripts.insert(path.to_owned(), Slot::Pending(id));
+            }
+        };
+
+        let fs_path = match path::as_fs_abs(&self.project_root, path) {
+            Ok(fs_path) => fs_path,
+            Err(err) => {
+                self.finish(Done { id, kind, path: path.to_owned(), result: Err(err) });
+                return;
+            }
+        };
+
+        let job = Job {
+            id,
+            kind,
+            path: path.to_owned(),
+            fs_path,
+        };
+
+        if self.streaming {
+            self.loader.submit(job);
+        } else {
+            let result = loader::decode(&job);
+            self.finish(Done { id, kind, path: job.path, result });
+        }
+    }
+
+    /// Takes the assets the loader has decoded and uploads as many as the budget allows. At least one is uploaded on
+    /// every call, so that even the ones bigger than the budget get their turn.
+    pub(crate) fn update(&mut self) {
+        let done: Vec<_> = self.loader.poll().collect();
+
+        for done in done {
+            match done.result {
+                // Uploading is the expensive part, the rest can be finished right away
+                Ok(Decoded::Mesh(_)) | Ok(Decoded::Texture(_)) => self.uploads.push_back(done),
+                _ => self.finish(done),
+            }
+        }
+
+        let mut uploaded = 0;
+
+        while uploaded < self.upload_budget {
+            let done = match self.uploads.pop_front() {
+                Some(done) => done,
+                None => break,
+            };
+
+            uploaded += done.result.as_ref().map_or(0, Decoded::upload_bytes);
+            self.finish(done);
+        }
+    }
+
+    /// Uploads what was decoded, if it's still wanted, and fills its slot.
+    fn finish(&mut self, done: Done) {
+        let Done { id, kind, path, result } = done;
+
+        let wanted = match kind {
+            AssetKind::Mesh => is_pending(&self.vaos, &path, id),
+            AssetKind::Texture => is_pending(&self.textures, &path, id),
+            AssetKind::Material => is_pending(&self.materials, &path, id),
+            AssetKind::Skeleton => is_pending(&self.skeletons, &path, id),
+            AssetKind::Clip => is_pending(&self.clips, &path, id),
+            AssetKind::KeyframeClip => is_pending(&self.keyframe_clips, &path, id),
+            AssetKind::Script => is_pending(&self.scripts, &path, id),
+        };
+
+        // It was purged or invalidated in the meantime, its file may have changed since it was read
+        if !wanted {
+            return;
+        }
+
+        self.loaded += 1;
+
+        match result {
+            Ok(Decoded::Mesh(mesh)) => {
+                let slot = Vao::from(&mesh).map_or_else(|err| Slot::Failed(err.at(&path)), |vao| Slot::Ready(Rc::new(vao)));
+                self.vaos.insert(path, slot);
+            }
+            Ok(Decoded::Texture(mut tex)) => {
+                tex.load_gl();
+                self.textures.insert(path, Slot::Ready(Rc::new(tex)));
+            }
+            Ok(Decoded::Material(mat)) => {
+                self.materials.insert(path, Slot::Ready(Rc::new(mat)));
+            }
+            Ok(Decoded::Skeleton(skeleton)) => {
+                self.skeletons.insert(path, Slot::Ready(Rc::new(skeleton)));
+            }
+            Ok(Decoded::Clip(clip)) => {
+                self.clips.insert(path, Slot::Ready(Rc::new(clip)));
+            }
+            Ok(Decoded::KeyframeClip(clip)) => {
+                self.keyframe_clips.insert(path, Slot::Ready(Rc::new(clip)));
+            }
+            Ok(Decoded::Script(script)) => {
+                self.scripts.insert(path, Slot::Ready(Rc::new(script)));
+            }
+            Err(err) => match kind {
+                AssetKind::Mesh => {
+                    self.vaos.insert(path, Slot::Failed(err));
+                }
+                AssetKind::Texture => {
+                    self.textures.insert(path, Slot::Failed(err));
+                }
+                AssetKind::Material => {
+                    self.materials.insert(path, Slot::Failed(err));
+                }
+                AssetKind::Skeleton => {
+                    self.skeletons.insert(path, Slot::Failed(err));
+                }
+                AssetKind::Clip => {
+                    self.clips.insert(path, Slot::Failed(err));
+                }
+                AssetKind::KeyframeClip => {
+                    self.keyframe_clips.insert(path, Slot::Failed(err));
+                }
+                AssetKind::Script => {
+                    self.scripts.insert(path, Slot::Failed(err));
+                }
+            },
+        }
+    }
+
+    /// Forgets about the asset at the given path, so that it's loaded from disk again the next time it's asked for. The
+    /// components still using the old one keep it until they let it go.
+    pub(crate) fn invalidate(&mut self, path: &Path) {
+        self.vaos.remove(path);
+        self.textures.remove(path);
+        self.materials.remove(path);
+        self.skeletons.remove(path);
+        self.clips.remove(path);
+        self.keyframe_clips.remove(path);
+        self.scripts.remove(path);
+    }
+
+    /// Forgets about the assets that have already been freed, the ones that nothing took and the ones that failed, and
+    /// stops waiting for the pending ones.
+    pub(crate) fn purge(&mut self) {
+        self.vaos.retain(|_, slot| slot.is_alive());
+        self.textures.retain(|_, slot| slot.is_alive());
+        self.materials.retain(|_, slot| slot.is_alive());
+        self.skeletons.retain(|_, slot| slot.is_alive());
+        self.clips.retain(|_, slot| slot.is_alive());
+        self.keyframe_clips.retain(|_, slot| slot.is_alive());
+        self.scripts.retain(|_, slot| slot.is_alive());
+
+        self.uploads.clear();
+        self.loaded = 0;
+    }
+
+    pub(crate) fn load_progress(&self) -> LoadProgress {
+        let pending = count_pending(&self.vaos)
+            + count_pending(&self.textures)
+            + count_pending(&self.materials)
+            + count_pending(&self.skeletons)
+            + count_pending(&self.clips)
+            + count_pending(&self.keyframe_clips)
+            + count_pending(&self.scripts);
+
+        LoadProgress {
+            loaded: self.loaded,
+            pending,
+        }
+    }
+
+    pub(crate) fn memory_stats<'a, R, P>(&self, render_targets: R, post_targets: P) -> MemoryStats
+        where R: IntoIterator<Item=&'a Framebuffer>, P: IntoIterator<Item=&'a Framebuffer> {
+        let mut stats = MemoryStats::default();
+
+        for vao in self.vaos.values().filter_map(Slot::get) {
+            stats.meshes += 1;
+            stats.mesh_bytes += vao.gpu_bytes();
+        }
+
+        for tex in self.textures.values().filter_map(Slot::get) {
+            stats.textures += 1;
+            stats.texture_bytes += tex.gpu_bytes();
+        }
+
+        for framebuffer in render_targets {
+            stats.render_targets += 1;
+            stats.render_target_bytes += framebuffer.gpu_bytes();
+        }
+
+        for framebuffer in post_targets {
+            stats.post_targets += 1;
+            stats.post_target_bytes += framebuffer.gpu_bytes();
+        }
+
+        stats
+    }
+}
+
+impl<T> Slot<T> {
+    fn get(&self) -> Option<Rc<T>> {
+        match self {
+            Slot::Ready(asset) => Some(asset.clone()),
+            Slot::Loaded(asset) => asset.upgrade(),
+            _ => None,
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        matches!(self, Slot::Loaded(asset) if asset.strong_count() > 0)
+    }
+}
+
+impl Decoded {
+    /// Roughly how much will be sent to the GPU.
+    fn upload_bytes(&self) -> usize {
+        match self {
+            Decoded::Mesh(mesh) => mem::size_of_val(mesh.vertices.as_slice()) + mem::size_of_val(mesh.indices.as_slice()),
+            Decoded::Texture(tex) => tex.raw.len() + tex.mips.iter().map(Vec::len).sum::<usize>(),
+            Decoded::Material(_) | Decoded::Skeleton(_) | Decoded::Clip(_) | Decoded::KeyframeClip(_) | Decoded::Script(_) => 0,
+        }
+    }
+}
+
+fn needs_load<T>(map: &HashMap<PathBuf, Slot<T>>, path: &Path) -> bool {
+    match map.get(path) {
+        None => true,
+        Some(Slot::Loaded(asset)) => asset.strong_count() == 0,
+        Some(_) => false,
+    }
+}
+
+fn is_pending<T>(map: &HashMap<PathBuf, Slot<T>>, path: &Path, id: u64) -> bool {
+    matches!(map.get(path), Some(Slot::Pending(pending_id)) if *pending_id == id)
+}
+
+fn count_pending<T>(map: &HashMap<PathBuf, Slot<T>>) -> u32 {
+    map.values().filter(|slot| matches!(slot, Slot::Pending(_))).count() as u32
+}
+
+/// Returns the asset if it's been loaded, handing it over to the caller if nothing had taken it yet.
+fn take<T>(map: &mut HashMap<PathBuf, Slot<T>>, path: &Path) -> Result<Option<Rc<T>>> {
+    let slot = match map.get_mut(path) {
+        Some(slot) => slot,
+        None => return Ok(None),
+    };
+
+    match slot {
+        Slot::Pending(_) => Ok(None),
+        Slot::Failed(err) => Err(err.clone()),
+        Slot::Loaded(asset) => Ok(asset.upgrade()),
+        Slot::Ready(asset) => {
+            let asset = asset.clone();
+            *slot = Slot::Loaded(Rc::downgrade(&asset));
+            Ok(Some(asset))
+        }
+    }
+}
@@ -1,11 +1,16 @@
 use std::path::PathBuf;
+use std::rc::Rc;
 
 use glam::Mat4;
 use serde::{Deserialize, Serialize};
 
 use raven_ecs::{Component, Entity};
 
-use crate::resource::{Scene, Texture};
+use crate::animation::{self, Pose};
+use crate::database::AssetRef;
+use crate::postprocess::PostEffect;
+use crate::resource::{AnimationClip, KeyframeClip, Material, Scene, Script, Skeleton, Texture};
+use crate::script::ScriptInstance;
 use crate::vao::Vao;
 
 #[derive(Component, Serialize, Deserialize, Clone, Default)]
@@ -22,33 +27,380 @@ pub struct HierarchyComponent {
 
 #[derive(Component, Serialize, Deserialize)]
 pub struct MeshComponent {
-    pub mesh: PathBuf,
-    pub mat: PathBuf,
+    pub mesh: AssetRef,
+    pub mat: AssetRef,
+    /// `.mesh` files with less and less detail, drawn instead of `mesh` when the entity's `LodGroupComponent` says so
+    #[serde(default)]
+    pub lods: Vec<AssetRef>,
 
     #[serde(skip)]
-    pub(crate) vao: Option<Vao>,
+    pub(crate) vao: Option<Rc<Vao>>,
+    /// One per LOD
     #[serde(skip)]
-    pub(crate) tex: Option<Texture>,
+    pub(crate) lod_vaos: Vec<Option<Rc<Vao>>>,
+    #[serde(skip)]
+    pub(crate) material: Option<Rc<Material>>,
+    #[serde(skip)]
+    pub(crate) tex: Option<MeshTexture>,
+}
+
+pub(crate) enum MeshTexture {
+    Loaded(Rc<Texture>),
+    /// Colour attachment of the framebuffer for the render texture at the given path, owned by the `Processor`
+    RenderTarget(PathBuf),
 }
 
 impl MeshComponent {
-    pub fn new(mesh_path: PathBuf, mat_path: PathBuf) -> MeshComponent {
+    pub fn new<M: Into<AssetRef>, T: Into<AssetRef>>(mesh: M, mat: T) -> MeshComponent {
         MeshComponent {
-            mesh: mesh_path,
-            mat: mat_path,
+            mesh: mesh.into(),
+            mat: mat.into(),
+            lods: Vec::new(),
 
             vao: None,
+            lod_vaos: Vec::new(),
+            material: None,
             tex: None,
         }
     }
 }
 
+/// Picks which LOD the meshes of its entity are drawn with, by how much of the screen the entity covers.
+#[derive(Component, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct LodGroupComponent {
+    /// Fractions of the viewport's height, from the largest down. Once the entity covers less than the i-th one, its
+    /// meshes are drawn with their i-th LOD
+    pub screen_sizes: Vec<f32>,
+    /// Below this fraction of the viewport's height the entity isn't drawn at all
+    pub cull_size: f32,
+}
+
+impl LodGroupComponent {
+    /// Switches to each of the given number of LODs whenever the entity's size on screen halves, starting at half the
+    /// viewport.
+    pub fn with_levels(levels: usize) -> LodGroupComponent {
+        LodGroupComponent {
+            screen_sizes: (1..=levels).map(|i| 0.5f32.powi(i as i32)).collect(),
+            ..LodGroupComponent::default()
+        }
+    }
+
+    /// The LOD to draw at the given size on screen, 0 being the mesh itself, or `None` if it's too small to be drawn.
+    pub fn level(&self, screen_size: f32) -> Option<usize> {
+        if screen_size < self.cull_size {
+            return None;
+        }
+
+        Some(self.screen_sizes.iter().take_while(|&&size| screen_size < size).count())
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CameraComponent {
+    pub projection: Projection,
+    /// Color the camera's viewport is cleared with before rendering. Only visible when the skybox is disabled
+    pub clear_color: [f32; 4],
+    pub skybox: bool,
+    /// Region of the target this camera renders to, as `[x, y, width, height]` normalized to the target size with the
+    /// origin in the bottom left corner
+    pub viewport: [f32; 4],
+    /// Render texture (`.rtex`) this camera draws to instead of the canvas
+    pub target: Option<AssetRef>,
+    /// Cameras are rendered in ascending order, so cameras with a greater order are drawn on top of the others
+    pub order: i32,
+    pub active: bool,
+    /// Effects applied, in order, to what the camera has drawn before it's copied to its target
+    pub post: Vec<Box<dyn PostEffect>>,
+}
+
+impl Default for CameraComponent {
+    fn default() -> Self {
+        CameraComponent {
+            projection: Projection::default(),
+            clear_color: [0.1, 0.1, 0.1, 1.0],
+            skybox: true,
+            viewport: [0.0, 0.0, 1.0, 1.0],
+            target: None,
+            order: 0,
+            active: true,
+            post: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view, in degrees
+        fov: f32,
+        near: f32,
+        far: f32,
+    },
+    Orthographic {
+        /// Vertical extent of the view volume, in world units
+        height: f32,
+        near: f32,
+        far: f32,
+    },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Perspective {
+            fov: 90.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+impl Projection {
+    pub fn as_mat4(&self, aspect_ratio: f32) -> Mat4 {
+        match *self {
+            Projection::Perspective { fov, near, far } => {
+                Mat4::perspective_rh(fov.to_radians(), aspect_ratio, near, far)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * aspect_ratio;
+                Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, near, far)
+            }
+        }
+    }
+}
+
+/// A light imported with a scene. Shading is unlit, so lights don't affect how things are drawn yet.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct LightComponent {
+    pub kind: LightKind,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance past which the light has no effect, for point and spot lights. Unlimited if `None`
+    pub range: Option<f32>,
+}
+
+/// Directional and spot lights shine along the -Z axis of their entity.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot {
+        /// Angles from the axis of the cone where the light starts fading out and where it ends, in radians
+        inner_cone: f32,
+        outer_cone: f32,
+    },
+}
+
+/// Plays animation clips on a skeleton. Skinned meshes are posed by the animator of their entity, or of their closest
+/// ancestor that has one, and are drawn where the entity of the animator is.
+#[derive(Component, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AnimatorComponent {
+    /// `.skel` the clips are played on
+    pub skeleton: Option<AssetRef>,
+    /// Blended together according to their weights
+    pub layers: Vec<AnimationLayer>,
+    /// Multiplies how fast time goes for every layer
+    pub speed: f32,
+    pub playing: bool,
+
+    #[serde(skip)]
+    pub(crate) loaded_skeleton: Option<Rc<Skeleton>>,
+    /// One per joint, computed from the layers on every frame
+    #[serde(skip)]
+    pub(crate) skinning: Option<Rc<Vec<Mat4>>>,
+}
+
+impl Default for AnimatorComponent {
+    fn default() -> Self {
+        AnimatorComponent {
+            skeleton: None,
+            layers: Vec::new(),
+            speed: 1.0,
+            playing: true,
+
+            loaded_skeleton: None,
+            skinning: None,
+        }
+    }
+}
+
+impl AnimatorComponent {
+    pub fn new<S: Into<AssetRef>>(skeleton: S) -> AnimatorComponent {
+        AnimatorComponent {
+            skeleton: Some(skeleton.into()),
+            ..AnimatorComponent::default()
+        }
+    }
+
+    /// Moves every layer forward by the given amount of seconds, unless paused.
+    pub fn advance(&mut self, delta: f32) {
+        if !self.playing {
+            return;
+        }
+
+        for layer in &mut self.layers {
+            layer.time += delta * self.speed;
+
+            // Keeps the time small enough not to lose precision
+            if let Some(clip) = layer.loaded.as_ref() {
+                layer.time = layer.clip_time(clip.duration);
+            }
+        }
+    }
+
+    /// Blends the layers whose clip is loaded, weighted by their weights. The skeleton is at rest where no layer
+    /// animates it.
+    pub fn pose(&self, skeleton: &Skeleton) -> Pose {
+        let mut pose = Pose::rest(skeleton);
+        let mut total_weight = 0.0;
+
+        for layer in &self.layers {
+            let clip = match layer.loaded.as_ref() {
+                Some(clip) if layer.weight > 0.0 => clip,
+                _ => continue,
+            };
+
+            total_weight += layer.weight;
+
+            // Each layer is given its share of what was blended so far, the first one replaces the rest pose
+            let sampled = Pose::sample(skeleton, clip, layer.clip_time(clip.duration));
+            pose.blend(&sampled, layer.weight / total_weight);
+        }
+
+        pose
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AnimationLayer {
+    /// `.anim` to play
+    pub clip: AssetRef,
+    /// In seconds since the clip started playing
+    pub time: f32,
+    pub weight: f32,
+    /// Starts over when it reaches the end, otherwise it stays on the last frame
+    pub looping: bool,
+
+    #[serde(skip)]
+    pub(crate) loaded: Option<Rc<AnimationClip>>,
+}
+
+impl AnimationLayer {
+    pub fn new<C: Into<AssetRef>>(clip: C) -> AnimationLayer {
+        AnimationLayer {
+            clip: clip.into(),
+            time: 0.0,
+            weight: 1.0,
+            looping: true,
+
+            loaded: None,
+        }
+    }
+
+    /// Where in a clip of the given duration the layer is.
+    pub fn clip_time(&self, duration: f32) -> f32 {
+        animation::clip_time(self.time, duration, self.looping)
+    }
+}
+
+/// Plays a keyframe clip on the values of the components of its entity and of its descendants. An entity can have more
+/// than one, each playing its own clip.
+#[derive(Component, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct KeyframeAnimatorComponent {
+    /// `.kanim` to play
+    pub clip: Option<AssetRef>,
+    /// In seconds since the clip started playing
+    pub time: f32,
+    /// Multiplies how fast time goes
+    pub speed: f32,
+    /// Starts over when it reaches the end, otherwise it stays on the last frame
+    pub looping: bool,
+    pub playing: bool,
+
+    #[serde(skip)]
+    pub(crate) loaded: Option<Rc<KeyframeClip>>,
+}
+
+impl Default for KeyframeAnimatorComponent {
+    fn default() -> Self {
+        KeyframeAnimatorComponent {
+            clip: None,
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+            playing: true,
+
+            loaded: None,
+        }
+    }
+}
+
+impl KeyframeAnimatorComponent {
+    pub fn new<C: Into<AssetRef>>(clip: C) -> KeyframeAnimatorComponent {
+        KeyframeAnimatorComponent {
+            clip: Some(clip.into()),
+            ..KeyframeAnimatorComponent::default()
+        }
+    }
+
+    /// Moves forward by the given amount of seconds, unless paused. Returns where in the clip it is then, if it's
+    /// loaded.
+    pub fn advance(&mut self, delta: f32) -> Option<f32> {
+        if self.playing {
+            self.time += delta * self.speed;
+        }
+
+        let clip = self.loaded.as_ref()?;
+
+        // Keeps the time small enough not to lose precision
+        self.time = animation::clip_time(self.time, clip.duration, self.looping);
+
+        Some(self.time)
+    }
+}
+
+/// Runs a script on its entity, see `script` for what scripts can do. An entity can have more than one, each keeping its
+/// own state.
 #[derive(Component, Serialize, Deserialize, Default)]
-pub struct CameraComponent {}
+#[serde(default)]
+pub struct ScriptComponent {
+    /// `.rhai` to run
+    pub script: Option<AssetRef>,
+
+    #[serde(skip)]
+    pub(crate) loaded: Option<Rc<Script>>,
+    /// Compiled, once the script is loaded
+    #[serde(skip)]
+    pub(crate) instance: Option<ScriptInstance>,
+    /// After the script failed, so that its error is only reported once. It runs again when its file changes
+    #[serde(skip)]
+    pub(crate) stopped: bool,
+}
+
+impl ScriptComponent {
+    pub fn new<S: Into<AssetRef>>(script: S) -> ScriptComponent {
+        ScriptComponent {
+            script: Some(script.into()),
+            ..ScriptComponent::default()
+        }
+    }
+
+    /// Switches to another script, which starts over once it's loaded.
+    pub fn set_script(&mut self, script: Option<AssetRef>) {
+        *self = ScriptComponent {
+            script,
+            ..ScriptComponent::default()
+        };
+    }
+}
 
 #[derive(Component, Serialize, Deserialize, Default)]
 pub struct SceneComponent {
-    pub scene: Option<PathBuf>,
+    pub scene: Option<AssetRef>,
     #[serde(skip)]
     pub loaded: Option<Scene>,
 }
@@ -0,0 +1,93 @@
+use glam::{Mat4, Vec4};
+
+use crate::resource::Aabb;
+
+/// The six planes enclosing the volume visible from a camera, with their normals pointing inwards.
+pub(crate) struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a combined projection and view matrix, as described by Gribb and Hartmann in "Fast
+    /// Extraction of Viewing Frustum Planes from the World-View-Projection Matrix".
+    ///
+    /// Expects clip space depth to go from 0 to 1, which is what the projection matrices used by cameras produce.
+    pub(crate) fn from_mat(view_projection: &Mat4) -> Frustum {
+        let m = view_projection.transpose();
+        let (row_0, row_1, row_2, row_3) = (m.x_axis, m.y_axis, m.z_axis, m.w_axis);
+
+        Frustum {
+            planes: [
+                row_3 + row_0, // Left
+                row_3 - row_0, // Right
+                row_3 + row_1, // Bottom
+                row_3 - row_1, // Top
+                row_2,         // Near
+                row_3 - row_2, // Far
+            ],
+        }
+    }
+
+    /// Returns false only if the box lies entirely outside of the frustum. May return true for some boxes that are
+    /// outside but close to the frustum's corners, which is fine for culling.
+    pub(crate) fn intersects(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner of the box that is furthest along the plane's normal
+            let furthest = Vec4::new(
+                if plane.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+                1.0,
+            );
+
+            plane.dot(furthest) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+
+    use super::*;
+
+    fn frustum() -> Frustum {
+        // Looking down -Z from the origin
+        let view = Mat4::look_at_rh(Vec3::ZERO, -Vec3::Z, Vec3::Y);
+        let projection = Mat4::perspective_rh(90_f32.to_radians(), 1.0, 0.1, 100.0);
+
+        Frustum::from_mat(&(projection * view))
+    }
+
+    fn unit_box_at(center: Vec3) -> Aabb {
+        Aabb {
+            min: center - Vec3::splat(0.5),
+            max: center + Vec3::splat(0.5),
+        }
+    }
+
+    #[test]
+    fn inside() {
+        assert!(frustum().intersects(&unit_box_at(Vec3::new(0.0, 0.0, -5.0))));
+    }
+
+    #[test]
+    fn partially_inside() {
+        // Straddles the right plane, which at this distance is at x = 5
+        assert!(frustum().intersects(&unit_box_at(Vec3::new(5.0, 0.0, -5.0))));
+        // Straddles the near plane
+        assert!(frustum().intersects(&unit_box_at(Vec3::ZERO)));
+    }
+
+    #[test]
+    fn outside() {
+        // Behind the camera
+        assert!(!frustum().intersects(&unit_box_at(Vec3::new(0.0, 0.0, 5.0))));
+        // Left of the left plane
+        assert!(!frustum().intersects(&unit_box_at(Vec3::new(-7.0, 0.0, -5.0))));
+        // Above the top plane
+        assert!(!frustum().intersects(&unit_box_at(Vec3::new(0.0, 7.0, -5.0))));
+        // Past the far plane
+        assert!(!frustum().intersects(&unit_box_at(Vec3::new(0.0, 0.0, -200.0))));
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+use crate::bake::ImportSettings;
+use crate::error::{Error, Result};
+use crate::io::Serializable;
+use crate::path;
+use crate::resource::{AnimationClip, KeyframeClip, RenderTexture, Script, Skeleton};
+
+/// Extensions of the files that get a GUID. Anything else in the project, like the import manifest, the action map or
+/// files that are only there for the external ones, like the buffers of a glTF file, is left alone.
+const ASSET_EXTENSIONS: &[&str] = &[
+    "scn",
+    "mesh",
+    "mat",
+    "tex",
+    RenderTexture::EXTENSION,
+    Skeleton::EXTENSION,
+    AnimationClip::EXTENSION,
+    KeyframeClip::EXTENSION,
+    Script::EXTENSION,
+    // External files, whose meta file also keeps their import settings
+    "png",
+    "jpg",
+    "jpeg",
+    "fbx",
+    "obj",
+    "gltf",
+    "glb",
+];
+
+/// Identifies an asset for as long as it exists, wherever it's moved to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Guid(Uuid);
+
+impl Guid {
+    /// A new random GUID.
+    pub fn generate() -> Guid {
+        Guid(Uuid::new_v4())
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// What's known about an asset besides its contents, saved next to it with the `.meta` extension appended to its name.
+/// Moving an asset together with its meta file keeps its GUID.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Meta {
+    pub guid: Guid,
+    /// How the asset is imported, for external files whose settings were changed from the defaults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub import: Option<ImportSettings>,
+}
+
+impl Meta {
+    pub const EXTENSION: &'static str = "meta";
+
+    /// The path to the meta file of the asset at the given path, `$/` or filesystem one.
+    pub fn path_for<P: AsRef<Path>>(asset: P) -> PathBuf {
+        let mut meta_path = asset.as_ref().as_os_str().to_owned();
+        meta_path.push(".");
+        meta_path.push(Meta::EXTENSION);
+
+        PathBuf::from(meta_path)
+    }
+
+    pub fn is_meta<P: AsRef<Path>>(path: P) -> bool {
+        path.as_ref().extension() == Some(Meta::EXTENSION.as_ref())
+    }
+}
+
+/// A reference from a scene or a material to another asset.
+///
+/// The GUID is what identifies the asset, the path is where it was when the reference was made. The path is used when
+/// there's no GUID, for references made before assets had one, or when the database doesn't know about it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetRef {
+    pub guid: Option<Guid>,
+    pub path: PathBuf,
+}
+
+impl AssetRef {
+    /// A reference by path only.
+    pub fn new<P: Into<PathBuf>>(path: P) -> AssetRef {
+        AssetRef {
+            guid: None,
+            path: path.into(),
+        }
+    }
+
+    /// Whether both refer to the same asset, by GUID if both have one and by path otherwise.
+    pub fn is_same_asset(&self, other: &AssetRef) -> bool {
+        match (self.guid, other.guid) {
+            (Some(guid), Some(other_guid)) => guid == other_guid,
+            _ => self.path == other.path,
+        }
+    }
+}
+
+impl From<PathBuf> for AssetRef {
+    fn from(path: PathBuf) -> AssetRef {
+        AssetRef::new(path)
+    }
+}
+
+impl From<&Path> for AssetRef {
+    fn from(path: &Path) -> AssetRef {
+        AssetRef::new(path)
+    }
+}
+
+impl From<String> for AssetRef {
+    fn from(path: String) -> AssetRef {
+        AssetRef::new(path)
+    }
+}
+
+impl From<&str> for AssetRef {
+    fn from(path: &str) -> AssetRef {
+        AssetRef::new(path)
+    }
+}
+
+// References without a GUID are saved as just their path, which is also how references were saved before GUIDs existed
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum AssetRefRepr {
+    Path(PathBuf),
+    Guid { guid: Guid, path: PathBuf },
+}
+
+impl Serialize for AssetRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let repr = match self.guid {
+            Some(guid) => AssetRefRepr::Guid { guid, path: self.path.clone() },
+            None => AssetRefRepr::Path(self.path.clone()),
+        };
+
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AssetRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<AssetRef, D::Error> {
+        Ok(match AssetRefRepr::deserialize(deserializer)? {
+            AssetRefRepr::Path(path) => AssetRef { guid: None, path },
+            AssetRefRepr::Guid { guid, path } => AssetRef { guid: Some(guid), path },
+        })
+    }
+}
+
+/// The GUIDs of the assets of a project and where those assets are, read from their meta files.
+///
+/// Opening a database only reads, assets without a meta file are given one by `assign_guids`.
+#[derive(Default)]
+pub struct AssetDatabase {
+    project_root: PathBuf,
+    paths: HashMap<Guid, PathBuf>,
+    guids: HashMap<PathBuf, Guid>,
+    // Assets whose meta file has a GUID that was already taken, usually because they were copied along with it
+    duplicates: Vec<PathBuf>,
+}
+
+impl AssetDatabase {
+    pub fn open<R: AsRef<Path>>(project_root: R) -> Result<AssetDatabase> {
+        let mut db = AssetDatabase {
+            project_root: project_root.as_ref().to_owned(),
+            paths: HashMap::new(),
+            guids: HashMap::new(),
+            duplicates: Vec::new(),
+        };
+
+        db.rescan()?;
+
+        Ok(db)
+    }
+
+    /// Reads the meta files again, to pick up assets that were added, moved or removed.
+    pub fn rescan(&mut self) -> Result<()> {
+        self.paths.clear();
+        self.guids.clear();
+        self.duplicates.clear();
+
+        for fs_path in list_files(&self.project_root)? {
+            if !Meta::is_meta(&fs_path) {
+                continue;
+            }
+
+            // A meta file left behind by an asset that was removed, or moved without it
+            let fs_asset_path = fs_path.with_extension("");
+            if !fs_asset_path.is_file() {
+                continue;
+            }
+
+            let asset_path = match path::from_fs_abs(&self.project_root, &fs_asset_path) {
+                Some(asset_path) => asset_path,
+                None => continue,
+            };
+
+            let meta = Meta::load(&fs_path)?;
+
+            if self.paths.contains_key(&meta.guid) {
+                self.duplicates.push(asset_path);
+            } else {
+                self.add(meta.guid, asset_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gives a GUID to the assets that have none, or that have the same as another one, writing their meta files.
+    /// Returns the `$/` paths of those assets.
+    pub fn assign_guids(&mut self) -> Result<Vec<PathBuf>> {
+        let mut assigned = std::mem::take(&mut self.duplicates);
+
+        for fs_path in list_files(&self.project_root)? {
+            if !is_asset(&fs_path) || Meta::path_for(&fs_path).is_file() {
+                continue;
+            }
+
+            if let Some(asset_path) = path::from_fs_abs(&self.project_root, &fs_path) {
+                assigned.push(asset_path);
+            }
+        }
+
+        for asset_path in &assigned {
+            let guid = Guid::generate();
+
+            Meta { guid, import: None }.save(Meta::path_for(path::as_fs_abs(&self.project_root, asset_path)?))?;
+            self.add(guid, asset_path.clone());
+        }
+
+        Ok(assigned)
+    }
+
+    pub fn get_guid<P: AsRef<Path>>(&self, asset_path: P) -> Option<Guid> {
+        self.guids.get(asset_path.as_ref()).copied()
+    }
+
+    pub fn get_path(&self, guid: Guid) -> Option<&Path> {
+        self.paths.get(&guid).map(PathBuf::as_path)
+    }
+
+    /// Returns the `$/` path of the asset, where it is now if the database knows about it and where it was when the
+    /// reference was made otherwise.
+    pub fn resolve<'a>(&'a self, asset: &'a AssetRef) -> &'a Path {
+        asset.guid
+            .and_then(|guid| self.get_path(guid))
+            .unwrap_or(&asset.path)
+    }
+
+    /// Makes a reference to the asset at the given `$/` path, with its GUID if it has one.
+    pub fn reference<P: AsRef<Path>>(&self, asset_path: P) -> AssetRef {
+        AssetRef {
+            guid: self.get_guid(&asset_path),
+            path: asset_path.as_ref().to_owned(),
+        }
+    }
+
+    /// Gives a GUID to the asset at the given `$/` path, unless it has one already, writing its meta file. An asset saved
+    /// in place of one that was removed, like the ones imported again, keeps the GUID of the meta file left behind.
+    pub fn assign_guid<P: AsRef<Path>>(&mut self, asset_path: P) -> Result<Guid> {
+        let asset_path = asset_path.as_ref();
+
+        if let Some(guid) = self.get_guid(asset_path) {
+            return Ok(guid);
+        }
+
+        let fs_meta_path = Meta::path_for(path::as_fs_abs(&self.project_root, asset_path)?);
+
+        let kept = if fs_meta_path.is_file() { Some(Meta::load(&fs_meta_path)?.guid) } else { None };
+
+        // Unless another asset has taken it since
+        let guid = match kept.filter(|guid| !self.paths.contains_key(guid)) {
+            Some(guid) => guid,
+            None => {
+                let guid = Guid::generate();
+                Meta { guid, import: None }.save(&fs_meta_path)?;
+                guid
+            }
+        };
+
+        self.add(guid, asset_path.to_owned());
+
+        Ok(guid)
+    }
+
+    /// Moves, or renames, an asset along with its meta file. The references to it keep working as long as they have its
+    /// GUID.
+    pub fn move_asset<F: AsRef<Path>, T: AsRef<Path>>(&mut self, from: F, to: T) -> Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+
+        let fs_from = path::as_fs_abs(&self.project_root, from)?;
+        let fs_to = path::as_fs_abs(&self.project_root, to)?;
+
+        if fs_to.exists() {
+            return Err(Error::from(io::Error::new(io::ErrorKind::AlreadyExists, "there's already a file there")).at(to));
+        }
+
+        if let Some(parent) = fs_to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::rename(&fs_from, &fs_to).map_err(|err| Error::from(err).at(from))?;
+
+        let fs_meta_from = Meta::path_for(&fs_from);
+        if fs_meta_from.is_file() {
+            fs::rename(&fs_meta_from, Meta::path_for(&fs_to)).map_err(|err| Error::from(err).at(from))?;
+        }
+
+        if let Some(guid) = self.guids.remove(from) {
+            self.add(guid, to.to_owned());
+        }
+
+        Ok(())
+    }
+
+    fn add(&mut self, guid: Guid, asset_path: PathBuf) {
+        self.guids.insert(asset_path.clone(), guid);
+        self.paths.insert(guid, asset_path);
+    }
+}
+
+/// Whether the file at the given path is one of the assets that get a GUID.
+fn is_asset(path: &Path) -> bool {
+    matches!(path.extension().and_then(|ext| ext.to_str()), Some(ext) if ASSET_EXTENSIONS.contains(&ext))
+}
+
+/// All the files in the directory and its subdirectories, sorted so that the same GUID always wins over its duplicates.
+/// Hidden files and directories, like the ones of version control, aren't assets and are left out, except for the one
+/// the imported assets are in.
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).map_err(|err| Error::from(err).at(&dir))? {
+            let entry = entry?;
+
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with('.') && name != path::IMPORT_DIR {
+                continue;
+            }
+
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{AssetRef, Guid};
+
+    #[test]
+    fn reads_references_saved_as_paths() {
+        let asset: AssetRef = serde_json::from_str("\"$/cube.mesh\"").unwrap();
+        assert_eq!(asset, AssetRef::new("$/cube.mesh"));
+
+        // And saves them the same way
+        assert_eq!(serde_json::to_string(&asset).unwrap(), "\"$/cube.mesh\"");
+    }
+
+    #[test]
+    fn round_trips_references_with_guids() {
+        let asset = AssetRef {
+            guid: Some(Guid::generate()),
+            path: PathBuf::from("$/cube.mesh"),
+        };
+
+        let json = serde_json::to_string(&asset).unwrap();
+
+        assert_eq!(serde_json::from_str::<AssetRef>(&json).unwrap(), asset);
+    }
+}
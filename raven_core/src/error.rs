@@ -0,0 +1,182 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::ecs::Entity;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong in the engine.
+///
+/// Errors about an asset carry its path, when it's known. They can be cloned, so that an asset that failed to load keeps
+/// failing with the same error every time it's asked for.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// Reading or writing a file failed
+    Io {
+        path: Option<PathBuf>,
+        source: Arc<io::Error>,
+    },
+    /// A file was read but its contents couldn't be made sense of
+    Decode {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// A shader failed to compile or link, with the log from the driver
+    Shader {
+        name: Option<String>,
+        log: String,
+    },
+    MissingComponent {
+        entity: Entity,
+        component: &'static str,
+    },
+    /// A path that should be relative to the project root, starting with `$/`, and isn't
+    InvalidPath(PathBuf),
+    /// An external file couldn't be turned into assets
+    Import {
+        path: PathBuf,
+        message: String,
+    },
+    /// A script failed to compile or raised an error while running, at the given line of its file if it's known
+    Script {
+        path: PathBuf,
+        line: Option<usize>,
+        message: String,
+    },
+    /// The platform failed us, for instance when creating a graphics context or watching files
+    Platform(String),
+    /// The driver can't draw into a framebuffer with the given attachments, with the status it reported
+    IncompleteFramebuffer(u32),
+}
+
+impl Error {
+    pub fn decode<M: ToString>(message: M) -> Error {
+        Error::Decode { path: None, message: message.to_string() }
+    }
+
+    pub fn import<P: AsRef<Path>, M: ToString>(path: P, message: M) -> Error {
+        Error::Import { path: path.as_ref().to_owned(), message: message.to_string() }
+    }
+
+    /// Attaches the path of the file the error is about, replacing the one it had. Errors that aren't about a file are
+    /// left as they are.
+    pub fn at<P: AsRef<Path>>(self, at: P) -> Error {
+        let at = Some(at.as_ref().to_owned());
+
+        match self {
+            Error::Io { source, .. } => Error::Io { path: at, source },
+            Error::Decode { message, .. } => Error::Decode { path: at, message },
+            err => err,
+        }
+    }
+
+    /// A short name for the kind of error, for tools that report errors to other programs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Io { .. } => "io",
+            Error::Decode { .. } => "decode",
+            Error::Shader { .. } => "shader",
+            Error::MissingComponent { .. } => "missing_component",
+            Error::InvalidPath(_) => "invalid_path",
+            Error::Import { .. } => "import",
+            Error::Script { .. } => "script",
+            Error::Platform(_) => "platform",
+            Error::IncompleteFramebuffer(_) => "incomplete_framebuffer",
+        }
+    }
+
+    /// The asset or file the error is about, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Io { path, .. } | Error::Decode { path, .. } => path.as_deref(),
+            Error::InvalidPath(path) | Error::Import { path, .. } | Error::Script { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path: Some(path), source } => write!(f, "couldn't access {:?}: {}", path, source),
+            Error::Io { path: None, source } => write!(f, "{}", source),
+            Error::Decode { path: Some(path), message } => write!(f, "couldn't decode {:?}: {}", path, message),
+            Error::Decode { path: None, message } => write!(f, "couldn't decode: {}", message),
+            Error::Shader { name: Some(name), log } => write!(f, "couldn't build shader {}: {}", name, log),
+            Error::Shader { name: None, log } => write!(f, "couldn't build shader: {}", log),
+            Error::MissingComponent { entity, component } => write!(f, "{:?} has no {}", entity, component),
+            Error::InvalidPath(path) => write!(f, "{:?} isn't relative to the project root", path),
+            Error::Import { path, message } => write!(f, "couldn't import {:?}: {}", path, message),
+            Error::Script { path, line: Some(line), message } => write!(f, "{}:{}: {}", path.display(), line, message),
+            Error::Script { path, line: None, message } => write!(f, "{}: {}", path.display(), message),
+            Error::Platform(message) => write!(f, "{}", message),
+            Error::IncompleteFramebuffer(status) => write!(f, "incomplete framebuffer, status {:#x}", status),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io { path: None, source: Arc::new(err) }
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(err: bincode::Error) -> Error {
+        match *err {
+            bincode::ErrorKind::Io(err) => Error::from(err),
+            err => Error::decode(err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        if err.is_io() {
+            Error::from(io::Error::from(err))
+        } else {
+            Error::decode(err)
+        }
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(err: notify::Error) -> Error {
+        match err {
+            notify::Error::Io(err) => Error::from(err),
+            err => Error::Platform(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::path::Path;
+
+    use super::Error;
+
+    #[test]
+    fn attaches_paths() {
+        let err = Error::from(io::Error::new(io::ErrorKind::NotFound, "not found")).at("$/cube.mesh");
+
+        assert_eq!(err.path(), Some(Path::new("$/cube.mesh")));
+        assert_eq!(err.to_string(), "couldn't access \"$/cube.mesh\": not found");
+        assert!(std::error::Error::source(&err).is_some());
+
+        // Only errors about files get one
+        let err = Error::Shader { name: None, log: "oops".to_owned() }.at("$/cube.mesh");
+        assert_eq!(err.path(), None);
+    }
+}
@@ -1,13 +1,35 @@
 use gl;
 
+use crate::error::{Error, Result};
+use crate::resource::Texture;
+
 pub struct Framebuffer {
     framebuffer_id: u32,
-    texture_id: u32,
+    // Colour attachment, deleted when dropped
+    texture: Texture,
     depth_n_stencil_id: u32,
+    // Of the colour attachment
+    bytes_per_pixel: usize,
 }
 
 impl Framebuffer {
-    pub fn new(size: (i32, i32)) -> Framebuffer {
+    pub fn new(size: (i32, i32)) -> Result<Framebuffer> {
+        // RGB is usually padded to 4 bytes by drivers
+        Framebuffer::with_format(size, gl::RGB, gl::RGB, gl::UNSIGNED_BYTE, 4)
+    }
+
+    /// Creates a framebuffer with a floating point colour attachment, whose values aren't clamped between 0 and 1.
+    pub fn new_hdr(size: (i32, i32)) -> Result<Framebuffer> {
+        Framebuffer::with_format(size, gl::RGBA16F, gl::RGBA, gl::FLOAT, 8)
+    }
+
+    fn with_format(
+        size: (i32, i32),
+        internal_format: u32,
+        format: u32,
+        ty: u32,
+        bytes_per_pixel: usize,
+    ) -> Result<Framebuffer> {
         let mut framebuffer_id = 0;
 
         unsafe {
@@ -23,10 +45,13 @@ impl Framebuffer {
             gl::GenTextures(1, &mut texture_id);
             gl::BindTexture(gl::TEXTURE_2D, texture_id);
 
-            gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGB as _, width, height, 0, gl::RGB, gl::UNSIGNED_BYTE, 0 as _);
+            gl::TexImage2D(gl::TEXTURE_2D, 0, internal_format as _, width, height, 0, format, ty, 0 as _);
 
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+            // So that post-processing effects sampling around a pixel don't wrap around at the edges
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
 
             gl::BindTexture(gl::TEXTURE_2D, 0);
 
@@ -46,16 +71,28 @@ impl Framebuffer {
             gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, depth_n_stencil_id);
         }
 
-        unsafe {
-            assert_eq!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER), gl::FRAMEBUFFER_COMPLETE);
+        let status = unsafe {
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
             gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
-        }
+            status
+        };
+
+        let mut texture = Texture::new(Vec::new(), [width as _, height as _]);
+        texture.id = Some(texture_id);
 
-        Framebuffer {
+        // Built before checking, so that everything is deleted if it's incomplete
+        let framebuffer = Framebuffer {
             framebuffer_id,
-            texture_id,
+            texture,
             depth_n_stencil_id,
+            bytes_per_pixel,
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(Error::IncompleteFramebuffer(status));
         }
+
+        Ok(framebuffer)
     }
 
     pub fn bind(&self) {
@@ -66,8 +103,24 @@ impl Framebuffer {
         unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0); }
     }
 
+    pub(crate) fn get_id(&self) -> u32 {
+        self.framebuffer_id
+    }
+
     pub fn get_tex_id(&self) -> u32 {
-        self.texture_id
+        self.texture.id.unwrap()
+    }
+
+    /// Returns the colour attachment, which can be sampled like any other loaded texture.
+    pub fn get_tex(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Estimated GPU memory taken up by the colour attachment and the depth and stencil one, which takes 4 bytes per
+    /// pixel.
+    pub(crate) fn gpu_bytes(&self) -> usize {
+        let [width, height] = self.texture.size;
+        (width * height) as usize * (self.bytes_per_pixel + 4)
     }
 }
 
@@ -75,7 +128,6 @@ impl Drop for Framebuffer {
     fn drop(&mut self) {
         unsafe {
             gl::DeleteFramebuffers(1, &self.framebuffer_id);
-            gl::DeleteTextures(1, &self.texture_id);
             gl::DeleteRenderbuffers(1, &self.depth_n_stencil_id);
         }
     }
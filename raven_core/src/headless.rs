@@ -0,0 +1,140 @@
+use std::fmt::Display;
+
+use glutin::{Api, ContextBuilder, GlProfile, GlRequest, PossiblyCurrent};
+use glutin::dpi::PhysicalSize;
+use glutin::platform::unix::HeadlessContextExt;
+use khronos_egl as egl;
+
+use crate::error::{Error, Result};
+use crate::framebuffer::Framebuffer;
+
+// From the EGL_MESA_platform_surfaceless extension
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+/// An OpenGL context that isn't tied to any window, for rendering on machines that have no display.
+///
+/// An EGL surfaceless context is tried first and, if EGL isn't available, an OSMesa one. Both work without a GPU when
+/// backed by Mesa's software rasterizer. Since there's no window, drawing goes to an offscreen framebuffer that is
+/// bound when the context is created and stays bound for as long as the context lives.
+pub struct HeadlessContext {
+    // Declared first so that it's dropped while the context is still current
+    framebuffer: Framebuffer,
+    // Never read but owns the context, which must be kept alive for as long as it's in use
+    #[allow(dead_code)]
+    backend: Backend,
+    size: [u32; 2],
+}
+
+enum Backend {
+    Egl {
+        egl: egl::DynamicInstance<egl::EGL1_5>,
+        display: egl::Display,
+        context: egl::Context,
+    },
+    #[allow(dead_code)]
+    OsMesa(glutin::Context<PossiblyCurrent>),
+}
+
+impl HeadlessContext {
+    /// Creates a new context with an offscreen framebuffer of the given size, makes it current and loads the OpenGL
+    /// function pointers from it.
+    pub fn new(width: u32, height: u32) -> Result<HeadlessContext> {
+        let backend = match Backend::new_egl() {
+            Ok(backend) => backend,
+            Err(egl_err) => Backend::new_osmesa(width, height).map_err(|osmesa_err| {
+                Error::Platform(format!(
+                    "couldn't create a headless context, EGL: {}, OSMesa: {}", egl_err, osmesa_err
+                ))
+            })?,
+        };
+
+        let framebuffer = Framebuffer::new((width as _, height as _))?;
+        framebuffer.bind();
+
+        Ok(HeadlessContext {
+            framebuffer,
+            backend,
+            size: [width, height],
+        })
+    }
+
+    pub fn get_size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    pub fn get_framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+}
+
+impl Backend {
+    fn new_egl() -> Result<Backend> {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required().map_err(platform)? };
+
+        let display = egl.get_platform_display(
+            PLATFORM_SURFACELESS_MESA,
+            egl::DEFAULT_DISPLAY,
+            &[egl::ATTRIB_NONE],
+        ).map_err(platform)?;
+        egl.initialize(display).map_err(platform)?;
+
+        egl.bind_api(egl::OPENGL_API).map_err(platform)?;
+
+        // Surfaceless displays only offer pbuffer configs, while the default would be window ones
+        let config = egl.choose_first_config(display, &[
+            egl::SURFACE_TYPE, egl::PBUFFER_BIT,
+            egl::RENDERABLE_TYPE, egl::OPENGL_BIT,
+            egl::NONE,
+        ]).map_err(platform)?.ok_or_else(|| platform("no suitable EGL config"))?;
+
+        let context = egl.create_context(display, config, None, &[
+            egl::CONTEXT_MAJOR_VERSION, 3,
+            egl::CONTEXT_MINOR_VERSION, 3,
+            egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+            egl::NONE,
+        ]).map_err(platform)?;
+
+        // No surfaces, everything is drawn to the offscreen framebuffer
+        egl.make_current(display, None, None, Some(context)).map_err(platform)?;
+
+        gl::load_with(|symbol| match egl.get_proc_address(symbol) {
+            Some(ptr) => ptr as _,
+            None => std::ptr::null(),
+        });
+
+        Ok(Backend::Egl {
+            egl,
+            display,
+            context,
+        })
+    }
+
+    fn new_osmesa(width: u32, height: u32) -> Result<Backend> {
+        let context = ContextBuilder::new()
+            .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
+            .with_gl_profile(GlProfile::Core)
+            .build_osmesa(PhysicalSize::new(width, height))
+            .map_err(platform)?;
+
+        let context = unsafe { context.make_current().map_err(|(_, err)| platform(err))? };
+
+        gl::load_with(|symbol| context.get_proc_address(symbol));
+
+        Ok(Backend::OsMesa(context))
+    }
+}
+
+fn platform<E: Display>(err: E) -> Error {
+    Error::Platform(err.to_string())
+}
+
+impl Drop for Backend {
+    fn drop(&mut self) {
+        if let Backend::Egl { egl, display, context } = self {
+            // Nothing sensible to do if these fail while dropping
+            let _ = egl.make_current(*display, None, None, None);
+            let _ = egl.destroy_context(*display, *context);
+            let _ = egl.terminate(*display);
+        }
+    }
+}
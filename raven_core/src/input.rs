@@ -0,0 +1,304 @@
+//! What the player is pressing, for interactive scenes.
+//!
+//! The runtime feeds window and device events to the `Input` of the `Processor`, which keeps track of the buttons that
+//! are held and of the ones that changed since the last frame. Games can ask about buttons directly or, better, about
+//! the named actions of an `ActionMap`, so that controls can be rebound without touching what uses them.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glam::Vec2;
+use glutin::event::{DeviceEvent, ElementState, MouseScrollDelta, WindowEvent};
+use serde::{Deserialize, Serialize};
+
+pub use glutin::event::{MouseButton, VirtualKeyCode as Key};
+
+use crate::error::{Error, Result};
+use crate::io::Serializable;
+use crate::path;
+
+/// How many pixels of scrolling on a touchpad make a line of scrolling with a mouse wheel.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Buttons of a standard gamepad, named after their position. Windows don't report gamepads, so these are pressed by
+/// whatever reads them through `Input::press` and `Input::release`, gilrs in the runtime.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Button {
+    Key(Key),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+/// Named actions, such as "jump" or "fire", and the buttons that trigger them. Each project has one, saved as JSON in
+/// `$/input.json`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ActionMap {
+    actions: BTreeMap<String, Vec<Button>>,
+}
+
+impl ActionMap {
+    pub const FILE_NAME: &'static str = "input.json";
+
+    /// The `$/` path of the action map.
+    pub fn path() -> PathBuf {
+        let mut actions_path = PathBuf::new();
+        actions_path.push(path::PROJECT_ROOT_RUNE);
+        actions_path.push(ActionMap::FILE_NAME);
+
+        actions_path
+    }
+
+    /// Loads the action map of the project, or an empty one if it has none.
+    pub fn open<R: AsRef<Path>>(project_root: R) -> Result<ActionMap> {
+        let fs_path = path::as_fs_abs(project_root, ActionMap::path())?;
+
+        match ActionMap::load(&fs_path) {
+            Err(Error::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => Ok(ActionMap::default()),
+            actions => actions,
+        }
+    }
+
+    pub fn save_to<R: AsRef<Path>>(&self, project_root: R) -> Result<()> {
+        self.save(path::as_fs_abs(project_root, ActionMap::path())?)
+    }
+
+    /// Makes the button trigger the action, on top of the buttons that already do.
+    pub fn bind(&mut self, action: &str, button: Button) {
+        let buttons = self.actions.entry(action.to_owned()).or_default();
+
+        if !buttons.contains(&button) {
+            buttons.push(button);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, button: Button) {
+        if let Some(buttons) = self.actions.get_mut(action) {
+            buttons.retain(|bound| *bound != button);
+        }
+    }
+
+    /// The buttons bound to the action, none if there's no such action.
+    pub fn buttons(&self, action: &str) -> &[Button] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item=&str> {
+        self.actions.keys().map(String::as_str)
+    }
+}
+
+/// The state of the buttons and of the mouse, as of the current frame.
+#[derive(Default)]
+pub struct Input {
+    held: HashSet<Button>,
+    /// Since the last frame
+    pressed: HashSet<Button>,
+    released: HashSet<Button>,
+
+    /// In physical pixels from the top left corner of the window
+    mouse_position: Vec2,
+    mouse_delta: Vec2,
+    /// In lines, positive when scrolling up or right
+    scroll: Vec2,
+
+    actions: ActionMap,
+}
+
+impl Input {
+    pub fn new(actions: ActionMap) -> Input {
+        Input {
+            actions,
+            ..Input::default()
+        }
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                if let Some(key) = input.virtual_keycode {
+                    self.set(Button::Key(key), input.state);
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => self.set(Button::Mouse(*button), *state),
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_position = Vec2::new(position.x as f32, position.y as f32);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x, *y),
+                    MouseScrollDelta::PixelDelta(delta) => Vec2::new(delta.x as f32, delta.y as f32) / PIXELS_PER_LINE,
+                };
+            }
+            // Releases go to the window that has the focus, buttons would otherwise stay held forever
+            WindowEvent::Focused(false) => {
+                for button in self.held.clone() {
+                    self.release(button);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        // Unlike the position of the cursor, it keeps changing when the cursor hits the edge of the screen
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            self.mouse_delta += Vec2::new(*x as f32, *y as f32);
+        }
+    }
+
+    pub fn press(&mut self, button: Button) {
+        // Keys held down repeat their presses
+        if self.held.insert(button) {
+            self.pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: Button) {
+        if self.held.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    fn set(&mut self, button: Button, state: ElementState) {
+        match state {
+            ElementState::Pressed => self.press(button),
+            ElementState::Released => self.release(button),
+        }
+    }
+
+    /// Forgets what changed during the frame, to be called once it has been drawn.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.scroll = Vec2::ZERO;
+    }
+
+    pub fn is_held(&self, button: Button) -> bool {
+        self.held.contains(&button)
+    }
+
+    /// Whether the button went down during the frame. It may have been released already.
+    pub fn was_pressed(&self, button: Button) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Whether the button went up during the frame. It may have been pressed again already.
+    pub fn was_released(&self, button: Button) -> bool {
+        self.released.contains(&button)
+    }
+
+    /// Whether any of the buttons bound to the action is held.
+    pub fn is_action_held(&self, action: &str) -> bool {
+        self.actions.buttons(action).iter().any(|&button| self.is_held(button))
+    }
+
+    /// Whether the action started during the frame, pressing a second button bound to it doesn't start it again.
+    pub fn was_action_pressed(&self, action: &str) -> bool {
+        let buttons = self.actions.buttons(action);
+
+        buttons.iter().any(|&button| self.was_pressed(button))
+            && buttons.iter().all(|&button| !self.is_held(button) || self.was_pressed(button))
+    }
+
+    /// Whether the action ended during the frame, when the last button bound to it that was held went up.
+    pub fn was_action_released(&self, action: &str) -> bool {
+        let buttons = self.actions.buttons(action);
+
+        buttons.iter().any(|&button| self.was_released(button)) && !self.is_action_held(action)
+    }
+
+    pub fn mouse_position(&self) -> Vec2 {
+        self.mouse_position
+    }
+
+    /// How far the mouse moved during the frame, in unspecified units that depend on the platform and the mouse.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    /// How much the wheel was turned during the frame, in lines.
+    pub fn scroll(&self) -> Vec2 {
+        self.scroll
+    }
+
+    pub fn actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    pub fn actions_mut(&mut self) -> &mut ActionMap {
+        &mut self.actions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ActionMap, Button, GamepadButton, Input, Key, MouseButton};
+
+    #[test]
+    fn tracks_presses_across_frames() {
+        let mut input = Input::default();
+        let space = Button::Key(Key::Space);
+
+        input.press(space);
+        assert!(input.is_held(space) && input.was_pressed(space));
+
+        // Held down, the key repeats
+        input.end_frame();
+        input.press(space);
+        assert!(input.is_held(space) && !input.was_pressed(space));
+
+        input.release(space);
+        assert!(!input.is_held(space) && input.was_released(space));
+
+        input.end_frame();
+        assert!(!input.was_released(space));
+    }
+
+    #[test]
+    fn triggers_actions_with_any_of_their_buttons() {
+        let mut actions = ActionMap::default();
+        actions.bind("fire", Button::Mouse(MouseButton::Left));
+        actions.bind("fire", Button::Gamepad(GamepadButton::RightTrigger));
+
+        let mut input = Input::new(actions);
+
+        input.press(Button::Mouse(MouseButton::Left));
+        assert!(input.was_action_pressed("fire"));
+
+        // Already firing
+        input.end_frame();
+        input.press(Button::Gamepad(GamepadButton::RightTrigger));
+        assert!(input.is_action_held("fire") && !input.was_action_pressed("fire"));
+
+        input.end_frame();
+        input.release(Button::Mouse(MouseButton::Left));
+        assert!(input.is_action_held("fire") && !input.was_action_released("fire"));
+
+        input.release(Button::Gamepad(GamepadButton::RightTrigger));
+        assert!(!input.is_action_held("fire") && input.was_action_released("fire"));
+
+        assert!(!input.is_action_held("jump"));
+    }
+}
@@ -1,89 +1,611 @@
-use std::error::Error;
 use std::fs::File;
+use std::io::Write;
+use std::mem;
 use std::path::Path;
 
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+use crate::database::Meta;
+use crate::error::{Error, Result};
+use crate::input::ActionMap;
+use crate::manifest::ImportManifest;
 use crate::resource::*;
 
+/// Errors from `save` and `load` carry the path they were given.
 pub trait Serializable: Sized {
-    fn save<P: AsRef<Path>>(&self, at: P) -> Result<(), Box<dyn Error>>;
-    fn load<P: AsRef<Path>>(at: P) -> Result<Self, Box<dyn Error>>;
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()>;
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self>;
 }
 
-fn save_bytes<T: Serialize, P: AsRef<Path>>(self_: &T, at: P) -> Result<(), Box<dyn Error>> {
-    let file = File::with_options()
-        .create(true)
-        .truncate(true)
-        .write(true)
-        .open(at)?;
-    let writer = std::io::BufWriter::new(file);
+fn save_bytes<T: Serialize, P: AsRef<Path>>(self_: &T, at: P) -> Result<()> {
+    let write = || -> Result<()> {
+        let writer = std::io::BufWriter::new(create(&at)?);
+        Ok(bincode::serialize_into(writer, self_)?)
+    };
+
+    write().map_err(|err| err.at(&at))
+}
+
+fn save_text<T: Serialize, P: AsRef<Path>>(self_: &T, at: P) -> Result<()> {
+    let write = || -> Result<()> {
+        let writer = std::io::BufWriter::new(create(&at)?);
+        Ok(serde_json::to_writer(writer, self_)?)
+    };
 
-    bincode::serialize_into(writer, self_).map_err(|err| Box::from(err))
+    write().map_err(|err| err.at(&at))
 }
 
-fn save_text<T: Serialize, P: AsRef<Path>>(self_: &T, at: P) -> Result<(), Box<dyn Error>> {
-    let file = File::with_options()
+fn load_bytes<T: DeserializeOwned, P: AsRef<Path>>(at: P) -> Result<T> {
+    let read = || -> Result<T> {
+        let reader = std::io::BufReader::new(File::with_options().read(true).open(&at)?);
+        Ok(bincode::deserialize_from(reader)?)
+    };
+
+    read().map_err(|err| err.at(&at))
+}
+
+fn load_text<T: DeserializeOwned, P: AsRef<Path>>(at: P) -> Result<T> {
+    let read = || -> Result<T> {
+        let reader = std::io::BufReader::new(File::with_options().read(true).open(&at)?);
+        Ok(serde_json::from_reader(reader)?)
+    };
+
+    read().map_err(|err| err.at(&at))
+}
+
+fn create<P: AsRef<Path>>(at: P) -> std::io::Result<File> {
+    File::with_options()
         .create(true)
         .truncate(true)
         .write(true)
-        .open(at)?;
-    let writer = std::io::BufWriter::new(file);
-
-    serde_json::to_writer(writer, self_).map_err(|err| Box::from(err))
+        .open(at)
 }
 
-fn load_bytes<T: DeserializeOwned, P: AsRef<Path>>(at: P) -> Result<T, Box<dyn Error>> {
-    let file = File::with_options().read(true).open(at)?;
-    let reader = std::io::BufReader::new(file);
+// Textures used to be saved as just their pixels and size. Now they start with this, followed by the version of the
+// format
+const TEXTURE_MAGIC: &[u8; 4] = b"RTEX";
+const TEXTURE_VERSION: u32 = 1;
 
-    bincode::deserialize_from(reader).map_err(|err| Box::from(err))
+#[derive(Deserialize)]
+struct LegacyTexture {
+    raw: Vec<u8>,
+    size: [u32; 2],
 }
 
-fn load_text<T: DeserializeOwned, P: AsRef<Path>>(at: P) -> Result<T, Box<dyn Error>> {
-    let file = File::with_options().read(true).open(at)?;
-    let reader = std::io::BufReader::new(file);
+impl Texture {
+    /// Decodes a texture in the format written by `save`, or in the one that came before texture settings existed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Texture> {
+        let rest = match bytes.strip_prefix(TEXTURE_MAGIC) {
+            Some(rest) => rest,
+            None => {
+                let legacy: LegacyTexture = bincode::deserialize(bytes)?;
+                return Ok(Texture::new(legacy.raw, legacy.size));
+            }
+        };
+
+        let (version, texture): (u32, Texture) = bincode::deserialize(rest)?;
 
-    serde_json::from_reader(reader).map_err(|err| Box::from(err))
+        if version != TEXTURE_VERSION {
+            return Err(Error::decode(format!("unsupported texture version {}", version)));
+        }
+
+        Ok(texture)
+    }
 }
 
 impl Serializable for Texture {
-    fn save<P: AsRef<Path>>(&self, at: P) -> Result<(), Box<dyn Error>> {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
+        let write = || -> Result<()> {
+            let mut writer = std::io::BufWriter::new(create(&at)?);
+
+            writer.write_all(TEXTURE_MAGIC)?;
+            bincode::serialize_into(writer, &(TEXTURE_VERSION, self))?;
+
+            Ok(())
+        };
+
+        write().map_err(|err| err.at(&at))
+    }
+
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
+        std::fs::read(&at)
+            .map_err(Error::from)
+            .and_then(|bytes| Texture::from_bytes(&bytes))
+            .map_err(|err| err.at(&at))
+    }
+}
+
+// Meshes used to be saved as just their vertices and indices, without joints and weights. Now they start with this,
+// followed by the version of the format
+const MESH_MAGIC: &[u8; 4] = b"RMSH";
+const MESH_VERSION: u32 = 3;
+
+// Some were saved with their bounding box after the indices, it's left over and computed again
+#[derive(Deserialize)]
+struct LegacyMesh {
+    vertices: Vec<LegacyVertex>,
+    indices: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+struct LegacyVertex {
+    position: glam::Vec3,
+    normal: glam::Vec3,
+    uv: glam::Vec2,
+}
+
+// Version 1, before vertex layouts
+#[derive(Deserialize)]
+struct MeshV1 {
+    vertices: Vec<VertexV1>,
+    indices: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+struct VertexV1 {
+    position: glam::Vec3,
+    normal: glam::Vec3,
+    uv: glam::Vec2,
+    joints: [u16; 4],
+    weights: [f32; 4],
+}
+
+// Version 2, every vertex with all the attributes, whether they were in the layout or not
+#[derive(Deserialize)]
+struct MeshV2 {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    aabb: Aabb,
+    layout: VertexLayout,
+}
+
+// Each attribute is saved on its own, the ones missing from the layout are left empty
+#[derive(Serialize, Deserialize)]
+struct MeshStreams {
+    indices: Vec<u32>,
+    aabb: Aabb,
+    layout: VertexLayout,
+    positions: Vec<glam::Vec3>,
+    normals: Vec<glam::Vec3>,
+    uvs: Vec<glam::Vec2>,
+    tangents: Vec<glam::Vec3>,
+    bitangents: Vec<glam::Vec3>,
+    colors: Vec<glam::Vec4>,
+    uvs2: Vec<glam::Vec2>,
+    joints: Vec<[u16; 4]>,
+    weights: Vec<[f32; 4]>,
+}
+
+impl MeshStreams {
+    fn from_mesh(mesh: &Mesh) -> MeshStreams {
+        let layout = mesh.layout;
+
+        MeshStreams {
+            indices: mesh.indices.clone(),
+            aabb: mesh.aabb,
+            layout,
+            positions: stream(mesh, true, |vertex| vertex.position),
+            normals: stream(mesh, true, |vertex| vertex.normal),
+            uvs: stream(mesh, layout.uv, |vertex| vertex.uv),
+            tangents: stream(mesh, layout.tangents, |vertex| vertex.tangent),
+            bitangents: stream(mesh, layout.tangents, |vertex| vertex.bitangent),
+            colors: stream(mesh, layout.colors, |vertex| vertex.color),
+            uvs2: stream(mesh, layout.uv2, |vertex| vertex.uv2),
+            joints: stream(mesh, layout.skin, |vertex| vertex.joints),
+            weights: stream(mesh, layout.skin, |vertex| vertex.weights),
+        }
+    }
+
+    fn into_mesh(self) -> Result<Mesh> {
+        let count = self.positions.len();
+        let layout = self.layout;
+
+        let streams = [
+            (true, self.normals.len()),
+            (layout.uv, self.uvs.len()),
+            (layout.tangents, self.tangents.len()),
+            (layout.tangents, self.bitangents.len()),
+            (layout.colors, self.colors.len()),
+            (layout.uv2, self.uvs2.len()),
+            (layout.skin, self.joints.len()),
+            (layout.skin, self.weights.len()),
+        ];
+
+        if streams.iter().any(|&(enabled, len)| len != if enabled { count } else { 0 }) {
+            return Err(Error::decode("the attributes of the mesh don't match its layout"));
+        }
+
+        let mut vertices: Vec<Vertex> = self.positions
+            .into_iter()
+            .zip(self.normals)
+            .map(|(position, normal)| Vertex::new(position, normal, glam::Vec2::ZERO))
+            .collect();
+
+        // Streams of the attributes that aren't in the layout are empty, those keep their defaults
+        for (vertex, uv) in vertices.iter_mut().zip(self.uvs) {
+            vertex.uv = uv;
+        }
+
+        for (vertex, (tangent, bitangent)) in vertices.iter_mut().zip(self.tangents.into_iter().zip(self.bitangents)) {
+            vertex.tangent = tangent;
+            vertex.bitangent = bitangent;
+        }
+
+        for (vertex, color) in vertices.iter_mut().zip(self.colors) {
+            vertex.color = color;
+        }
+
+        for (vertex, uv2) in vertices.iter_mut().zip(self.uvs2) {
+            vertex.uv2 = uv2;
+        }
+
+        for (vertex, (joints, weights)) in vertices.iter_mut().zip(self.joints.into_iter().zip(self.weights)) {
+            vertex.joints = joints;
+            vertex.weights = weights;
+        }
+
+        Ok(Mesh {
+            vertices,
+            indices: self.indices,
+            aabb: self.aabb,
+            layout,
+        })
+    }
+}
+
+/// One attribute of every vertex, or nothing if the layout doesn't have it.
+fn stream<T>(mesh: &Mesh, enabled: bool, attribute: fn(&Vertex) -> T) -> Vec<T> {
+    if enabled {
+        mesh.vertices.iter().map(attribute).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+impl Mesh {
+    /// Decodes a mesh in the format written by `save`, in the ones that saved every attribute of every vertex or came
+    /// before vertex layouts, or in the one that came before skinning existed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Mesh> {
+        let rest = match bytes.strip_prefix(MESH_MAGIC) {
+            Some(rest) => rest,
+            None => {
+                let legacy: LegacyMesh = bincode::deserialize(bytes)?;
+
+                let vertices = legacy.vertices
+                    .into_iter()
+                    .map(|vertex| Vertex::new(vertex.position, vertex.normal, vertex.uv))
+                    .collect();
+
+                return Ok(Mesh::new(vertices, legacy.indices));
+            }
+        };
+
+        // Written as a tuple of the two, so the mesh follows the version
+        let version: u32 = bincode::deserialize(rest)?;
+        let rest = &rest[mem::size_of::<u32>()..];
+
+        match version {
+            1 => {
+                let v1: MeshV1 = bincode::deserialize(rest)?;
+
+                let vertices = v1.vertices
+                    .into_iter()
+                    .map(|vertex| Vertex {
+                        joints: vertex.joints,
+                        weights: vertex.weights,
+                        ..Vertex::new(vertex.position, vertex.normal, vertex.uv)
+                    })
+                    .collect();
+
+                Ok(Mesh::new(vertices, v1.indices))
+            }
+            2 => {
+                let v2: MeshV2 = bincode::deserialize(rest)?;
+
+                Ok(Mesh {
+                    vertices: v2.vertices,
+                    indices: v2.indices,
+                    aabb: v2.aabb,
+                    layout: v2.layout,
+                })
+            }
+            MESH_VERSION => bincode::deserialize::<MeshStreams>(rest)?.into_mesh(),
+            _ => Err(Error::decode(format!("unsupported mesh version {}", version))),
+        }
+    }
+}
+
+impl Serializable for Mesh {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
+        let write = || -> Result<()> {
+            let mut writer = std::io::BufWriter::new(create(&at)?);
+
+            writer.write_all(MESH_MAGIC)?;
+            bincode::serialize_into(writer, &(MESH_VERSION, MeshStreams::from_mesh(self)))?;
+
+            Ok(())
+        };
+
+        write().map_err(|err| err.at(&at))
+    }
+
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
+        std::fs::read(&at)
+            .map_err(Error::from)
+            .and_then(|bytes| Mesh::from_bytes(&bytes))
+            .map_err(|err| err.at(&at))
+    }
+}
+
+impl Serializable for Skeleton {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
+        save_text(self, at)
+    }
+
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
+        load_text(at)
+    }
+}
+
+impl Serializable for AnimationClip {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
         save_bytes(self, at)
     }
 
-    fn load<P: AsRef<Path>>(at: P) -> Result<Self, Box<dyn Error>> {
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
         load_bytes(at)
     }
 }
 
-impl Serializable for Mesh {
-    fn save<P: AsRef<Path>>(&self, at: P) -> Result<(), Box<dyn Error>> {
+impl Serializable for KeyframeClip {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
         save_bytes(self, at)
     }
 
-    fn load<P: AsRef<Path>>(at: P) -> Result<Self, Box<dyn Error>> {
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
         load_bytes(at)
     }
 }
 
 impl Serializable for Material {
-    fn save<P: AsRef<Path>>(&self, at: P) -> Result<(), Box<dyn Error>> {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
         save_text(self, at)
     }
 
-    fn load<P: AsRef<Path>>(at: P) -> Result<Self, Box<dyn Error>> {
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
+        load_text(at)
+    }
+}
+
+// Plain text, so that scripts can be written with any editor
+impl Serializable for Script {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
+        std::fs::write(&at, &self.source).map_err(|err| Error::from(err).at(&at))
+    }
+
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
+        std::fs::read_to_string(&at)
+            .map(|source| Script { source })
+            .map_err(|err| Error::from(err).at(&at))
+    }
+}
+
+impl Serializable for RenderTexture {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
+        save_text(self, at)
+    }
+
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
         load_text(at)
     }
 }
 
 impl Serializable for Scene {
-    fn save<P: AsRef<Path>>(&self, at: P) -> Result<(), Box<dyn Error>> {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
+        save_text(self, at)
+    }
+
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
+        load_text(at)
+    }
+}
+
+impl Serializable for Meta {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
         save_text(self, at)
     }
 
-    fn load<P: AsRef<Path>>(at: P) -> Result<Self, Box<dyn Error>> {
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
         load_text(at)
     }
 }
+
+impl Serializable for ActionMap {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
+        save_text(self, at)
+    }
+
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
+        load_text(at)
+    }
+}
+
+impl Serializable for ImportManifest {
+    fn save<P: AsRef<Path>>(&self, at: P) -> Result<()> {
+        save_text(self, at)
+    }
+
+    fn load<P: AsRef<Path>>(at: P) -> Result<Self> {
+        load_text(at)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Serialize;
+
+    use crate::error::Error;
+    use crate::glam::{Vec2, Vec3, Vec4};
+    use crate::resource::{Aabb, Mesh, Scene, Texture, TextureFormat, Vertex, VertexLayout};
+
+    use super::{Serializable, MESH_MAGIC};
+
+    #[derive(Serialize)]
+    struct LegacyTexture {
+        raw: Vec<u8>,
+        size: [u32; 2],
+    }
+
+    #[test]
+    fn loads_legacy_textures() {
+        let bytes = bincode::serialize(&LegacyTexture { raw: vec![1, 2, 3, 4], size: [1, 1] }).unwrap();
+
+        let texture = Texture::from_bytes(&bytes).unwrap();
+
+        assert_eq!(texture.raw, vec![1, 2, 3, 4]);
+        assert_eq!(texture.size, [1, 1]);
+        assert_eq!(texture.format, TextureFormat::Rgba8);
+        assert!(texture.mips.is_empty());
+    }
+
+    #[derive(Serialize)]
+    struct LegacyMesh {
+        vertices: Vec<(Vec3, Vec3, Vec2)>,
+        indices: Vec<u32>,
+    }
+
+    #[test]
+    fn loads_legacy_meshes() {
+        let legacy = LegacyMesh {
+            vertices: vec![(Vec3::X, Vec3::Y, Vec2::ONE), (Vec3::Z, Vec3::Y, Vec2::ONE)],
+            indices: vec![0, 1, 1],
+        };
+
+        let mut bytes = bincode::serialize(&legacy).unwrap();
+        let mesh = Mesh::from_bytes(&bytes).unwrap();
+
+        assert_eq!(mesh.vertices[0], Vertex::new(Vec3::X, Vec3::Y, Vec2::ONE));
+        assert_eq!(mesh.indices, legacy.indices);
+        assert_eq!(mesh.aabb, Aabb { min: Vec3::ZERO, max: Vec3::new(1.0, 0.0, 1.0) });
+        assert!(!mesh.is_skinned());
+
+        // Followed by a bounding box, which is ignored
+        bytes.extend(bincode::serialize(&Aabb { min: Vec3::ZERO, max: Vec3::ZERO }).unwrap());
+        assert_eq!(Mesh::from_bytes(&bytes).unwrap().aabb, mesh.aabb);
+    }
+
+    #[derive(Serialize)]
+    struct MeshV1 {
+        vertices: Vec<(Vec3, Vec3, Vec2, [u16; 4], [f32; 4])>,
+        indices: Vec<u32>,
+        aabb: Aabb,
+    }
+
+    #[test]
+    fn loads_version_1_meshes() {
+        let v1 = MeshV1 {
+            vertices: vec![(Vec3::X, Vec3::Y, Vec2::ONE, [3, 0, 0, 0], [1.0, 0.0, 0.0, 0.0])],
+            indices: vec![0, 0, 0],
+            aabb: Aabb { min: Vec3::X, max: Vec3::X },
+        };
+
+        let mut bytes = MESH_MAGIC.to_vec();
+        bytes.extend(bincode::serialize(&(1_u32, v1)).unwrap());
+
+        let mesh = Mesh::from_bytes(&bytes).unwrap();
+
+        assert_eq!(mesh.vertices[0].joints, [3, 0, 0, 0]);
+        assert_eq!(mesh.vertices[0].color, Vec4::ONE);
+        assert_eq!(mesh.layout, VertexLayout { skin: true, ..VertexLayout::default() });
+    }
+
+    #[derive(Serialize)]
+    struct MeshV2 {
+        vertices: Vec<Vertex>,
+        indices: Vec<u32>,
+        aabb: Aabb,
+        layout: VertexLayout,
+    }
+
+    #[test]
+    fn loads_version_2_meshes() {
+        let mut vertex = Vertex::new(Vec3::X, Vec3::Y, Vec2::ONE);
+        vertex.color = Vec4::new(1.0, 0.0, 0.0, 1.0);
+
+        let v2 = MeshV2 {
+            vertices: vec![vertex],
+            indices: vec![0, 0, 0],
+            aabb: Aabb { min: Vec3::X, max: Vec3::X },
+            layout: VertexLayout { colors: true, ..VertexLayout::default() },
+        };
+
+        let mut bytes = MESH_MAGIC.to_vec();
+        bytes.extend(bincode::serialize(&(2_u32, &v2)).unwrap());
+
+        let mesh = Mesh::from_bytes(&bytes).unwrap();
+
+        assert_eq!(mesh.vertices, v2.vertices);
+        assert_eq!(mesh.layout, v2.layout);
+    }
+
+    #[test]
+    fn saves_only_the_attributes_in_the_layout() {
+        let mut vertex = Vertex::new(Vec3::X, Vec3::Y, Vec2::ONE);
+        vertex.color = Vec4::new(1.0, 0.0, 0.0, 1.0);
+        vertex.uv2 = Vec2::new(0.5, 0.5);
+
+        let mut mesh = Mesh::new(vec![vertex; 3], vec![0, 1, 2]);
+        mesh.layout = VertexLayout { colors: true, ..VertexLayout::default() };
+
+        let dir = std::env::temp_dir().join(format!("raven_io_test_mesh_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("round_trip.mesh");
+
+        mesh.save(&path).unwrap();
+        let size = std::fs::metadata(&path).unwrap().len();
+        let loaded = Mesh::load(&path).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // The second UVs aren't in the layout, so they weren't saved and come back as the default
+        assert_eq!(loaded.vertices[0].color, vertex.color);
+        assert_eq!(loaded.vertices[0].uv2, Vec2::ZERO);
+        assert_eq!(loaded.layout, mesh.layout);
+
+        // Less than just the vertices took up when they were saved whole
+        let whole = bincode::serialized_size(&mesh.vertices).unwrap();
+        assert!(size < whole, "{} bytes, {} with every attribute", size, whole);
+    }
+
+    #[test]
+    fn round_trips_textures() {
+        let mut texture = Texture::new(vec![0; 16 * 4], [4, 4]);
+        texture.format = TextureFormat::Bc1;
+        texture.raw = vec![7; 8];
+        texture.mips = vec![vec![8; 8], vec![9; 8]];
+
+        let dir = std::env::temp_dir().join(format!("raven_io_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("round_trip.tex");
+
+        texture.save(&path).unwrap();
+        let loaded = Texture::load(&path).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.raw, texture.raw);
+        assert_eq!(loaded.format, TextureFormat::Bc1);
+        assert_eq!(loaded.mips, texture.mips);
+    }
+
+    #[test]
+    fn errors_carry_the_path() {
+        let path = std::env::temp_dir().join("raven_io_test_missing.scn");
+
+        match Scene::load(&path) {
+            Err(Error::Io { path: Some(err_path), .. }) => assert_eq!(err_path, path),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
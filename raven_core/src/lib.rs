@@ -1,27 +1,41 @@
 #![feature(with_options)]
 #![feature(duration_constants)]
 
-use std::error::Error;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::result::Result;
+use std::rc::Rc;
+use std::time::Duration;
 
 use gl;
 pub use glam;
-use glam::{Mat4, Quat, Vec3};
+use glam::{Mat4, Quat, Vec3, Vec4};
+pub use image;
+use image::RgbaImage;
 pub use mat4;
 use mat4::decompose;
 
 use ecs::*;
 
-use crate::component::{CameraComponent, HierarchyComponent, MeshComponent, SceneComponent, TransformComponent};
+use crate::component::{AnimatorComponent, CameraComponent, HierarchyComponent, KeyframeAnimatorComponent, LodGroupComponent, MeshComponent, MeshTexture, SceneComponent, ScriptComponent, TransformComponent};
+use crate::framebuffer::Framebuffer;
 use crate::io::Serializable;
-use crate::resource::{Material, Mesh, Scene, Texture};
+use crate::resource::{Aabb, BlendMode, RenderTexture, Scene, Skeleton, Texture};
 use crate::shader::Shader;
 use crate::standard_shader::get_standard_shader;
 use crate::vao::Vao;
+use crate::culling::Frustum;
+use crate::time::CullingStats;
+use crate::cache::{AssetCache, LoadProgress, MemoryStats};
+use crate::database::{AssetDatabase, AssetRef};
+use crate::input::{ActionMap, Input};
+use crate::postprocess::PostProcessor;
+use crate::script::ScriptHost;
 
 use crate::skybox::Skybox;
 
+pub use crate::error::{Error, Result};
+
 pub mod ecs {
     pub use raven_ecs::*;
 }
@@ -32,17 +46,36 @@ pub mod io;
 pub mod path;
 pub mod framebuffer;
 pub mod time;
+pub mod headless;
+pub mod cache;
+pub mod postprocess;
+pub mod shader;
+pub mod bake;
+pub mod watch;
+pub mod database;
+pub mod manifest;
+pub mod animation;
+pub mod primitive;
+pub mod input;
+pub mod script;
 
+mod error;
 mod vao;
 mod tex;
-mod shader;
 mod standard_shader;
 mod skybox;
+mod culling;
+mod loader;
 
 pub struct Processor {
     state: ProcessorState,
     skybox: Skybox,
+    post: PostProcessor,
     scene: Option<Scene>,
+    input: Input,
+    // Why the action map couldn't be loaded the last time it was, until it's taken
+    actions_error: Option<Error>,
+    scripts: ScriptHost,
 }
 
 pub(crate) struct ProcessorState {
@@ -50,42 +83,235 @@ pub(crate) struct ProcessorState {
     canvas_size: [u32; 2],
     shader: Shader,
     camera_mats: Option<CameraMats>,
+
+    // Framebuffers for the render textures that cameras are drawing to, keyed by the path of the `.rtex` asset
+    render_targets: HashMap<PathBuf, Framebuffer>,
+    // Render texture currently being drawn to, None when drawing to the canvas
+    current_target: Option<PathBuf>,
+
+    culling_stats: CullingStats,
+    // Seen before they were drawn during the last frame
+    render_texture_cycles: Vec<PathBuf>,
+
+    assets: AssetCache,
+    // Resolves the references between assets to their `$/` paths
+    db: AssetDatabase,
+
+    // Meshes to draw with the current camera
+    queue: Vec<RenderItem>,
+    instancing: bool,
+    // Drawn in place of the meshes that are still loading
+    placeholder: Rc<Vao>,
+}
+
+impl ProcessorState {
+    /// The size of what the camera draws to, in pixels. Its render target has to be prepared already.
+    fn target_size(&self, camera: &Camera) -> [u32; 2] {
+        match &camera.target {
+            Some(target) => self.render_targets[target].get_tex().size,
+            None => self.canvas_size,
+        }
+    }
 }
 
 struct CameraMats {
     view_mat: Mat4,
     projection_mat: Mat4,
+    frustum: Frustum,
+    // In world space
+    position: Vec3,
+}
+
+impl CameraMats {
+    /// How much of the viewport's height the sphere around a world space box covers.
+    fn screen_size(&self, aabb: &Aabb) -> f32 {
+        let center = (aabb.min + aabb.max) / 2.0;
+        let radius = (aabb.max - aabb.min).length() / 2.0;
+
+        // Distance along the view direction for perspective projections, always 1 for orthographic ones
+        let w = (self.projection_mat * self.view_mat * center.extend(1.0)).w;
+
+        if w <= f32::EPSILON {
+            // Around or behind the camera
+            return f32::INFINITY;
+        }
+
+        // The viewport is 2 * w / projection[1][1] tall at that distance
+        radius * self.projection_mat.y_axis.y / w
+    }
 }
 
 #[derive(Debug)]
 pub enum FrameError {
     NoCamera,
-    Generic(Box<dyn Error>)
+    Generic(Error)
 }
 
 impl Processor {
-    pub fn new<R: AsRef<Path>>(project_root: R) -> Result<Processor, Box<dyn Error>> {
+    pub fn new<R: AsRef<Path>>(project_root: R) -> Result<Processor> {
         let skybox = Skybox::load()?;
 
+        // A broken action map shouldn't keep the scene from being shown, nothing is bound until it's fixed
+        let (actions, actions_error) = match ActionMap::open(&project_root) {
+            Ok(actions) => (actions, None),
+            Err(err) => (ActionMap::default(), Some(err)),
+        };
+
         Ok(Processor {
             state: ProcessorState {
                 project_root: project_root.as_ref().to_owned(),
                 canvas_size: [800, 400],
                 shader: get_standard_shader()?,
                 camera_mats: None,
+
+                render_targets: HashMap::new(),
+                current_target: None,
+
+                culling_stats: CullingStats::default(),
+                render_texture_cycles: Vec::new(),
+
+                assets: AssetCache::new(&project_root),
+                db: AssetDatabase::open(&project_root)?,
+
+                queue: Vec::new(),
+                instancing: true,
+                placeholder: Rc::new(Vao::from(&primitive::cube(1.0))?),
             },
             scene: None,
             skybox,
+            post: PostProcessor::new(),
+            input: Input::new(actions),
+            actions_error,
+            scripts: ScriptHost::new(),
         })
     }
 
-    pub fn load_scene<P: AsRef<Path>>(&mut self, scene_path: P) -> Result<(), Box<dyn Error>> {
-        let scene_path = path::as_fs_abs(&self.state.project_root, scene_path);
+    /// Loads the scene at the given `$/` path in place of the current one, which is kept if the new one can't be
+    /// loaded.
+    pub fn load_scene<P: AsRef<Path>>(&mut self, scene_path: P) -> Result<()> {
+        let scene_path = path::as_fs_abs(&self.state.project_root, scene_path)?;
 
         self.scene = Some(Scene::load(scene_path)?);
+
+        // Assets only used by the previous scene have been freed when it was dropped
+        self.state.assets.purge();
+
         Ok(())
     }
 
+    /// Reloads the assets at the given `$/` paths, usually the ones reported as changed by a `watch::ProjectWatcher`.
+    ///
+    /// Meshes, materials and textures are loaded again the next time they're drawn, and so are the scenes nested with
+    /// a `SceneComponent`. Scripts start over with their new source. The loaded scene itself is left alone, so that
+    /// changes made to it aren't lost. The asset database is read again too, in case assets were moved, and so is the
+    /// action map of the project, unless it can't be loaded, see `take_actions_error`.
+    pub fn reload_assets(&mut self, changed: &[PathBuf]) -> Result<()> {
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        self.state.db.rescan()?;
+
+        if changed.contains(&ActionMap::path()) {
+            match ActionMap::open(&self.state.project_root) {
+                Ok(actions) => *self.input.actions_mut() = actions,
+                Err(err) => self.actions_error = Some(err),
+            }
+        }
+
+        for path in changed {
+            self.state.assets.invalidate(path);
+            // Recreated with the new size on the next frame
+            self.state.render_targets.remove(path);
+        }
+
+        if let Some(scene) = self.scene.as_mut() {
+            Processor::invalidate_assets(scene, changed, &self.state.db);
+        }
+
+        Ok(())
+    }
+
+    fn invalidate_assets(scene: &mut Scene, changed: &[PathBuf], db: &AssetDatabase) {
+        let is_changed = |asset: &AssetRef| changed.iter().any(|path| path == db.resolve(asset));
+
+        for (_, (mut scene_comp, ), _)
+        in <(SceneComponent, )>::query_shallow_mut(scene) {
+            if matches!(scene_comp.scene.as_ref(), Some(scene) if is_changed(scene)) {
+                // Loaded again by `load_downstream_scenes`
+                scene_comp.loaded = None;
+            } else if let Some(loaded) = scene_comp.loaded.as_mut() {
+                Processor::invalidate_assets(loaded, changed, db);
+            }
+        }
+
+        for (_, (mut mesh_comp, ), _)
+        in <(MeshComponent, )>::query_deep_mut(scene) {
+            if is_changed(&mesh_comp.mesh) {
+                mesh_comp.vao = None;
+            }
+
+            // LODs could have been removed since their vertex arrays were requested, `load_meshes` drops the extra ones
+            let mesh_comp = &mut *mesh_comp;
+
+            for (lod, lod_vao) in mesh_comp.lods.iter().zip(&mut mesh_comp.lod_vaos) {
+                if is_changed(lod) {
+                    *lod_vao = None;
+                }
+            }
+
+            if is_changed(&mesh_comp.mat) {
+                mesh_comp.material = None;
+                mesh_comp.tex = None;
+            }
+
+            let tex_path = mesh_comp.material.as_ref().and_then(|mat| mat.tex.as_ref());
+            let tex_changed = matches!(tex_path, Some(tex) if is_changed(tex));
+
+            if tex_changed && matches!(mesh_comp.tex, Some(MeshTexture::Loaded(_))) {
+                mesh_comp.tex = None;
+            }
+        }
+
+        for (_, (mut animator_comp, ), _)
+        in <(AnimatorComponent, )>::query_shallow_mut(scene) {
+            if matches!(animator_comp.skeleton.as_ref(), Some(skeleton) if is_changed(skeleton)) {
+                animator_comp.loaded_skeleton = None;
+            }
+
+            for layer in &mut animator_comp.layers {
+                if is_changed(&layer.clip) {
+                    layer.loaded = None;
+                }
+            }
+        }
+
+        for (_, (mut keyframe_comp, ), _)
+        in <(KeyframeAnimatorComponent, )>::query_deep_mut(scene) {
+            if matches!(keyframe_comp.clip.as_ref(), Some(clip) if is_changed(clip)) {
+                keyframe_comp.loaded = None;
+            }
+        }
+
+        for (_, (mut script_comp, ), _)
+        in <(ScriptComponent, )>::query_deep_mut(scene) {
+            // Starts over, even if it had stopped because of an error
+            if matches!(script_comp.script.as_ref(), Some(script) if is_changed(script)) {
+                script_comp.loaded = None;
+                script_comp.instance = None;
+                script_comp.stopped = false;
+            }
+        }
+    }
+
+    pub fn get_asset_db(&self) -> &AssetDatabase {
+        &self.state.db
+    }
+
+    pub fn get_asset_db_mut(&mut self) -> &mut AssetDatabase {
+        &mut self.state.db
+    }
+
     pub fn get_scene(&self) -> Option<&Scene> {
         self.scene.as_ref()
     }
@@ -94,20 +320,148 @@ impl Processor {
         self.scene.as_mut()
     }
 
+    pub fn get_input(&self) -> &Input {
+        &self.input
+    }
+
+    /// For the window to feed its events to, and to call `Input::end_frame` on after every frame.
+    pub fn get_input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
+    /// Why the action map of the project couldn't be loaded, if it couldn't since this was last called. The processor
+    /// starts with an empty map in its place, and keeps the previous one when it's reloaded.
+    pub fn take_actions_error(&mut self) -> Option<Error> {
+        self.actions_error.take()
+    }
+
+    /// Moves the animations of the scene, and of the scenes nested in it, forward by the time since the last frame.
+    pub fn animate(&mut self, delta: Duration) {
+        if let Some(scene) = self.scene.as_mut() {
+            Processor::animate_scene(scene, delta.as_secs_f32());
+        }
+    }
+
+    fn animate_scene(scene: &mut Scene, delta: f32) {
+        for (_, (mut scene_comp, ), _)
+        in <(SceneComponent, )>::query_shallow_mut(scene) {
+            if let Some(loaded) = scene_comp.loaded.as_mut() {
+                Processor::animate_scene(loaded, delta);
+            }
+        }
+
+        for (_, (mut animator_comp, ), _)
+        in <(AnimatorComponent, )>::query_shallow_mut(scene) {
+            animator_comp.advance(delta);
+        }
+
+        // Clips are applied once every animator has moved on, since applying them needs the whole scene
+        let mut playing = Vec::new();
+
+        for (entity, (mut keyframe_comp, ), _)
+        in <(KeyframeAnimatorComponent, )>::query_deep_mut(scene) {
+            if let Some(time) = keyframe_comp.advance(delta) {
+                playing.push((entity, keyframe_comp.loaded.clone().unwrap(), time));
+            }
+        }
+
+        for (entity, clip, time) in playing {
+            animation::apply_clip(scene, entity, &clip, time);
+        }
+    }
+
+    /// Runs the scripts of the scene, and of the scenes nested in it, for a frame that came the given time after the
+    /// last one. Scripts start once they're loaded. The ones that fail report their error here, once, and stop.
+    pub fn run_scripts(&mut self, delta: Duration) -> Vec<Error> {
+        let mut errors = Vec::new();
+
+        if let Some(scene) = self.scene.as_mut() {
+            Processor::run_scene_scripts(scene, &mut self.state, &self.scripts, &mut self.input, delta.as_secs_f32(), &mut errors);
+        }
+
+        errors
+    }
+
+    fn run_scene_scripts(scene: &mut Scene, state: &mut ProcessorState, scripts: &ScriptHost, input: &mut Input, delta: f32, errors: &mut Vec<Error>) {
+        for (_, (mut scene_comp, ), _)
+        in <(SceneComponent, )>::query_shallow_mut(scene) {
+            if let Some(loaded) = scene_comp.loaded.as_mut() {
+                Processor::run_scene_scripts(loaded, state, scripts, input, delta, errors);
+            }
+        }
+
+        for (_, (mut script_comp, ), _)
+        in <(ScriptComponent, )>::query_deep_mut(scene) {
+            if script_comp.loaded.is_some() || script_comp.stopped {
+                continue;
+            }
+
+            let script = match script_comp.script.as_ref() {
+                Some(script) => state.db.resolve(script),
+                None => continue,
+            };
+
+            match state.assets.request_script(script) {
+                Ok(loaded) => script_comp.loaded = loaded,
+                Err(err) => {
+                    script_comp.stopped = true;
+                    errors.push(err);
+                }
+            }
+        }
+
+        scripts.run(scene, input, &mut state.db, delta, errors);
+    }
+
     pub fn set_canvas_size(&mut self, width: u32, height: u32) {
         self.state.canvas_size = [width, height];
     }
 
+    /// Returns how many meshes were drawn and how many were culled during the last frame, summed over all cameras.
+    pub fn get_culling_stats(&self) -> CullingStats {
+        self.state.culling_stats
+    }
+
+    /// Render textures that were seen before they were drawn during the last frame, because the cameras drawing them
+    /// see each other's textures in a cycle. They show what was drawn into them the frame before.
+    pub fn get_render_texture_cycles(&self) -> &[PathBuf] {
+        &self.state.render_texture_cycles
+    }
+
+    /// Returns how much GPU memory is taken up by the meshes, textures and render textures currently in use.
+    pub fn get_memory_stats(&self) -> MemoryStats {
+        self.state.assets.memory_stats(self.state.render_targets.values(), self.post.get_targets())
+    }
+
+    /// Returns how many of the assets used by the current scene have been loaded and how many are still pending.
+    pub fn get_load_progress(&self) -> LoadProgress {
+        self.state.assets.load_progress()
+    }
+
+    /// Whether to load assets on background threads, drawing the scene without them until they're ready, or to load
+    /// them as soon as they're needed and stall the frame. Enabled by default.
+    pub fn set_streaming(&mut self, streaming: bool) {
+        self.state.assets.set_streaming(streaming);
+    }
+
+    /// Sets roughly how many bytes of meshes and textures are uploaded to the GPU every frame when streaming.
+    pub fn set_upload_budget(&mut self, bytes: usize) {
+        self.state.assets.set_upload_budget(bytes);
+    }
+
     pub fn do_frame(&mut self) -> Result<(), FrameError> {
-        clear_canvas();
+        let [width, height] = self.state.canvas_size;
 
-        {
-            let [width, height] = self.state.canvas_size;
-            unsafe {
-                gl::Viewport(0, 0, width as _, height as _);
-            }
+        self.state.assets.update();
+
+        self.state.culling_stats = CullingStats::default();
+
+        unsafe {
+            gl::Viewport(0, 0, width as _, height as _);
         }
 
+        clear_canvas();
+
         if self.scene.is_none() {
             return Ok(());
         }
@@ -117,20 +471,140 @@ impl Processor {
         }
 
         Processor::load_downstream_scenes(self.scene.as_mut().unwrap(), &self.state).map_err(|err| FrameError::Generic(err))?;
+        Processor::load_keyframe_clips(self.scene.as_mut().unwrap(), &mut self.state).map_err(|err| FrameError::Generic(err))?;
+        Processor::pose_animators(self.scene.as_mut().unwrap(), &mut self.state).map_err(|err| FrameError::Generic(err))?;
+        Processor::load_meshes(self.scene.as_mut().unwrap(), &mut self.state).map_err(|err| FrameError::Generic(err))?;
 
-        self.state.camera_mats = Some(
-            compute_camera_mats(self.scene.as_ref().unwrap(), Mat4::default(), &self.state.canvas_size)
-            .ok_or_else(|| FrameError::NoCamera)?
-        );
+        let mut cameras = Vec::new();
+        collect_cameras(self.scene.as_ref().unwrap(), &self.state.db, Mat4::default(), &mut cameras).map_err(|err| FrameError::Generic(err))?;
+
+        if cameras.is_empty() {
+            return Err(FrameError::NoCamera);
+        }
 
-        self.skybox.draw(self.state.camera_mats.as_ref().unwrap());
+        // Whatever framebuffer the caller has bound is the canvas
+        let canvas_framebuffer = bound_framebuffer();
 
-        Processor::process_scene(self.scene.as_mut().unwrap(), &mut self.state, Mat4::default()).map_err(|err| FrameError::Generic(err))?;
+        Processor::prepare_render_targets(&cameras, &mut self.state).map_err(|err| FrameError::Generic(err))?;
+
+        // Cameras drawing to a render texture go before the cameras that see it, so that they see the current frame
+        let mut surfaces = Vec::new();
+        collect_render_texture_surfaces(self.scene.as_ref().unwrap(), Mat4::default(), &mut surfaces).map_err(|err| FrameError::Generic(err))?;
+
+        let seen: Vec<_> = cameras.iter().map(|camera| {
+            let [_, _, viewport_width, viewport_height] = camera.viewport_px(&self.state.target_size(camera));
+            let CameraMats { frustum, .. } = camera.mats(viewport_width as f32 / viewport_height.max(1) as f32);
+
+            let mut seen: Vec<PathBuf> = Vec::new();
+
+            for surface in &surfaces {
+                let visible = surface.bounds.as_ref().map_or(true, |bounds| frustum.intersects(bounds));
+
+                if visible && !seen.contains(&surface.texture) {
+                    seen.push(surface.texture.clone());
+                }
+            }
+
+            seen
+        }).collect();
+
+        let (cameras, cycles) = sort_cameras(cameras, &seen);
+        self.state.render_texture_cycles = cycles;
+
+        for camera in cameras {
+            let target_framebuffer = match &camera.target {
+                Some(target) => self.state.render_targets[target].get_id(),
+                None => canvas_framebuffer,
+            };
+
+            let viewport = camera.viewport_px(&self.state.target_size(&camera));
+            let [_, _, viewport_width, viewport_height] = viewport;
+
+            // A camera whose viewport lies outside of the canvas has nothing to draw
+            if viewport_width <= 0 || viewport_height <= 0 {
+                continue;
+            }
+
+            // The scene is drawn in HDR, then the post-processing stack resolves it into the camera's viewport
+            let scene_framebuffer = self.post
+                .get_scene_target([viewport_width as _, viewport_height as _])
+                .map_err(FrameError::Generic)?;
+            scene_framebuffer.bind();
+
+            unsafe {
+                gl::Viewport(0, 0, viewport_width, viewport_height);
+            }
+
+            clear_viewport([0, 0, viewport_width, viewport_height], camera.comp.clear_color);
+
+            self.state.camera_mats = Some(camera.mats(viewport_width as f32 / viewport_height as f32));
+            self.state.current_target = camera.target.clone();
+
+            if camera.comp.skybox {
+                self.skybox.draw(self.state.camera_mats.as_ref().unwrap());
+            }
+
+            Processor::process_scene(self.scene.as_ref().unwrap(), &mut self.state, Mat4::default()).map_err(|err| FrameError::Generic(err))?;
+            Processor::draw_queue(&mut self.state);
+
+            self.post.resolve(&camera.comp.post, scene_framebuffer.get_tex(), target_framebuffer, viewport)
+                .map_err(|err| FrameError::Generic(err))?;
+        }
+
+        self.post.end_frame();
+        self.state.current_target = None;
+
+        bind_framebuffer(canvas_framebuffer);
+
+        unsafe {
+            gl::Viewport(0, 0, width as _, height as _);
+        }
+
+        Ok(())
+    }
+
+    /// Makes sure that every render texture targeted by a camera has a framebuffer to draw into.
+    fn prepare_render_targets(cameras: &[Camera], state: &mut ProcessorState) -> Result<()> {
+        for camera in cameras {
+            let target = match &camera.target {
+                Some(target) => target,
+                None => continue,
+            };
+
+            if state.render_targets.contains_key(target) {
+                continue;
+            }
+
+            let render_tex = RenderTexture::load(path::as_fs_abs(&state.project_root, target)?)?;
+            let [width, height] = render_tex.size;
+
+            state.render_targets.insert(target.clone(), Framebuffer::new((width as _, height as _))?);
+        }
 
         Ok(())
     }
 
-    fn load_downstream_scenes(scene: &mut Scene, state: &ProcessorState) -> Result<(), Box<dyn Error>> {
+    /// Reads back the pixels of the canvas drawn by the last call to `do_frame`.
+    ///
+    /// The canvas is read from whatever framebuffer is currently bound, so it must be the same that was bound when
+    /// drawing the frame.
+    pub fn capture_frame(&self) -> RgbaImage {
+        let [width, height] = self.state.canvas_size;
+
+        let mut raw = vec![0_u8; (width * height * 4) as usize];
+
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(0, 0, width as _, height as _, gl::RGBA, gl::UNSIGNED_BYTE, raw.as_mut_ptr() as _);
+        }
+
+        let frame = RgbaImage::from_raw(width, height, raw).unwrap();
+
+        // OpenGL puts the first row at the bottom, images put it at the top
+        image::imageops::flip_vertical(&frame)
+    }
+
+    fn load_downstream_scenes(scene: &mut Scene, state: &ProcessorState) -> Result<()> {
         for (_, (mut scene_comp, ), _)
         in <(SceneComponent, )>::query_shallow_mut(scene) {
             // Ignore SceneComponents with no scene selected
@@ -140,7 +614,7 @@ impl Processor {
             };
 
             if scene_comp.loaded.is_none() {
-                scene_comp.loaded = Some(Scene::load(path::as_fs_abs(&state.project_root, scene))?);
+                scene_comp.loaded = Some(Scene::load(path::as_fs_abs(&state.project_root, state.db.resolve(scene))?)?);
             }
 
             Processor::load_downstream_scenes(scene_comp.loaded.as_mut().unwrap(), state)?;
@@ -148,108 +622,567 @@ impl Processor {
         Ok(())
     }
 
-    fn process_scene(scene: &mut Scene, state: &mut ProcessorState, base_transform: Mat4) -> Result<(), Box<dyn Error>> {
-        let scene_containers: Vec<(Entity, Mat4)> = <(SceneComponent, )>::query_shallow(scene)
-            .filter(|(_, (scene_comp,), _)| scene_comp.scene.is_some()) // Ignore SceneComponents with no scene selected
-            .map(|(entity, _, _)| (entity, base_transform * combined_transform(scene, entity))).collect();
+    /// Requests the clips of the keyframe animators, which only start playing once they're loaded.
+    fn load_keyframe_clips(scene: &mut Scene, state: &mut ProcessorState) -> Result<()> {
+        for (_, (mut scene_comp, ), _)
+        in <(SceneComponent, )>::query_shallow_mut(scene) {
+            if let Some(loaded) = scene_comp.loaded.as_mut() {
+                Processor::load_keyframe_clips(loaded, state)?;
+            }
+        }
+
+        for (_, (mut keyframe_comp, ), _)
+        in <(KeyframeAnimatorComponent, )>::query_deep_mut(scene) {
+            if keyframe_comp.loaded.is_none() {
+                keyframe_comp.loaded = match keyframe_comp.clip.as_ref() {
+                    Some(clip) => state.assets.request_keyframe_clip(state.db.resolve(clip))?,
+                    None => None,
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Blends the clips of every animator into the matrices its skinned meshes are drawn with, once per frame for all
+    /// the cameras. Animators whose skeleton is still loading have none, and their meshes are drawn as they were bound.
+    fn pose_animators(scene: &mut Scene, state: &mut ProcessorState) -> Result<()> {
+        for (_, (mut scene_comp, ), _)
+        in <(SceneComponent, )>::query_shallow_mut(scene) {
+            if let Some(loaded) = scene_comp.loaded.as_mut() {
+                Processor::pose_animators(loaded, state)?;
+            }
+        }
+
+        for (_, (mut animator_comp, ), _)
+        in <(AnimatorComponent, )>::query_shallow_mut(scene) {
+            if animator_comp.loaded_skeleton.is_none() {
+                animator_comp.loaded_skeleton = match animator_comp.skeleton.as_ref() {
+                    Some(skeleton) => state.assets.request_skeleton(state.db.resolve(skeleton))?,
+                    None => None,
+                };
+            }
+
+            for layer in &mut animator_comp.layers {
+                if layer.loaded.is_none() {
+                    layer.loaded = state.assets.request_clip(state.db.resolve(&layer.clip))?;
+                }
+            }
+
+            animator_comp.skinning = animator_comp.loaded_skeleton.clone().map(|skeleton| {
+                let mut skinning = animator_comp.pose(&skeleton).skinning_matrices(&skeleton);
+                skinning.truncate(Skeleton::MAX_JOINTS);
 
-        for (entity, base_transform) in scene_containers {
-            let mut scene_comp = scene.get_one_mut::<SceneComponent>(entity).unwrap();
-            Processor::process_scene(scene_comp.loaded.as_mut().unwrap(), state, base_transform)?;
+                Rc::new(skinning)
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Requests the meshes, materials and textures of the scene, once per frame before any camera draws it. Assets that
+    /// are still loading are asked for again on every frame until they're ready.
+    fn load_meshes(scene: &mut Scene, state: &mut ProcessorState) -> Result<()> {
+        for (_, (mut scene_comp, ), _)
+        in <(SceneComponent, )>::query_shallow_mut(scene) {
+            if let Some(loaded) = scene_comp.loaded.as_mut() {
+                Processor::load_meshes(loaded, state)?;
+            }
         }
 
         for (_, (mut mesh_comp, ), _)
         in <(MeshComponent, )>::query_deep_mut(scene) {
-            if mesh_comp.vao.is_some() { continue; };
+            if mesh_comp.vao.is_none() {
+                mesh_comp.vao = state.assets.request_vao(state.db.resolve(&mesh_comp.mesh))?;
+            }
 
-            let mesh = Mesh::load(path::as_fs_abs(&state.project_root, &mesh_comp.mesh))?;
-            let mat = Material::load(path::as_fs_abs(&state.project_root, &mesh_comp.mat))?;
+            let mesh_comp = &mut *mesh_comp;
+            mesh_comp.lod_vaos.resize(mesh_comp.lods.len(), None);
 
-            let vao = Vao::from(&mesh)?;
+            for (lod, lod_vao) in mesh_comp.lods.iter().zip(&mut mesh_comp.lod_vaos) {
+                if lod_vao.is_none() {
+                    *lod_vao = state.assets.request_vao(state.db.resolve(lod))?;
+                }
+            }
+
+            if mesh_comp.material.is_none() {
+                mesh_comp.material = state.assets.request_material(state.db.resolve(&mesh_comp.mat))?;
+            }
 
-            mesh_comp.vao = Some(vao);
+            if mesh_comp.tex.is_none() {
+                let tex_path = match mesh_comp.material.as_ref().and_then(|mat| mat.tex.as_ref()) {
+                    Some(tex) => state.db.resolve(tex).to_owned(),
+                    None => continue,
+                };
 
-            if let Some(tex_path) = mat.tex {
-                let mut tex = Texture::load(path::as_fs_abs(&state.project_root, &tex_path))?;
-                tex.load_gl();
-                mesh_comp.tex = Some(tex);
+                mesh_comp.tex = if RenderTexture::is_render_texture(&tex_path) {
+                    Some(MeshTexture::RenderTarget(tex_path))
+                } else {
+                    state.assets.request_texture(&tex_path)?.map(MeshTexture::Loaded)
+                };
             }
         }
 
-        // Now we can properly render them
+        Ok(())
+    }
+
+    fn process_scene(scene: &Scene, state: &mut ProcessorState, base_transform: Mat4) -> Result<()> {
+        for (entity, (scene_comp, ), _)
+        in <(SceneComponent, )>::query_shallow(scene) {
+            // Ignore SceneComponents with no scene selected
+            if let Some(loaded) = scene_comp.loaded.as_ref() {
+                Processor::process_scene(loaded, state, base_transform * combined_transform(scene, entity)?)?;
+            }
+        }
+
+        // Meshes still loading are drawn as a unit cube with the default material, while the ones whose material or
+        // texture are still loading are drawn untextured. LODs still loading are left out, the mesh itself is drawn
+        // until they're ready
         for (entity, (mesh_comp, ), _)
         in <(MeshComponent, )>::query_deep(scene) {
-            let vao = mesh_comp.vao.as_ref().unwrap();
+            let (mut vao, pending) = match mesh_comp.vao.as_ref() {
+                Some(vao) => (vao.clone(), false),
+                None => (state.placeholder.clone(), true),
+            };
+
+            // Skinned meshes are drawn where their animator is, through its joints
+            let animator = if vao.skinned { find_animator(scene, entity) } else { None };
+
+            let (model, joints) = match animator {
+                Some((animator_entity, skinning)) => (base_transform * combined_transform(scene, animator_entity)?, Some(skinning)),
+                None => (base_transform * combined_transform(scene, entity)?, None),
+            };
 
-            state.shader.enable();
-            state.shader.set_mat4("model", &(base_transform * combined_transform(scene, entity)));
+            let camera_mats = state.camera_mats.as_ref().unwrap();
+            let CameraMats { frustum, position, .. } = camera_mats;
 
-            let CameraMats { view_mat, projection_mat } = state.camera_mats.as_ref().unwrap();
+            if let Some(lod_group) = scene.get_one::<LodGroupComponent>(entity) {
+                // Every mesh of the entity switches together, by how big they all are on screen
+                let bounds = scene.get_all::<MeshComponent>(entity)
+                    .iter()
+                    .filter_map(|mesh_comp| mesh_comp.vao.as_ref().map(|vao| vao.aabb))
+                    .fold(vao.aabb, |bounds, aabb| bounds.union(&aabb));
 
-            state.shader.set_mat4("view", view_mat);
-            state.shader.set_mat4("projection", projection_mat);
+                let level = match lod_group.level(camera_mats.screen_size(&bounds.transform(&model))) {
+                    Some(level) => level,
+                    None => {
+                        state.culling_stats.culled += 1;
+                        continue;
+                    }
+                };
 
-            Texture::use_tex(mesh_comp.tex.as_ref(), &mut state.shader);
+                // Meshes with fewer LODs than the group stay on their last one
+                let level = level.min(mesh_comp.lod_vaos.len());
 
-            vao.draw();
+                if let Some(Some(lod_vao)) = level.checked_sub(1).filter(|_| !pending).map(|i| &mesh_comp.lod_vaos[i]) {
+                    vao = lod_vao.clone();
+                }
+            }
+
+            let aabb = vao.aabb.transform(&model);
+
+            // The joints can take skinned meshes anywhere, their bounding box is only where they were bound
+            if joints.is_none() && !frustum.intersects(&aabb) {
+                state.culling_stats.culled += 1;
+                continue;
+            }
+
+            state.culling_stats.drawn += 1;
+
+            let material = if pending { None } else { mesh_comp.material.as_ref() };
+
+            let tex_id = match mesh_comp.tex.as_ref().filter(|_| !pending) {
+                Some(MeshTexture::Loaded(tex)) => tex.id,
+                // Sampling from the texture that is being drawn to is undefined behaviour, so leave it out
+                Some(MeshTexture::RenderTarget(target)) if state.current_target.as_ref() == Some(target) => None,
+                Some(MeshTexture::RenderTarget(target)) => state.render_targets.get(target).map(|framebuffer| framebuffer.get_tex_id()),
+                None => None,
+            };
+
+            let color = match (material.and_then(|mat| mat.color), tex_id) {
+                (Some(color), _) => color,
+                (None, Some(_)) => [1.0; 4],
+                (None, None) => UNTEXTURED_COLOR,
+            };
+
+            state.queue.push(RenderItem {
+                vao,
+                tex_id,
+                color,
+                blend: material.map_or(BlendMode::Opaque, |mat| mat.blend),
+                model,
+                joints,
+                distance: position.distance_squared((aabb.min + aabb.max) / 2.0),
+            });
         }
 
         Ok(())
     }
+
+    /// Draws everything that was queued by `process_scene` with the current camera, then empties the queue.
+    ///
+    /// Opaque and alpha tested items are drawn first, sorted so that state changes are kept to a minimum: the texture is
+    /// bound once for all the items that use it and all the instances of the same mesh are drawn with a single
+    /// instanced draw call. Transparent items are drawn last, back to front, without writing to the depth buffer so
+    /// that they don't hide each other. There's only one shader for now, so it's enabled once for the whole queue.
+    fn draw_queue(state: &mut ProcessorState) {
+        let mut queue = std::mem::take(&mut state.queue);
+
+        queue.sort_by(|a, b| {
+            a.blend.is_transparent().cmp(&b.blend.is_transparent()).then_with(|| {
+                if a.blend.is_transparent() {
+                    // Farthest first, the order between transparent items matters more than batching them
+                    b.distance.partial_cmp(&a.distance).unwrap_or(Ordering::Equal)
+                } else if state.instancing {
+                    a.batch_key().cmp(&b.batch_key())
+                } else {
+                    // The sort is stable, so opaque items are left in the order the scene was walked in
+                    Ordering::Equal
+                }
+            })
+        });
+
+        let CameraMats { view_mat, projection_mat, .. } = state.camera_mats.as_ref().unwrap();
+
+        state.shader.enable();
+        state.shader.set_mat4("view", view_mat);
+        state.shader.set_mat4("projection", projection_mat);
+
+        let mut bound_tex_id = None;
+        let mut bound_color = None;
+        let mut bound_blend = None;
+        let mut bound_joints = None;
+        let mut models = Vec::new();
+
+        let mut start = 0;
+
+        while start < queue.len() {
+            let first = &queue[start];
+
+            let end = if state.instancing {
+                // Items with the same key are contiguous after sorting, transparent ones only if they're also next to
+                // each other from the camera's point of view
+                start + queue[start..].iter().take_while(|item| item.batch_key() == first.batch_key()).count()
+            } else {
+                // One draw call per item, setting up everything from scratch every time like before batching
+                state.shader.enable();
+                state.shader.set_mat4("view", view_mat);
+                state.shader.set_mat4("projection", projection_mat);
+
+                bound_tex_id = None;
+                bound_color = None;
+                bound_blend = None;
+                bound_joints = None;
+
+                start + 1
+            };
+
+            if bound_blend != Some(first.blend) {
+                use_blend(first.blend, &mut state.shader);
+                bound_blend = Some(first.blend);
+            }
+
+            if bound_tex_id != Some(first.tex_id) {
+                Texture::use_tex_id(first.tex_id, &mut state.shader);
+                bound_tex_id = Some(first.tex_id);
+            }
+
+            if bound_color != Some(first.color) {
+                state.shader.set_vec4("baseColor", Vec4::from(first.color));
+                bound_color = Some(first.color);
+            }
+
+            if bound_joints != Some(first.joints_ptr()) {
+                state.shader.set_bool("skinned", first.joints.is_some());
+
+                if let Some(joints) = first.joints.as_ref() {
+                    state.shader.set_mat4_array("joints", joints);
+                }
+
+                bound_joints = Some(first.joints_ptr());
+            }
+
+            if state.instancing {
+                models.clear();
+                models.extend(queue[start..end].iter().map(|item| item.model));
+
+                first.vao.draw_instanced(&models);
+            } else {
+                first.vao.draw(&first.model);
+            }
+
+            start = end;
+        }
+
+        // Back to the defaults, clearing the depth buffer doesn't work while writing to it is disabled
+        use_blend(BlendMode::Opaque, &mut state.shader);
+
+        // Hand the allocation back so that it can be reused on the next frame
+        queue.clear();
+        state.queue = queue;
+    }
+
+    /// When enabled, which is the default, meshes are drawn in batches using instancing. Otherwise every mesh is drawn
+    /// on its own, in scene order and with a plain draw call as it was before batching, which is only useful to measure
+    /// how much batching helps.
+    pub fn set_instancing(&mut self, instancing: bool) {
+        self.state.instancing = instancing;
+    }
+}
+
+/// What meshes are drawn with when their material has no colour and no texture, or while their texture is loading.
+const UNTEXTURED_COLOR: [f32; 4] = [0.4, 0.4, 0.4, 1.0];
+
+/// A mesh waiting to be drawn, with everything needed to draw it.
+struct RenderItem {
+    vao: Rc<Vao>,
+    tex_id: Option<u32>,
+    color: [f32; 4],
+    blend: BlendMode,
+    model: Mat4,
+    // Skinning matrices of the animator posing the mesh, if it's skinned
+    joints: Option<Rc<Vec<Mat4>>>,
+    // Squared distance between the camera and the center of the mesh's bounding box
+    distance: f32,
 }
 
-fn compute_camera_mats(scene: &Scene, base_transform: Mat4, canvas_size: &[u32; 2]) -> Option<CameraMats> {
-    for (_, (scene_comp, transform_comp), _)
-    in <(SceneComponent, TransformComponent)>::query_deep(scene) {
+impl RenderItem {
+    /// Items with the same key can be drawn in the same instanced draw call.
+    fn batch_key(&self) -> (u8, u32, Option<u32>, [u32; 4], *const Vao, *const Vec<Mat4>) {
+        let (mode, cutoff) = match self.blend {
+            BlendMode::Opaque => (0, 0.0),
+            BlendMode::AlphaTest { cutoff } => (1, cutoff),
+            BlendMode::AlphaBlend => (2, 0.0),
+            BlendMode::Additive => (3, 0.0),
+        };
+
+        (mode, cutoff.to_bits(), self.tex_id, self.color.map(f32::to_bits), Rc::as_ptr(&self.vao), self.joints_ptr())
+    }
+
+    /// Tells apart the animators posing the items, null for the items that aren't skinned.
+    fn joints_ptr(&self) -> *const Vec<Mat4> {
+        self.joints.as_ref().map_or(std::ptr::null(), Rc::as_ptr)
+    }
+}
+
+/// Sets up the blending function, depth writes and alpha test for the given blend mode.
+fn use_blend(blend: BlendMode, shader: &mut Shader) {
+    let cutoff = match blend {
+        BlendMode::AlphaTest { cutoff } => cutoff,
+        _ => 0.0,
+    };
+
+    shader.set_float("alphaCutoff", cutoff);
+    shader.set_bool("opaque", !blend.is_transparent());
+
+    unsafe {
+        match blend {
+            BlendMode::Opaque | BlendMode::AlphaTest { .. } => {
+                gl::Disable(gl::BLEND);
+            }
+            // The alpha of the destination is kept as it is, so that what's behind stays opaque
+            BlendMode::AlphaBlend => {
+                gl::Enable(gl::BLEND);
+                gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ZERO, gl::ONE);
+            }
+            BlendMode::Additive => {
+                gl::Enable(gl::BLEND);
+                gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE, gl::ZERO, gl::ONE);
+            }
+        }
+
+        gl::DepthMask(if blend.is_transparent() { gl::FALSE } else { gl::TRUE });
+    }
+}
+
+/// A camera found while traversing the scene tree, along with the global transform of the entity it's attached to.
+struct Camera {
+    comp: CameraComponent,
+    transform: Mat4,
+    /// The `$/` path of the render texture the camera draws to
+    target: Option<PathBuf>,
+}
+
+impl Camera {
+    /// Returns the region of the target, in pixels, this camera renders to as `[x, y, width, height]`.
+    fn viewport_px(&self, target_size: &[u32; 2]) -> [i32; 4] {
+        let [width, height] = *target_size;
+        let [x, y, w, h] = self.comp.viewport;
+
+        let x0 = (x.clamp(0.0, 1.0) * width as f32).round() as i32;
+        let y0 = (y.clamp(0.0, 1.0) * height as f32).round() as i32;
+        let x1 = ((x + w).clamp(0.0, 1.0) * width as f32).round() as i32;
+        let y1 = ((y + h).clamp(0.0, 1.0) * height as f32).round() as i32;
+
+        [x0, y0, x1 - x0, y1 - y0]
+    }
+
+    fn mats(&self, aspect_ratio: f32) -> CameraMats {
+        let mut position = Vec3::default();
+
+        let view_mat = {
+            let mut scale = Vec3::default();
+            let mut rotation = Quat::default();
+
+            decompose(self.transform.as_ref(), position.as_mut(), scale.as_mut(), rotation.as_mut());
+
+            let forward = rotation.mul_vec3(-Vec3::Z).normalize();
+            let target = position + forward;
+
+            let right = Vec3::cross(forward, Vec3::Y).normalize();
+            let up = Vec3::cross(right, forward).normalize();
+
+            Mat4::look_at_rh(position, target, up)
+        };
+
+        let projection_mat = self.comp.projection.as_mat4(aspect_ratio);
+
+        CameraMats {
+            view_mat,
+            projection_mat,
+            frustum: Frustum::from_mat(&(projection_mat * view_mat)),
+            position,
+        }
+    }
+}
+
+/// Collects all the active cameras in `scene` and in the scenes it contains.
+fn collect_cameras(scene: &Scene, db: &AssetDatabase, base_transform: Mat4, out: &mut Vec<Camera>) -> Result<()> {
+    for (entity, (scene_comp, ), _)
+    in <(SceneComponent, )>::query_shallow(scene) {
         // Ignore SceneComponents with no scene selected
         if scene_comp.scene.is_none() {
             continue;
         }
 
-        let transform = base_transform * transform_comp.0.clone();
-        if let Some(mats) = compute_camera_mats(scene_comp.loaded.as_ref().unwrap(), transform, canvas_size) {
-            return Some(mats);
+        let transform = base_transform * combined_transform(scene, entity)?;
+        collect_cameras(scene_comp.loaded.as_ref().unwrap(), db, transform, out)?;
+    }
+
+    for (entity, (camera_comp, ), _)
+    in <(CameraComponent, )>::query_shallow(scene) {
+        if !camera_comp.active {
+            continue;
         }
+
+        out.push(Camera {
+            comp: camera_comp.clone(),
+            transform: base_transform * combined_transform(scene, entity)?,
+            target: camera_comp.target.as_ref().map(|target| db.resolve(target).to_owned()),
+        });
     }
 
-    <(CameraComponent, )>::query_shallow(scene).next().map(|(entity, _, _)| {
-        let transform = combined_transform(scene, entity);
+    Ok(())
+}
 
-        CameraMats {
-            view_mat: {
-                let mut position = Vec3::default();
-                let mut scale = Vec3::default();
-                let mut rotation = Quat::default();
+/// A mesh showing a render texture.
+struct Surface {
+    /// The `$/` path of the render texture
+    texture: PathBuf,
+    /// In world space, None for skinned meshes, which can be anywhere
+    bounds: Option<Aabb>,
+}
+
+/// Collects the loaded meshes showing a render texture in `scene` and in the scenes it contains.
+fn collect_render_texture_surfaces(scene: &Scene, base_transform: Mat4, out: &mut Vec<Surface>) -> Result<()> {
+    for (entity, (scene_comp, ), _)
+    in <(SceneComponent, )>::query_shallow(scene) {
+        if let Some(loaded) = scene_comp.loaded.as_ref() {
+            collect_render_texture_surfaces(loaded, base_transform * combined_transform(scene, entity)?, out)?;
+        }
+    }
 
-                decompose(transform.as_ref(), position.as_mut(), scale.as_mut(), rotation.as_mut());
+    for (entity, (mesh_comp, ), _)
+    in <(MeshComponent, )>::query_deep(scene) {
+        let (texture, vao) = match (&mesh_comp.tex, &mesh_comp.vao) {
+            (Some(MeshTexture::RenderTarget(texture)), Some(vao)) => (texture, vao),
+            _ => continue,
+        };
 
-                let forward = rotation.mul_vec3(-Vec3::Z).normalize();
-                let target = position + forward;
+        let bounds = if vao.skinned {
+            None
+        } else {
+            Some(vao.aabb.transform(&(base_transform * combined_transform(scene, entity)?)))
+        };
 
-                let right = Vec3::cross(forward, Vec3::Y).normalize();
-                let up = Vec3::cross(right, forward).normalize();
+        out.push(Surface { texture: texture.clone(), bounds });
+    }
 
-                Mat4::look_at_rh(position, target, up)
-            },
-            projection_mat: {
-                let [width, height] = canvas_size;
-                let aspect_ratio = *width as f32 / *height as f32;
-                Mat4::perspective_rh(90_f32.to_radians(), aspect_ratio, 0.1, 100.0)
-            },
-        }
-    })
+    Ok(())
 }
 
-pub fn combined_transform(scene: &Scene, mut entity: Entity) -> Mat4 {
+/// Orders the cameras so that the ones drawing to a render texture go before the ones that see it, given the render
+/// textures each camera sees. Otherwise, cameras drawing to a texture go first, then the ones with the lowest `order`,
+/// then the ones that were found first.
+///
+/// When cameras see each other's textures in a cycle, the first of them goes anyway. The textures it sees before they're
+/// drawn are returned.
+fn sort_cameras(cameras: Vec<Camera>, seen: &[Vec<PathBuf>]) -> (Vec<Camera>, Vec<PathBuf>) {
+    // The cameras each camera has to wait for. Cameras don't see the texture they draw to, see `process_scene`
+    let waits_for: Vec<Vec<usize>> = (0..cameras.len())
+        .map(|j| {
+            (0..cameras.len())
+                .filter(|&i| match &cameras[i].target {
+                    Some(target) => cameras[j].target.as_ref() != Some(target) && seen[j].contains(target),
+                    None => false,
+                })
+                .collect()
+        })
+        .collect();
+
+    let key = |i: usize| (cameras[i].target.is_none(), cameras[i].comp.order, i);
+
+    let mut drawn = vec![false; cameras.len()];
+    let mut order = Vec::with_capacity(cameras.len());
+    let mut cycles = Vec::new();
+
+    while order.len() < cameras.len() {
+        let pending = (0..cameras.len()).filter(|&i| !drawn[i]);
+
+        let ready = pending.clone()
+            .filter(|&i| waits_for[i].iter().all(|&k| drawn[k]))
+            .min_by_key(|&i| key(i));
+
+        let next = match ready {
+            Some(next) => next,
+            None => {
+                // Every camera left waits for another one
+                let next = pending.min_by_key(|&i| key(i)).unwrap();
+
+                for &k in &waits_for[next] {
+                    let target = cameras[k].target.as_ref().unwrap();
+
+                    if !drawn[k] && !cycles.contains(target) {
+                        cycles.push(target.clone());
+                    }
+                }
+
+                next
+            }
+        };
+
+        drawn[next] = true;
+        order.push(next);
+    }
+
+    let mut cameras: Vec<_> = cameras.into_iter().map(Some).collect();
+    let sorted = order.into_iter().map(|i| cameras[i].take().unwrap()).collect();
+
+    (sorted, cycles)
+}
+
+/// The transform of an entity in the space of its scene, combining the ones of all its ancestors. Fails if the entity or
+/// any of its ancestors lacks a transform or a hierarchy component.
+pub fn combined_transform(scene: &Scene, mut entity: Entity) -> Result<Mat4> {
     let mut transform_components = Vec::new();
 
     loop {
         let transform_component = scene.get_one::<TransformComponent>(entity)
-            .expect("entity does not have a transform component");
+            .ok_or(Error::MissingComponent { entity, component: "TransformComponent" })?;
         transform_components.push(transform_component);
 
         let hierarchy_component = scene.get_one::<HierarchyComponent>(entity)
-            .expect("entity does not have a hierarchy component");
+            .ok_or(Error::MissingComponent { entity, component: "HierarchyComponent" })?;
 
         if let Some(parent_entity) = hierarchy_component.parent {
             entity = parent_entity;
@@ -264,7 +1197,19 @@ pub fn combined_transform(scene: &Scene, mut entity: Entity) -> Mat4 {
         out = out * transform_component.0;
     }
 
-    out
+    Ok(out)
+}
+
+/// The entity with the animator posing the skinned mesh of the given entity, which is the entity itself or its closest
+/// ancestor with an `AnimatorComponent`, along with the skinning matrices it has computed for this frame.
+fn find_animator(scene: &Scene, mut entity: Entity) -> Option<(Entity, Rc<Vec<Mat4>>)> {
+    loop {
+        if let Some(animator_comp) = scene.get_one::<AnimatorComponent>(entity) {
+            return animator_comp.skinning.clone().map(|skinning| (entity, skinning));
+        }
+
+        entity = scene.get_one::<HierarchyComponent>(entity)?.parent?;
+    }
 }
 
 fn clear_canvas() {
@@ -273,3 +1218,35 @@ fn clear_canvas() {
         gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
     }
 }
+
+fn bound_framebuffer() -> u32 {
+    let mut id = 0;
+
+    unsafe {
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut id);
+    }
+
+    id as _
+}
+
+fn bind_framebuffer(id: u32) {
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+    }
+}
+
+/// Clears only the given region of the canvas, leaving what other cameras have drawn untouched.
+fn clear_viewport(viewport: [i32; 4], color: [f32; 4]) {
+    let [x, y, width, height] = viewport;
+    let [red, green, blue, alpha] = color;
+
+    unsafe {
+        gl::Enable(gl::SCISSOR_TEST);
+        gl::Scissor(x, y, width, height);
+
+        gl::ClearColor(red, green, blue, alpha);
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+        gl::Disable(gl::SCISSOR_TEST);
+    }
+}
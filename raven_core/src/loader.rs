@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::error::{Error, Result};
+use crate::io::Serializable;
+use crate::resource::{AnimationClip, KeyframeClip, Material, Mesh, Script, Skeleton, Texture};
+
+const THREADS: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum AssetKind {
+    Mesh,
+    Texture,
+    Material,
+    Skeleton,
+    Clip,
+    KeyframeClip,
+    Script,
+}
+
+pub(crate) struct Job {
+    /// Tells apart jobs loading the same asset, if it's asked for again before the first one is done
+    pub(crate) id: u64,
+    pub(crate) kind: AssetKind,
+    /// The `$/` path, which identifies the asset
+    pub(crate) path: PathBuf,
+    pub(crate) fs_path: PathBuf,
+}
+
+/// An asset read from disk and decoded, but not uploaded to the GPU yet.
+pub(crate) enum Decoded {
+    Mesh(Mesh),
+    Texture(Texture),
+    Material(Material),
+    Skeleton(Skeleton),
+    Clip(AnimationClip),
+    KeyframeClip(KeyframeClip),
+    Script(Script),
+}
+
+pub(crate) struct Done {
+    pub(crate) id: u64,
+    pub(crate) kind: AssetKind,
+    pub(crate) path: PathBuf,
+    pub(crate) result: Result<Decoded>,
+}
+
+/// Reads and decodes assets on background threads. Anything that touches OpenGL is left to the render thread.
+pub(crate) struct Loader {
+    // An option so that it can be dropped before joining the threads, which stop once it's gone
+    jobs: Option<Sender<Job>>,
+    done: Receiver<Done>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Loader {
+    pub(crate) fn new() -> Loader {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (done_sender, done) = mpsc::channel();
+
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let threads = (0..THREADS).map(|i| {
+            let job_receiver = job_receiver.clone();
+            let done_sender = done_sender.clone();
+
+            thread::Builder::new()
+                .name(format!("raven-loader-{}", i))
+                .spawn(move || loop {
+                    // The lock is released as soon as a job is taken, so that the other threads can take the next one
+                    let job = match job_receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => return,
+                    };
+
+                    let done = Done {
+                        id: job.id,
+                        kind: job.kind,
+                        result: decode(&job),
+                        path: job.path,
+                    };
+
+                    if done_sender.send(done).is_err() {
+                        return;
+                    }
+                })
+                .expect("couldn't spawn an asset loading thread")
+        }).collect();
+
+        Loader {
+            jobs: Some(jobs),
+            done,
+            threads,
+        }
+    }
+
+    pub(crate) fn submit(&self, job: Job) {
+        // The threads only stop when the loader is dropped
+        self.jobs.as_ref().unwrap().send(job).unwrap();
+    }
+
+    /// Returns the assets that were decoded since the last call, without waiting.
+    pub(crate) fn poll(&self) -> impl Iterator<Item=Done> + '_ {
+        self.done.try_iter()
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        self.jobs = None;
+
+        for thread in self.threads.drain(..) {
+            // A thread that panicked has nothing left to clean up
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Reads and decodes an asset on the current thread.
+pub(crate) fn decode(job: &Job) -> Result<Decoded> {
+    let decoded = match job.kind {
+        AssetKind::Mesh => Mesh::load(&job.fs_path).map(Decoded::Mesh),
+        AssetKind::Texture => Texture::load(&job.fs_path).map(Decoded::Texture),
+        AssetKind::Material => Material::load(&job.fs_path).map(Decoded::Material),
+        AssetKind::Skeleton => Skeleton::load(&job.fs_path).map(Decoded::Skeleton),
+        AssetKind::Clip => AnimationClip::load(&job.fs_path).map(Decoded::Clip),
+        AssetKind::KeyframeClip => KeyframeClip::load(&job.fs_path).map(Decoded::KeyframeClip),
+        AssetKind::Script => Script::load(&job.fs_path).map(Decoded::Script),
+    };
+
+    // Reported with the `$/` path, which is the one users know about
+    decoded.map_err(|err: Error| err.at(&job.path))
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::io::Serializable;
+use crate::path;
+
+/// How an external file was imported: what it contained, with which settings and by which version of the importer.
+/// When none of these change, importing it again would produce the same assets.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImportRecord {
+    pub source_hash: String,
+    pub settings_hash: String,
+    pub importer_version: u32,
+}
+
+impl ImportRecord {
+    /// Hashes the external file at the given filesystem path and the settings it's imported with.
+    pub fn new<P: AsRef<Path>, S: Serialize>(fs_source: P, settings: &S, importer_version: u32) -> Result<ImportRecord> {
+        let fs_source = fs_source.as_ref();
+
+        let hash_source = || -> Result<String> {
+            let mut hasher = Md5::default();
+            io::copy(&mut File::open(fs_source)?, &mut hasher)?;
+            Ok(format!("{:x}", hasher.finalize()))
+        };
+
+        let source_hash = hash_source().map_err(|err| err.at(fs_source))?;
+
+        let mut hasher = Md5::default();
+        Digest::update(&mut hasher, serde_json::to_vec(settings)?);
+        let settings_hash = format!("{:x}", hasher.finalize());
+
+        Ok(ImportRecord {
+            source_hash,
+            settings_hash,
+            importer_version,
+        })
+    }
+}
+
+/// The external files of a project that were imported, and how. Saved in the import directory, so that an external file
+/// is only imported again when something changed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ImportManifest {
+    records: BTreeMap<PathBuf, ImportRecord>,
+}
+
+impl ImportManifest {
+    pub const FILE_NAME: &'static str = "manifest.json";
+
+    /// The `$/` path of the manifest.
+    pub fn path() -> PathBuf {
+        let mut manifest_path = PathBuf::new();
+        manifest_path.push(path::PROJECT_ROOT_RUNE);
+        manifest_path.push(path::IMPORT_DIR);
+        manifest_path.push(ImportManifest::FILE_NAME);
+
+        manifest_path
+    }
+
+    /// Loads the manifest of the project, or an empty one if nothing was imported yet.
+    pub fn open<R: AsRef<Path>>(project_root: R) -> Result<ImportManifest> {
+        let fs_path = path::as_fs_abs(project_root, ImportManifest::path())?;
+
+        match ImportManifest::load(&fs_path) {
+            Err(Error::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => Ok(ImportManifest::default()),
+            manifest => manifest,
+        }
+    }
+
+    pub fn save_to<R: AsRef<Path>>(&self, project_root: R) -> Result<()> {
+        let fs_path = path::as_fs_abs(project_root, ImportManifest::path())?;
+
+        if let Some(parent) = fs_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        self.save(fs_path)
+    }
+
+    /// Whether the external file at the given `$/` path was imported exactly as the record says.
+    pub fn is_up_to_date<P: AsRef<Path>>(&self, source: P, record: &ImportRecord) -> bool {
+        self.records.get(source.as_ref()) == Some(record)
+    }
+
+    pub fn get<P: AsRef<Path>>(&self, source: P) -> Option<&ImportRecord> {
+        self.records.get(source.as_ref())
+    }
+
+    pub fn insert(&mut self, source: PathBuf, record: ImportRecord) {
+        self.records.insert(source, record);
+    }
+
+    pub fn remove<P: AsRef<Path>>(&mut self, source: P) -> Option<ImportRecord> {
+        self.records.remove(source.as_ref())
+    }
+
+    /// The `$/` paths of the external files that were imported.
+    pub fn sources(&self) -> impl Iterator<Item=&Path> {
+        self.records.keys().map(PathBuf::as_path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{ImportManifest, ImportRecord};
+
+    #[test]
+    fn records_go_out_of_date() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let fs_source = dir.join("tex.png");
+        let source = PathBuf::from("$/tex.png");
+
+        fs::write(&fs_source, b"one").unwrap();
+
+        let mut manifest = ImportManifest::open(dir).unwrap();
+        let record = ImportRecord::new(&fs_source, &1, 1).unwrap();
+        assert!(!manifest.is_up_to_date(&source, &record));
+
+        manifest.insert(source.clone(), record);
+        manifest.save_to(dir).unwrap();
+
+        let manifest = ImportManifest::open(dir).unwrap();
+        assert!(manifest.is_up_to_date(&source, &ImportRecord::new(&fs_source, &1, 1).unwrap()));
+
+        // Other settings or another importer
+        assert!(!manifest.is_up_to_date(&source, &ImportRecord::new(&fs_source, &2, 1).unwrap()));
+        assert!(!manifest.is_up_to_date(&source, &ImportRecord::new(&fs_source, &1, 2).unwrap()));
+
+        // Other contents
+        fs::write(&fs_source, b"two").unwrap();
+        assert!(!manifest.is_up_to_date(&source, &ImportRecord::new(&fs_source, &1, 1).unwrap()));
+    }
+}
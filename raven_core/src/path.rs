@@ -1,27 +1,44 @@
 use std::path::{Path, PathBuf};
 
+use crate::error::{Error, Result};
+
 pub const PROJECT_ROOT_RUNE: &'static str = "$/";
 
+/// Directory in the project root where the assets imported from external files are written.
+pub const IMPORT_DIR: &str = ".import";
+
 pub fn is_valid<P: AsRef<Path>>(path: P) -> bool {
     path.as_ref().starts_with(PROJECT_ROOT_RUNE)
 }
 
-pub fn strip_rune<P: AsRef<Path> + ?Sized>(path: &P) -> &Path {
+pub fn strip_rune<P: AsRef<Path> + ?Sized>(path: &P) -> Result<&Path> {
     path.as_ref()
         .strip_prefix(PROJECT_ROOT_RUNE)
-        .expect("expected to find project root rune to strip it")
+        .map_err(|_| Error::InvalidPath(path.as_ref().to_owned()))
 }
 
 /// Given the absolute path to the project root and the absolute path to an asset, returns the filesystem absolute path.
+/// Fails with `Error::InvalidPath` if the path doesn't start with `$/`.
 ///
 /// For instance:
 /// `$/ferris/ferris.fbx` becomes `/project/ferris/ferris.fbx` given that `project_root` is `/project`
-pub fn as_fs_abs<R: AsRef<Path>, P: AsRef<Path>>(project_root: R, path: P) -> PathBuf {
-    assert!(path.as_ref().starts_with(PROJECT_ROOT_RUNE));
-
+pub fn as_fs_abs<R: AsRef<Path>, P: AsRef<Path>>(project_root: R, path: P) -> Result<PathBuf> {
     let mut abs_path = PathBuf::default();
     abs_path.push(project_root.as_ref());
-    abs_path.push(strip_rune(path.as_ref()));
+    abs_path.push(strip_rune(path.as_ref())?);
+
+    Ok(abs_path)
+}
+
+/// The opposite of `as_fs_abs`, returns `None` if `fs_path` isn't inside of `project_root`.
+///
+/// For instance:
+/// `/project/ferris/ferris.fbx` becomes `$/ferris/ferris.fbx` given that `project_root` is `/project`
+pub fn from_fs_abs<R: AsRef<Path>, P: AsRef<Path>>(project_root: R, fs_path: P) -> Option<PathBuf> {
+    let rel_path = fs_path.as_ref().strip_prefix(project_root).ok()?;
+
+    let mut path = PathBuf::from(PROJECT_ROOT_RUNE);
+    path.push(rel_path);
 
-    abs_path
+    Some(path)
 }
@@ -0,0 +1,369 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use raven_ecs::typetag;
+
+use crate::error::Result;
+use crate::resource::Texture;
+
+use super::{FragmentShader, PostContext, PostEffect, Target};
+
+/// Maps HDR colours to the 0 to 1 range that can be displayed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ToneMapping {
+    pub operator: ToneMapOperator,
+    /// Colours are multiplied by this before being mapped
+    pub exposure: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapOperator {
+    /// Simple and desaturates bright colours
+    Reinhard,
+    /// Filmic curve, more contrast and keeps bright colours saturated
+    Aces,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            operator: ToneMapOperator::Aces,
+            exposure: 1.0,
+        }
+    }
+}
+
+const TONE_MAPPING: FragmentShader = FragmentShader {
+    name: "tone_mapping",
+    source: r"
+#version 330 core
+
+in vec2 uv;
+out vec4 color;
+
+uniform sampler2D tex0;
+uniform float exposure;
+// 0 for Reinhard, 1 for ACES
+uniform int mode;
+
+// Krzysztof Narkowicz's fit of the ACES curve
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec4 hdr = texture(tex0, uv);
+    vec3 exposed = hdr.rgb * exposure;
+
+    vec3 mapped = mode == 0 ? exposed / (exposed + vec3(1.0)) : aces(exposed);
+
+    color = vec4(mapped, hdr.a);
+}
+",
+};
+
+#[typetag::serde]
+impl PostEffect for ToneMapping {
+    fn apply(&self, ctx: &mut PostContext, input: &Texture) -> Result<()> {
+        ctx.draw(Target::Output, &TONE_MAPPING, &[input], |shader| {
+            shader.set_float("exposure", self.exposure);
+            shader.set_int("mode", match self.operator {
+                ToneMapOperator::Reinhard => 0,
+                ToneMapOperator::Aces => 1,
+            });
+        })
+    }
+}
+
+/// Converts linear colours to the gamma space monitors expect.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GammaCorrection {
+    pub gamma: f32,
+}
+
+impl Default for GammaCorrection {
+    fn default() -> Self {
+        GammaCorrection {
+            gamma: 2.2,
+        }
+    }
+}
+
+const GAMMA_CORRECTION: FragmentShader = FragmentShader {
+    name: "gamma_correction",
+    source: r"
+#version 330 core
+
+in vec2 uv;
+out vec4 color;
+
+uniform sampler2D tex0;
+uniform float gamma;
+
+void main() {
+    vec4 linear = texture(tex0, uv);
+    color = vec4(pow(max(linear.rgb, vec3(0.0)), vec3(1.0 / gamma)), linear.a);
+}
+",
+};
+
+#[typetag::serde]
+impl PostEffect for GammaCorrection {
+    fn apply(&self, ctx: &mut PostContext, input: &Texture) -> Result<()> {
+        ctx.draw(Target::Output, &GAMMA_CORRECTION, &[input], |shader| {
+            shader.set_float("gamma", self.gamma);
+        })
+    }
+}
+
+/// Makes bright areas bleed light into their surroundings. Belongs before `ToneMapping`, since only HDR colours can be
+/// bright enough to go over the threshold.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Bloom {
+    /// How bright a colour must be, in its brightest channel, to bloom
+    pub threshold: f32,
+    /// How much of the bloom is added back to the image
+    pub intensity: f32,
+    /// Blur passes, more make the bloom spread farther
+    pub iterations: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 1.0,
+            intensity: 0.5,
+            iterations: 4,
+        }
+    }
+}
+
+const BLOOM_BRIGHT: FragmentShader = FragmentShader {
+    name: "bloom_bright",
+    source: r"
+#version 330 core
+
+in vec2 uv;
+out vec4 color;
+
+uniform sampler2D tex0;
+uniform float threshold;
+
+void main() {
+    vec3 c = texture(tex0, uv).rgb;
+    float brightness = max(c.r, max(c.g, c.b));
+
+    // Only keeps what's above the threshold, so that bloom fades in rather than popping
+    color = vec4(c * max(brightness - threshold, 0.0) / max(brightness, 0.0001), 1.0);
+}
+",
+};
+
+// Separable gaussian blur, along `direction`
+const BLOOM_BLUR: FragmentShader = FragmentShader {
+    name: "bloom_blur",
+    source: r"
+#version 330 core
+
+in vec2 uv;
+out vec4 color;
+
+uniform sampler2D tex0;
+uniform vec2 texelSize;
+uniform vec2 direction;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec2 step = direction * texelSize;
+    vec3 sum = texture(tex0, uv).rgb * weights[0];
+
+    for (int i = 1; i < 5; i++) {
+        sum += texture(tex0, uv + step * float(i)).rgb * weights[i];
+        sum += texture(tex0, uv - step * float(i)).rgb * weights[i];
+    }
+
+    color = vec4(sum, 1.0);
+}
+",
+};
+
+const BLOOM_COMBINE: FragmentShader = FragmentShader {
+    name: "bloom_combine",
+    source: r"
+#version 330 core
+
+in vec2 uv;
+out vec4 color;
+
+uniform sampler2D tex0;
+uniform sampler2D tex1;
+uniform float intensity;
+
+void main() {
+    vec4 scene = texture(tex0, uv);
+    color = vec4(scene.rgb + texture(tex1, uv).rgb * intensity, scene.a);
+}
+",
+};
+
+#[typetag::serde]
+impl PostEffect for Bloom {
+    fn apply(&self, ctx: &mut PostContext, input: &Texture) -> Result<()> {
+        // Blurring at half resolution is cheaper and spreads farther
+        let [width, height] = ctx.get_output_size();
+        let size = [(width / 2).max(1), (height / 2).max(1)];
+
+        let horizontal = ctx.get_target("bloom_horizontal", size)?;
+        let vertical = ctx.get_target("bloom_vertical", size)?;
+
+        ctx.draw(Target::Framebuffer(&vertical), &BLOOM_BRIGHT, &[input], |shader| {
+            shader.set_float("threshold", self.threshold);
+        })?;
+
+        for _ in 0..self.iterations {
+            ctx.draw(Target::Framebuffer(&horizontal), &BLOOM_BLUR, &[vertical.get_tex()], |shader| {
+                shader.set_vec2("direction", Vec2::X);
+            })?;
+            ctx.draw(Target::Framebuffer(&vertical), &BLOOM_BLUR, &[horizontal.get_tex()], |shader| {
+                shader.set_vec2("direction", Vec2::Y);
+            })?;
+        }
+
+        ctx.draw(Target::Output, &BLOOM_COMBINE, &[input, vertical.get_tex()], |shader| {
+            shader.set_float("intensity", self.intensity);
+        })
+    }
+}
+
+/// Fast approximate anti-aliasing, smooths jagged edges by blurring along them. Expects colours between 0 and 1, so it
+/// belongs after `ToneMapping`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Fxaa {
+    /// How far along an edge, in pixels, it looks for colours to blend
+    pub span_max: f32,
+}
+
+impl Default for Fxaa {
+    fn default() -> Self {
+        Fxaa {
+            span_max: 8.0,
+        }
+    }
+}
+
+const FXAA: FragmentShader = FragmentShader {
+    name: "fxaa",
+    source: r"
+#version 330 core
+
+in vec2 uv;
+out vec4 color;
+
+uniform sampler2D tex0;
+uniform vec2 texelSize;
+uniform float spanMax;
+
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+
+void main() {
+    float lumaNW = dot(texture(tex0, uv + vec2(-1.0, -1.0) * texelSize).rgb, LUMA);
+    float lumaNE = dot(texture(tex0, uv + vec2(1.0, -1.0) * texelSize).rgb, LUMA);
+    float lumaSW = dot(texture(tex0, uv + vec2(-1.0, 1.0) * texelSize).rgb, LUMA);
+    float lumaSE = dot(texture(tex0, uv + vec2(1.0, 1.0) * texelSize).rgb, LUMA);
+    vec4 center = texture(tex0, uv);
+    float lumaM = dot(center.rgb, LUMA);
+
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    // Perpendicular to the gradient, so along the edge
+    vec2 dir = vec2(-((lumaNW + lumaNE) - (lumaSW + lumaSE)), (lumaNW + lumaSW) - (lumaNE + lumaSE));
+
+    float dirReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float rcpDirMin = 1.0 / (min(abs(dir.x), abs(dir.y)) + dirReduce);
+    dir = clamp(dir * rcpDirMin, vec2(-spanMax), vec2(spanMax)) * texelSize;
+
+    vec3 rgbA = 0.5 * (
+        texture(tex0, uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+        texture(tex0, uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 rgbB = rgbA * 0.5 + 0.25 * (
+        texture(tex0, uv - dir * 0.5).rgb +
+        texture(tex0, uv + dir * 0.5).rgb);
+
+    // The wider sample went past the edge, fall back to the narrower one
+    float lumaB = dot(rgbB, LUMA);
+    color = vec4(lumaB < lumaMin || lumaB > lumaMax ? rgbA : rgbB, center.a);
+}
+",
+};
+
+#[typetag::serde]
+impl PostEffect for Fxaa {
+    fn apply(&self, ctx: &mut PostContext, input: &Texture) -> Result<()> {
+        ctx.draw(Target::Output, &FXAA, &[input], |shader| {
+            shader.set_float("spanMax", self.span_max);
+        })
+    }
+}
+
+/// Darkens the corners of the image.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Vignette {
+    /// How dark the corners get, 0 does nothing and 1 makes them black
+    pub intensity: f32,
+    /// Distance from the center, where the image is 1 across, at which darkening starts
+    pub radius: f32,
+    /// Distance over which it goes from no darkening to the full intensity
+    pub smoothness: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Vignette {
+            intensity: 0.5,
+            radius: 0.35,
+            smoothness: 0.4,
+        }
+    }
+}
+
+const VIGNETTE: FragmentShader = FragmentShader {
+    name: "vignette",
+    source: r"
+#version 330 core
+
+in vec2 uv;
+out vec4 color;
+
+uniform sampler2D tex0;
+uniform float intensity;
+uniform float radius;
+uniform float smoothness;
+
+void main() {
+    vec4 c = texture(tex0, uv);
+    float darkening = smoothstep(radius, radius + smoothness, distance(uv, vec2(0.5)));
+
+    color = vec4(c.rgb * (1.0 - darkening * intensity), c.a);
+}
+",
+};
+
+#[typetag::serde]
+impl PostEffect for Vignette {
+    fn apply(&self, ctx: &mut PostContext, input: &Texture) -> Result<()> {
+        ctx.draw(Target::Output, &VIGNETTE, &[input], |shader| {
+            shader.set_float("intensity", self.intensity);
+            shader.set_float("radius", self.radius);
+            shader.set_float("smoothness", self.smoothness);
+        })
+    }
+}
@@ -0,0 +1,357 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::rc::Rc;
+
+use gl;
+use glam::Vec2;
+
+use raven_ecs::typetag;
+
+use crate::error::{Error, Result};
+use crate::framebuffer::Framebuffer;
+use crate::resource::Texture;
+use crate::shader::{Shader, ShaderComponent, ShaderComponentType};
+
+pub use effects::*;
+
+mod effects;
+
+/// A full screen pass applied to what a camera has drawn, before it's copied to the camera's target.
+///
+/// Cameras draw the scene in HDR, so the first effects of a stack get colours that can be greater than 1. Effects that
+/// need colours between 0 and 1, like `Fxaa`, should come after a `ToneMapping`.
+///
+/// Effects are saved along with the camera, so custom ones must be registered with `#[typetag::serde]` the same way as
+/// components, with `typetag` coming from `raven_core::ecs`.
+#[typetag::serde(tag = "type")]
+pub trait PostEffect: PostEffectBase {
+    /// Draws `input` with the effect applied to `Target::Output`.
+    fn apply(&self, ctx: &mut PostContext, input: &Texture) -> Result<()>;
+}
+
+/// Implemented for every `PostEffect` that is `Clone`, so that cameras can be cloned and the editor can downcast
+/// effects to inspect them.
+pub trait PostEffectBase {
+    fn clone_box(&self) -> Box<dyn PostEffect>;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: PostEffect + Clone + 'static> PostEffectBase for T {
+    fn clone_box(&self) -> Box<dyn PostEffect> {
+        Box::new(self.clone())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn PostEffect> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Fragment shader of a full screen pass.
+///
+/// It gets the `uv` of the fragment, the inputs of the pass as `tex0`, `tex1` and so on, and the size of a texel of the
+/// first input as `texelSize`, and must write to `color`. Shaders are compiled the first time they're used and cached
+/// by name.
+pub struct FragmentShader {
+    pub name: &'static str,
+    pub source: &'static str,
+}
+
+pub enum Target<'a> {
+    /// Where the effect must draw its result
+    Output,
+    /// An intermediate framebuffer, from `PostContext::get_target`
+    Framebuffer(&'a Framebuffer),
+}
+
+/// Gives effects the means to draw full screen passes.
+pub struct PostContext<'a> {
+    processor: &'a mut PostProcessor,
+    output: Output,
+}
+
+#[derive(Clone, Copy)]
+struct Output {
+    framebuffer_id: u32,
+    viewport: [i32; 4],
+}
+
+impl<'a> PostContext<'a> {
+    /// Size in pixels of `Target::Output`.
+    pub fn get_output_size(&self) -> [u32; 2] {
+        let [_, _, width, height] = self.output.viewport;
+        [width as _, height as _]
+    }
+
+    /// Returns an HDR framebuffer of the given size for intermediate results, which is reused on the next frames as long
+    /// as it's asked for with the same name and size.
+    pub fn get_target(&mut self, name: &'static str, size: [u32; 2]) -> Result<Rc<Framebuffer>> {
+        self.processor.get_target(name, size)
+    }
+
+    /// Draws a full screen pass to `target`, with the `inputs` bound in order. `set_uniforms` is called with the shader
+    /// enabled, to set the uniforms other than the inputs.
+    pub fn draw<F: FnOnce(&mut Shader)>(
+        &mut self,
+        target: Target,
+        shader: &FragmentShader,
+        inputs: &[&Texture],
+        set_uniforms: F,
+    ) -> Result<()> {
+        match target {
+            Target::Output => bind(self.output.framebuffer_id, self.output.viewport),
+            Target::Framebuffer(framebuffer) => {
+                let [width, height] = framebuffer.get_tex().size;
+                bind(framebuffer.get_id(), [0, 0, width as _, height as _]);
+            }
+        }
+
+        self.processor.draw(shader, inputs, set_uniforms)
+    }
+}
+
+const FULL_SCREEN_VERT_SHADER: &'static str = r"
+#version 330 core
+layout (location = 0) in vec2 pos_in;
+
+out vec2 uv;
+
+void main() {
+    uv = pos_in * 0.5 + 0.5;
+    gl_Position = vec4(pos_in, 0.0, 1.0);
+}
+";
+
+const COPY: FragmentShader = FragmentShader {
+    name: "copy",
+    source: r"
+#version 330 core
+
+in vec2 uv;
+out vec4 color;
+
+uniform sampler2D tex0;
+
+void main() {
+    color = texture(tex0, uv);
+}
+",
+};
+
+/// Runs cameras' post-processing stacks and owns what they need to do it.
+pub(crate) struct PostProcessor {
+    vao_id: u32,
+    vbo_id: u32,
+
+    shaders: HashMap<&'static str, Shader>,
+    // Also tells whether each one was used during the current frame
+    targets: HashMap<(&'static str, [u32; 2]), (Rc<Framebuffer>, bool)>,
+}
+
+impl PostProcessor {
+    pub(crate) fn new() -> PostProcessor {
+        let mut vao_id = 0;
+        let mut vbo_id = 0;
+
+        // A single triangle covering the whole screen, the parts outside of it are clipped
+        let vertices: [f32; 6] = [-1.0, -1.0, 3.0, -1.0, -1.0, 3.0];
+
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao_id);
+            gl::BindVertexArray(vao_id);
+
+            gl::GenBuffers(1, &mut vbo_id);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as _,
+                vertices.as_ptr() as _,
+                gl::STATIC_DRAW,
+            );
+
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, (2 * std::mem::size_of::<f32>()) as _, 0 as _);
+            gl::EnableVertexArrayAttrib(vao_id, 0);
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        PostProcessor {
+            vao_id,
+            vbo_id,
+
+            shaders: HashMap::new(),
+            targets: HashMap::new(),
+        }
+    }
+
+    /// Returns the HDR framebuffer a camera with a viewport of the given size draws the scene into.
+    pub(crate) fn get_scene_target(&mut self, size: [u32; 2]) -> Result<Rc<Framebuffer>> {
+        self.get_target("scene", size)
+    }
+
+    /// Applies the effects to `scene` one after the other and draws the result to the given viewport of the given
+    /// framebuffer. With no effects, `scene` is just copied.
+    pub(crate) fn resolve(
+        &mut self,
+        effects: &[Box<dyn PostEffect>],
+        scene: &Texture,
+        framebuffer_id: u32,
+        viewport: [i32; 4],
+    ) -> Result<()> {
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+
+        let result = self.apply_effects(effects, scene, Output { framebuffer_id, viewport });
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+        }
+
+        result
+    }
+
+    fn apply_effects(&mut self, effects: &[Box<dyn PostEffect>], scene: &Texture, output: Output) -> Result<()> {
+        if effects.is_empty() {
+            let mut ctx = PostContext { processor: self, output };
+            return ctx.draw(Target::Output, &COPY, &[scene], |_| {});
+        }
+
+        let [_, _, width, height] = output.viewport;
+        let mut input: Option<Rc<Framebuffer>> = None;
+
+        for (i, effect) in effects.iter().enumerate() {
+            let is_last = i == effects.len() - 1;
+
+            // Intermediate results go back and forth between two framebuffers
+            let framebuffer = if is_last {
+                None
+            } else {
+                Some(self.get_target(if i % 2 == 0 { "ping" } else { "pong" }, [width as _, height as _])?)
+            };
+
+            let effect_output = match &framebuffer {
+                Some(framebuffer) => Output {
+                    framebuffer_id: framebuffer.get_id(),
+                    viewport: [0, 0, width, height],
+                },
+                None => output,
+            };
+
+            let mut ctx = PostContext { processor: self, output: effect_output };
+            effect.apply(&mut ctx, input.as_ref().map_or(scene, |input| input.get_tex()))?;
+
+            input = framebuffer;
+        }
+
+        Ok(())
+    }
+
+    /// Frees the framebuffers that weren't used during the frame, for example because a viewport changed size.
+    pub(crate) fn end_frame(&mut self) {
+        self.targets.retain(|_, (_, used)| *used);
+
+        for (_, used) in self.targets.values_mut() {
+            *used = false;
+        }
+    }
+
+    pub(crate) fn get_targets(&self) -> impl Iterator<Item=&Framebuffer> {
+        self.targets.values().map(|(framebuffer, _)| framebuffer.as_ref())
+    }
+
+    fn get_target(&mut self, name: &'static str, size: [u32; 2]) -> Result<Rc<Framebuffer>> {
+        let (framebuffer, used) = match self.targets.entry((name, size)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let [width, height] = size;
+                entry.insert((Rc::new(Framebuffer::new_hdr((width as _, height as _))?), false))
+            }
+        };
+
+        *used = true;
+
+        Ok(framebuffer.clone())
+    }
+
+    fn draw<F: FnOnce(&mut Shader)>(
+        &mut self,
+        shader: &FragmentShader,
+        inputs: &[&Texture],
+        set_uniforms: F,
+    ) -> Result<()> {
+        if !self.shaders.contains_key(shader.name) {
+            let build = || {
+                Shader::new()
+                    .with_component(ShaderComponent::new(FULL_SCREEN_VERT_SHADER, ShaderComponentType::VERTEX)?)
+                    .with_component(ShaderComponent::new(shader.source, ShaderComponentType::FRAGMENT)?)
+                    .build()
+            };
+
+            let compiled = build().map_err(|err| match err {
+                Error::Shader { log, .. } => Error::Shader { name: Some(shader.name.to_owned()), log },
+                err => err,
+            })?;
+
+            self.shaders.insert(shader.name, compiled);
+        }
+
+        let compiled = self.shaders.get_mut(shader.name).unwrap();
+        compiled.enable();
+
+        for (i, input) in inputs.iter().enumerate() {
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0 + i as u32);
+                gl::BindTexture(gl::TEXTURE_2D, input.id.expect("texture not loaded"));
+            }
+
+            compiled.set_int(format!("tex{}", i), i as _);
+        }
+
+        if let Some(first) = inputs.first() {
+            let [width, height] = first.size;
+            compiled.set_vec2("texelSize", Vec2::new(1.0 / width as f32, 1.0 / height as f32));
+        }
+
+        set_uniforms(compiled);
+
+        unsafe {
+            gl::BindVertexArray(self.vao_id);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            gl::BindVertexArray(0);
+
+            // Leave texture unit 0 active, which is the one everything else uses
+            for i in (0..inputs.len()).rev() {
+                gl::ActiveTexture(gl::TEXTURE0 + i as u32);
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for PostProcessor {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao_id);
+            gl::DeleteBuffers(1, &self.vbo_id);
+        }
+    }
+}
+
+fn bind(framebuffer_id: u32, viewport: [i32; 4]) {
+    let [x, y, width, height] = viewport;
+
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer_id);
+        gl::Viewport(x, y, width, height);
+    }
+}
@@ -0,0 +1,316 @@
+//! Meshes generated from code rather than imported, for quickly putting together scenes.
+//!
+//! Every mesh is centred in the origin with Y up, has smooth normals where the surface is curved and UVs covering the
+//! whole texture once.
+
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use glam::{Vec2, Vec3};
+
+use crate::resource::{Mesh, Vertex};
+
+/// The shapes there's a generator for, with the sizes the editor creates them with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Primitive {
+    Cube,
+    Sphere,
+    Capsule,
+    Cylinder,
+    Plane,
+    Torus,
+}
+
+impl Primitive {
+    pub const ALL: [Primitive; 6] = [
+        Primitive::Cube,
+        Primitive::Sphere,
+        Primitive::Capsule,
+        Primitive::Cylinder,
+        Primitive::Plane,
+        Primitive::Torus,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Primitive::Cube => "Cube",
+            Primitive::Sphere => "Sphere",
+            Primitive::Capsule => "Capsule",
+            Primitive::Cylinder => "Cylinder",
+            Primitive::Plane => "Plane",
+            Primitive::Torus => "Torus",
+        }
+    }
+
+    /// The mesh with its default size, a unit wide except for the plane, which is ten.
+    pub fn mesh(self) -> Mesh {
+        match self {
+            Primitive::Cube => cube(1.0),
+            Primitive::Sphere => sphere(0.5, 32, 16),
+            Primitive::Capsule => capsule(0.5, 2.0, 32, 8),
+            Primitive::Cylinder => cylinder(0.5, 2.0, 32),
+            Primitive::Plane => plane(10.0, 10),
+            Primitive::Torus => torus(0.35, 0.15, 32, 16),
+        }
+    }
+}
+
+/// A cube with the given side, each face mapped to the whole texture.
+pub fn cube(size: f32) -> Mesh {
+    let mut builder = Builder::default();
+
+    // Normal, then the directions of U and V on the face
+    let faces = [
+        (Vec3::X, -Vec3::Z, Vec3::Y),
+        (-Vec3::X, Vec3::Z, Vec3::Y),
+        (Vec3::Y, Vec3::X, -Vec3::Z),
+        (-Vec3::Y, Vec3::X, Vec3::Z),
+        (Vec3::Z, Vec3::X, Vec3::Y),
+        (-Vec3::Z, -Vec3::X, Vec3::Y),
+    ];
+
+    for &(normal, u, v) in &faces {
+        let half = size / 2.0;
+        let center = normal * half;
+
+        let first = builder.vertices.len() as u32;
+
+        for &(x, y) in &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            let position = center + (u * (x * 2.0 - 1.0) + v * (y * 2.0 - 1.0)) * half;
+            builder.vertices.push(Vertex::new(position, normal, Vec2::new(x, y)));
+        }
+
+        builder.indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    builder.build()
+}
+
+/// A UV sphere, with `segments` around its vertical axis and `rings` from pole to pole.
+pub fn sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    let rings = rings.max(2);
+
+    let profile: Vec<_> = (0..=rings)
+        .map(|j| {
+            let v = j as f32 / rings as f32;
+            let normal = from_pole(v * PI);
+
+            (normal * radius, normal, v)
+        })
+        .collect();
+
+    let mut builder = Builder::default();
+    builder.revolve(&profile, segments);
+    builder.build()
+}
+
+/// A cylinder capped by two hemispheres, `height` tall in total. Each hemisphere has `rings` from the pole down.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+    let rings = rings.max(1);
+    let cylinder = (height - radius * 2.0).max(0.0);
+
+    // V follows the length of the outline, so that the texture isn't stretched on the cylinder
+    let length = PI * radius + cylinder;
+
+    let mut profile = Vec::new();
+
+    for &(offset, start) in &[(-cylinder / 2.0, 0.0), (cylinder / 2.0, FRAC_PI_2)] {
+        for j in 0..=rings {
+            let angle = start + j as f32 / rings as f32 * FRAC_PI_2;
+            let normal = from_pole(angle);
+
+            let arc = angle * radius + if offset > 0.0 { cylinder } else { 0.0 };
+
+            profile.push((normal * radius + Vec2::new(0.0, offset), normal, arc / length));
+        }
+    }
+
+    let mut builder = Builder::default();
+    builder.revolve(&profile, segments);
+    builder.build()
+}
+
+/// A cylinder `height` tall, closed at both ends.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> Mesh {
+    let half = height / 2.0;
+
+    let mut builder = Builder::default();
+
+    builder.revolve(&[
+        (Vec2::new(radius, -half), Vec2::X, 0.0),
+        (Vec2::new(radius, half), Vec2::X, 1.0),
+    ], segments);
+
+    builder.disc(-half, radius, segments);
+    builder.disc(half, radius, segments);
+
+    builder.build()
+}
+
+/// A square on the XZ plane facing up, split into `subdivisions` squares along each side.
+pub fn plane(size: f32, subdivisions: u32) -> Mesh {
+    let subdivisions = subdivisions.max(1);
+
+    let mut builder = Builder::default();
+
+    for j in 0..=subdivisions {
+        for i in 0..=subdivisions {
+            let uv = Vec2::new(i as f32, j as f32) / subdivisions as f32;
+            let position = Vec3::new(uv.x - 0.5, 0.0, 0.5 - uv.y) * size;
+
+            builder.vertices.push(Vertex::new(position, Vec3::Y, uv));
+        }
+    }
+
+    builder.grid(0, subdivisions, subdivisions);
+    builder.build()
+}
+
+/// A ring lying on the XZ plane, `radius` being the distance from the center to the middle of the tube. The tube is
+/// split into `sides` around itself.
+pub fn torus(radius: f32, tube_radius: f32, segments: u32, sides: u32) -> Mesh {
+    let sides = sides.max(3);
+
+    // Around the tube starting from the inside, under it first
+    let profile: Vec<_> = (0..=sides)
+        .map(|j| {
+            let v = j as f32 / sides as f32;
+            let angle = v * TAU - PI;
+            let normal = Vec2::new(angle.cos(), angle.sin());
+
+            (Vec2::new(radius, 0.0) + normal * tube_radius, normal, v)
+        })
+        .collect();
+
+    let mut builder = Builder::default();
+    builder.revolve(&profile, segments);
+    builder.build()
+}
+
+/// The direction at the given angle from the bottom pole, on the plane of a profile.
+fn from_pole(angle: f32) -> Vec2 {
+    Vec2::new(angle.sin(), -angle.cos())
+}
+
+#[derive(Default)]
+struct Builder {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Builder {
+    /// Spins a profile around the Y axis. The profile goes from the bottom up as `(point, normal, v)`, where the points
+    /// and normals are `(distance from the axis, height)`.
+    fn revolve(&mut self, profile: &[(Vec2, Vec2, f32)], segments: u32) {
+        let segments = segments.max(3);
+        let first = self.vertices.len() as u32;
+
+        for &(point, normal, v) in profile {
+            // The seam has its vertices twice, at the left and right edge of the texture
+            for i in 0..=segments {
+                let u = i as f32 / segments as f32;
+                let (sin, cos) = (u * TAU).sin_cos();
+
+                self.vertices.push(Vertex::new(
+                    Vec3::new(point.x * cos, point.y, -point.x * sin),
+                    Vec3::new(normal.x * cos, normal.y, -normal.x * sin).normalize(),
+                    Vec2::new(u, v),
+                ));
+            }
+        }
+
+        self.grid(first, segments, profile.len() as u32 - 1);
+    }
+
+    /// A flat disc at height `y`, facing away from the origin.
+    fn disc(&mut self, y: f32, radius: f32, segments: u32) {
+        let segments = segments.max(3);
+        let normal = Vec3::Y * y.signum();
+
+        let center = self.vertices.len() as u32;
+        self.vertices.push(Vertex::new(Vec3::new(0.0, y, 0.0), normal, Vec2::splat(0.5)));
+
+        for i in 0..segments {
+            let (sin, cos) = (i as f32 / segments as f32 * TAU).sin_cos();
+            let uv = Vec2::new(cos, -sin * normal.y) / 2.0 + Vec2::splat(0.5);
+
+            self.vertices.push(Vertex::new(Vec3::new(cos * radius, y, -sin * radius), normal, uv));
+        }
+
+        for i in 0..segments {
+            let (a, b) = (center + 1 + i, center + 1 + (i + 1) % segments);
+
+            // Counterclockwise seen from where the disc faces
+            if y >= 0.0 {
+                self.indices.extend_from_slice(&[center, a, b]);
+            } else {
+                self.indices.extend_from_slice(&[center, b, a]);
+            }
+        }
+    }
+
+    /// Two triangles for every square of a grid of `rows + 1` rows of `columns + 1` vertices, starting at `first`.
+    /// Front faces are counterclockwise when U goes right and V goes up.
+    fn grid(&mut self, first: u32, columns: u32, rows: u32) {
+        for j in 0..rows {
+            for i in 0..columns {
+                let a = first + j * (columns + 1) + i;
+                let (b, c, d) = (a + 1, a + columns + 2, a + columns + 1);
+
+                self.indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+    }
+
+    fn build(self) -> Mesh {
+        Mesh::new(self.vertices, self.indices)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+
+    use super::Primitive;
+
+    #[test]
+    fn faces_point_along_the_normals() {
+        for primitive in Primitive::ALL {
+            let mesh = primitive.mesh();
+
+            assert!(!mesh.indices.is_empty());
+            assert!(mesh.indices.iter().all(|&index| (index as usize) < mesh.vertices.len()));
+
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+                let face_normal = (b.position - a.position).cross(c.position - a.position);
+
+                // Triangles collapsed into a point at the poles have no normal
+                if face_normal.length() < 1e-6 {
+                    continue;
+                }
+
+                for vertex in [a, b, c] {
+                    assert!((vertex.normal.length() - 1.0).abs() < 1e-4, "{:?}", primitive);
+                    assert!(vertex.normal.dot(face_normal) > 0.0, "{:?}", primitive);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn has_the_default_size() {
+        let size = |primitive: Primitive| {
+            let aabb = primitive.mesh().aabb;
+            aabb.max - aabb.min
+        };
+
+        let close = |a: Vec3, b: Vec3| (a - b).abs().max_element() < 1e-4;
+
+        assert!(close(size(Primitive::Cube), Vec3::ONE));
+        assert!(close(size(Primitive::Sphere), Vec3::ONE));
+        assert!(close(size(Primitive::Capsule), Vec3::new(1.0, 2.0, 1.0)));
+        assert!(close(size(Primitive::Cylinder), Vec3::new(1.0, 2.0, 1.0)));
+        assert!(close(size(Primitive::Plane), Vec3::new(10.0, 0.0, 10.0)));
+        assert!(close(size(Primitive::Torus), Vec3::new(1.0, 0.3, 1.0)));
+    }
+}
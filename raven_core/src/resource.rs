@@ -1,46 +1,474 @@
 use std::ops::{Deref, DerefMut};
-use std::path::PathBuf;
+use std::path::Path;
 
-use glam::{Vec2, Vec3};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
 use serde::{Deserialize, Serialize};
 
 use raven_ecs::World;
 
+use crate::animation::{ComponentField, Track};
+use crate::database::AssetRef;
+
 #[derive(Serialize, Deserialize)]
 pub struct Texture {
+    /// Pixels of the full size image, encoded according to `format`
     pub raw: Vec<u8>,
     pub size: [u32; 2],
+    pub format: TextureFormat,
+    /// Pre-baked mipmaps, each half the size of the previous one, down to 1x1. When there are none and the sampler uses
+    /// mipmaps, they're generated when loading, which is only possible for uncompressed textures
+    pub mips: Vec<Vec<u8>>,
+    pub color_space: ColorSpace,
+    pub sampler: Sampler,
+    /// Whether to keep the pixels in memory after uploading them to the GPU
+    pub readable: bool,
 
     #[serde(skip)]
     pub(crate) id: Option<u32>,
+    // Set when loaded
+    #[serde(skip)]
+    pub(crate) gpu_bytes: usize,
 }
 
 impl Texture {
+    /// Creates an uncompressed texture from RGBA pixels, with the default settings.
     pub fn new(raw: Vec<u8>, size: [u32; 2]) -> Texture {
         Texture {
             raw,
             size,
+            format: TextureFormat::Rgba8,
+            mips: Vec::new(),
+            color_space: ColorSpace::default(),
+            sampler: Sampler::default(),
+            readable: false,
             id: None,
+            gpu_bytes: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TextureFormat {
+    /// Uncompressed, 4 bytes per pixel
+    Rgba8,
+    /// BC1 (DXT1), 8 bytes per 4x4 block, no alpha
+    Bc1,
+    /// BC3 (DXT5), 16 bytes per 4x4 block
+    Bc3,
+    /// ETC2, 8 bytes per 4x4 block, no alpha
+    Etc2Rgb8,
+    /// ETC2 with EAC alpha, 16 bytes per 4x4 block
+    Etc2Rgba8,
+}
+
+impl TextureFormat {
+    pub fn is_compressed(&self) -> bool {
+        *self != TextureFormat::Rgba8
+    }
+
+    /// Bytes taken up by an image of the given size in this format.
+    pub fn level_bytes(&self, size: [u32; 2]) -> usize {
+        let [width, height] = size;
+        let blocks = ((width + 3) / 4) as usize * ((height + 3) / 4) as usize;
+
+        match self {
+            TextureFormat::Rgba8 => (width * height) as usize * 4,
+            TextureFormat::Bc1 | TextureFormat::Etc2Rgb8 => blocks * 8,
+            TextureFormat::Bc3 | TextureFormat::Etc2Rgba8 => blocks * 16,
+        }
+    }
+}
+
+/// How the colours of a texture are to be interpreted.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ColorSpace {
+    /// Used as they are, for textures holding data rather than colours, such as normal maps
+    Linear,
+    /// Converted to linear when sampled, for colour textures painted or photographed as they appear on screen. Only
+    /// looks right if the camera converts back with `GammaCorrection`
+    Srgb,
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        // Nothing is gamma corrected unless asked for, so this keeps textures looking as they did in their images
+        ColorSpace::Linear
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Sampler {
+    pub wrap: Wrap,
+    /// Used when the texture is drawn smaller than it is
+    pub min_filter: Filter,
+    /// Used when the texture is drawn bigger than it is
+    pub mag_filter: Filter,
+    /// Off by default, so that textures without settings are filtered as they were before samplers existed
+    pub mipmaps: bool,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler {
+            wrap: Wrap::Repeat,
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmaps: false,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    /// Pixelated
+    Nearest,
+    /// Smooth
+    Linear,
+}
+
+/// An offscreen texture that cameras can render to and materials can sample from.
 #[derive(Serialize, Deserialize)]
+pub struct RenderTexture {
+    pub size: [u32; 2],
+}
+
+impl RenderTexture {
+    pub const EXTENSION: &'static str = "rtex";
+
+    /// Materials refer to render textures the same way they refer to regular textures, the extension tells them apart.
+    pub fn is_render_texture<P: AsRef<Path>>(path: P) -> bool {
+        path.as_ref().extension().map_or(false, |ext| ext == Self::EXTENSION)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
 pub struct Material {
-    pub tex: Option<PathBuf>,
+    pub tex: Option<AssetRef>,
+    /// How the material is combined with what's already been drawn. Materials saved before blend modes existed are
+    /// opaque
+    #[serde(default)]
+    pub blend: BlendMode,
+    /// Multiplies the colour of the texture, or is used instead of it when there's none. Materials without one are drawn
+    /// with the texture as it is, or grey
+    #[serde(default)]
+    pub color: Option<[f32; 4]>,
+    /// Physically based factors from imported files. Shading is unlit, so they're kept but not used yet
+    #[serde(default)]
+    pub pbr: Option<PbrFactors>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PbrFactors {
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    /// Ignores the texture's alpha
+    Opaque,
+    /// Discards the fragments whose alpha is below the cutoff and draws the others as opaque, for foliage and fences
+    AlphaTest { cutoff: f32 },
+    /// Mixes the colour with what's behind it according to its alpha, for glass and smoke
+    AlphaBlend,
+    /// Adds the colour, weighted by its alpha, to what's behind it, for fire and glows
+    Additive,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Opaque
+    }
+}
+
+impl BlendMode {
+    /// Transparent materials don't write to the depth buffer and are drawn after the opaque ones, back to front.
+    pub fn is_transparent(&self) -> bool {
+        matches!(self, BlendMode::AlphaBlend | BlendMode::Additive)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Mesh {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
+    /// Bounding box of the vertices, in the mesh's local space
+    pub aabb: Aabb,
+    pub layout: VertexLayout,
 }
 
-#[derive(Serialize, Deserialize)]
+impl Mesh {
+    /// A mesh with UVs, skinned if any vertex is attached to a joint.
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Mesh {
+        let layout = VertexLayout {
+            skin: vertices.iter().any(|vertex| vertex.weights != [0.0; 4]),
+            ..VertexLayout::default()
+        };
+
+        Mesh::with_layout(vertices, indices, layout)
+    }
+
+    pub fn with_layout(vertices: Vec<Vertex>, indices: Vec<u32>, layout: VertexLayout) -> Mesh {
+        let aabb = Aabb::from_points(vertices.iter().map(|vertex| vertex.position));
+
+        Mesh {
+            vertices,
+            indices,
+            aabb,
+            layout,
+        }
+    }
+
+    /// Whether the vertices are attached to joints, in which case the mesh is posed by an `AnimatorComponent`.
+    pub fn is_skinned(&self) -> bool {
+        self.layout.skin
+    }
+}
+
+/// Which of the optional attributes of `Vertex` the vertices of a mesh have. The others are left at their defaults and
+/// aren't sent to the GPU.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexLayout {
+    pub uv: bool,
+    /// Both the tangent and the bitangent
+    pub tangents: bool,
+    pub colors: bool,
+    pub uv2: bool,
+    /// Both the joints and the weights
+    pub skin: bool,
+}
+
+impl Default for VertexLayout {
+    fn default() -> Self {
+        VertexLayout {
+            uv: true,
+            tangents: false,
+            colors: false,
+            uv2: false,
+            skin: false,
+        }
+    }
+}
+
+/// Axis aligned bounding box.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Returns the smallest box containing all the points, or an empty box at the origin if there are none.
+    pub fn from_points<I: IntoIterator<Item=Vec3>>(points: I) -> Aabb {
+        let mut points = points.into_iter();
+
+        let first = match points.next() {
+            Some(first) => first,
+            None => return Aabb { min: Vec3::ZERO, max: Vec3::ZERO },
+        };
+
+        points.fold(Aabb { min: first, max: first }, |aabb, point| Aabb {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        })
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let Aabb { min, max } = *self;
+
+        [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Returns the axis aligned box containing this box after it has been transformed by `mat`.
+    pub fn transform(&self, mat: &Mat4) -> Aabb {
+        Aabb::from_points(self.corners().iter().map(|corner| mat.transform_point3(*corner)))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub struct Vertex {
     pub position: Vec3,
     pub normal: Vec3,
     pub uv: Vec2,
+    /// Indices into the joints of the skeleton the mesh is skinned to, only meaningful where the weight isn't 0
+    pub joints: [u16; 4],
+    /// How much each joint moves the vertex, summing up to 1. All 0 for vertices that aren't skinned
+    pub weights: [f32; 4],
+    /// Directions of increasing U and V on the surface, for normal mapping
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    /// Linear RGBA, multiplies the colour of the material
+    pub color: Vec4,
+    /// Second set of texture coordinates, for lightmaps and detail textures
+    pub uv2: Vec2,
+}
+
+impl Vertex {
+    /// A white vertex that isn't attached to any joint, and has none of the other optional attributes.
+    pub fn new(position: Vec3, normal: Vec3, uv: Vec2) -> Vertex {
+        Vertex {
+            position,
+            normal,
+            uv,
+            joints: [0; 4],
+            weights: [0.0; 4],
+            tangent: Vec3::ZERO,
+            bitangent: Vec3::ZERO,
+            color: Vec4::ONE,
+            uv2: Vec2::ZERO,
+        }
+    }
+}
+
+/// The joints skinned meshes are attached to, posed by the clips of an `AnimatorComponent`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Skeleton {
+    /// Every joint comes after its parent
+    pub joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    pub const EXTENSION: &'static str = "skel";
+
+    /// Most joints a skeleton can have, as many as the standard shader can pose. Their matrices have to fit in the 1024
+    /// uniform components OpenGL 3.3 guarantees to vertex shaders, along with the other uniforms.
+    pub const MAX_JOINTS: usize = 60;
+
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Joint {
+    /// What the channels of animation clips refer to the joint by
+    pub name: String,
+    pub parent: Option<usize>,
+    /// Relative to the parent, or to the entity with the `AnimatorComponent` for the joints without one. Used for the
+    /// parts of the pose that no clip animates
+    pub rest: JointTransform,
+    /// From the space of the meshes to the space of the joint when the meshes were bound to it
+    pub inverse_bind: Mat4,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct JointTransform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for JointTransform {
+    fn default() -> Self {
+        JointTransform {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl JointTransform {
+    pub fn from_mat4(mat: &Mat4) -> JointTransform {
+        let (scale, rotation, translation) = mat.to_scale_rotation_translation();
+
+        JointTransform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn to_mat4(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+/// Keyframed translations, rotations and scales of the joints of a skeleton, matched to them by name so that the same
+/// clip can be played on every skeleton with the same joint names.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AnimationClip {
+    /// In seconds
+    pub duration: f32,
+    pub channels: Vec<JointChannel>,
+}
+
+impl AnimationClip {
+    pub const EXTENSION: &'static str = "anim";
+}
+
+/// The keyframes of a single joint. Empty tracks leave that part of the joint at its rest transform.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct JointChannel {
+    pub joint: String,
+    pub translation: Track<Vec3>,
+    pub rotation: Track<Quat>,
+    pub scale: Track<Vec3>,
+}
+
+/// Keyframed values of the components of an entity and of its descendants, played by a `KeyframeAnimatorComponent`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct KeyframeClip {
+    /// In seconds
+    pub duration: f32,
+    pub channels: Vec<KeyframeChannel>,
+}
+
+impl KeyframeClip {
+    pub const EXTENSION: &'static str = "kanim";
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct KeyframeChannel {
+    /// Names of the entities from a child of the one playing the clip down to the animated one. Empty to animate the
+    /// entity playing the clip
+    pub target: Vec<String>,
+    pub property: AnimatedProperty,
+}
+
+/// What a channel animates, and the keyframes it's animated with.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AnimatedProperty {
+    /// Parts of the `TransformComponent`, the other parts are kept
+    Translation(Track<Vec3>),
+    Rotation(Track<Quat>),
+    Scale(Track<Vec3>),
+    Field(ComponentField, Track<f32>),
+}
+
+/// Source code in Rhai, attached to entities with a `ScriptComponent`. Compiled when it's first run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Script {
+    pub source: String,
+}
+
+impl Script {
+    pub const EXTENSION: &'static str = "rhai";
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -0,0 +1,645 @@
+//! Behaviour attached to entities with a `ScriptComponent`, written in [Rhai](https://rhai.rs).
+//!
+//! A script can define two functions, both optional:
+//!
+//! - `fn on_start(entity)`, called once before the first update
+//! - `fn on_update(entity, delta)`, called every frame with the seconds since the last one
+//!
+//! Functions can't see the variables declared outside of them. What a script needs to remember from one call to the
+//! next goes in `this`, which is an object map of its own, as in `this.speed = 2.0`.
+//!
+//! Entities have these properties, which read and write their components:
+//!
+//! - `name`, of the `NameComponent`
+//! - `position`, `rotation` and `scale`, the parts of the `TransformComponent`, relative to the parent. The rotation is
+//!   in degrees around X, Y and Z, like in the editor
+//! - `parent`, `()` for the entities at the root, and `children`, an array, from the `HierarchyComponent`
+//! - `mesh` and `material`, the `$/` paths of the mesh and the material of the `MeshComponent`
+//! - `camera` and `light`, copies of the `CameraComponent` and the `LightComponent`, written back when assigned, as in
+//!   `entity.camera.fov = 60`. Cameras have `fov`, in degrees, `near`, `far`, `order` and `active`. Lights have
+//!   `color`, a `Vec3`, `intensity` and `range`, `()` when unlimited. Assigning to an entity without one attaches it
+//!
+//! Besides them, scripts can call:
+//!
+//! - `find(name)`, the entity with the given name in the same scene as the script, `()` if there's none
+//! - `is_held(action)`, `was_pressed(action)` and `was_released(action)`, about the actions of the project's
+//!   `ActionMap`, and `is_key_held(key)`, where keys are named like `Key`, such as `"W"` or `"Space"`
+//! - `mouse_delta()`, a `Vec2` with `x` and `y`
+//! - `vec3(x, y, z)`, for the `Vec3`s positions and the like are made of. They have `x`, `y` and `z`, can be added,
+//!   subtracted and multiplied or divided by numbers, and have `length()`, `normalize()`, `dot(other)` and
+//!   `cross(other)`
+//!
+//! A script that fails to compile, or raises an error, stops running until its file changes.
+
+use std::cell::RefCell;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::rc::Rc;
+
+use glam::{EulerRot, Mat4, Quat, Vec2, Vec3};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, ParseError, Scope, AST, FLOAT, INT};
+
+use crate::component::{
+    CameraComponent, HierarchyComponent, LightComponent, MeshComponent, NameComponent, Projection, ScriptComponent,
+    TransformComponent,
+};
+use crate::database::AssetDatabase;
+use crate::ecs::{Component, Entity, Query};
+use crate::error::Error;
+use crate::input::{Button, Input, Key};
+use crate::resource::Scene;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// A compiled script and its state, one per `ScriptComponent`.
+pub(crate) struct ScriptInstance {
+    ast: AST,
+    /// `this` in the hooks
+    this: Dynamic,
+    started: bool,
+}
+
+/// What the functions registered with the engine act on. The scene, the input and the asset database are moved in for
+/// as long as the scripts run, since the engine needs its functions before any scene exists.
+#[derive(Default)]
+struct Context {
+    scene: Scene,
+    input: Input,
+    db: AssetDatabase,
+}
+
+pub(crate) struct ScriptHost {
+    engine: Engine,
+    context: Rc<RefCell<Context>>,
+}
+
+impl ScriptHost {
+    pub(crate) fn new() -> ScriptHost {
+        let mut engine = Engine::new();
+        let context = Rc::new(RefCell::new(Context::default()));
+
+        register_math(&mut engine);
+        register_entity(&mut engine, &context);
+        register_components(&mut engine, &context);
+        register_input(&mut engine, &context);
+
+        ScriptHost {
+            engine,
+            context,
+        }
+    }
+
+    /// Runs the scripts of the scene, not of the scenes nested in it, whose source has been loaded. The ones that fail
+    /// add their error and are stopped.
+    pub(crate) fn run(&self, scene: &mut Scene, input: &mut Input, db: &mut AssetDatabase, delta: f32, errors: &mut Vec<Error>) {
+        let mut ready = Vec::new();
+
+        for (entity, (script_comp, ), (n, )) in <(ScriptComponent, )>::query_deep(scene) {
+            if let (Some(script), Some(_), false) = (&script_comp.script, &script_comp.loaded, script_comp.stopped) {
+                ready.push((entity, n, db.resolve(script).to_owned()));
+            }
+        }
+
+        if ready.is_empty() {
+            return;
+        }
+
+        self.swap(scene, input, db);
+
+        for (entity, n, path) in ready {
+            let taken = self.context.borrow_mut().scene
+                .get_nth_mut::<ScriptComponent>(entity, n)
+                .map(|mut script_comp| (script_comp.instance.take(), script_comp.loaded.clone().unwrap()));
+
+            // Scripts can't destroy entities, but better safe than sorry
+            let (instance, script) = match taken {
+                Some(taken) => taken,
+                None => continue,
+            };
+
+            let result = match instance {
+                Some(instance) => Ok(instance),
+                None => self.compile(&script.source).map_err(|err| parse_error(&path, err)),
+            };
+
+            let result = result.and_then(|mut instance| {
+                self.update(&mut instance, entity, delta)
+                    .map(|()| instance)
+                    .map_err(|err| eval_error(&path, *err))
+            });
+
+            let mut context = self.context.borrow_mut();
+            let script_comp = context.scene.get_nth_mut::<ScriptComponent>(entity, n);
+
+            match (script_comp, result) {
+                (Some(mut script_comp), Ok(instance)) => script_comp.instance = Some(instance),
+                (Some(mut script_comp), Err(err)) => {
+                    script_comp.stopped = true;
+                    errors.push(err);
+                }
+                (None, _) => (),
+            };
+        }
+
+        self.swap(scene, input, db);
+    }
+
+    fn swap(&self, scene: &mut Scene, input: &mut Input, db: &mut AssetDatabase) {
+        let mut context = self.context.borrow_mut();
+
+        mem::swap(&mut context.scene, scene);
+        mem::swap(&mut context.input, input);
+        mem::swap(&mut context.db, db);
+    }
+
+    fn compile(&self, source: &str) -> Result<ScriptInstance, ParseError> {
+        Ok(ScriptInstance {
+            ast: self.engine.compile(source)?,
+            this: Dynamic::from_map(Map::new()),
+            started: false,
+        })
+    }
+
+    fn update(&self, instance: &mut ScriptInstance, entity: Entity, delta: f32) -> ScriptResult<()> {
+        if !instance.started {
+            instance.started = true;
+
+            // Statements outside of the functions
+            self.engine.run_ast_with_scope(&mut Scope::new(), &instance.ast)?;
+            self.call(instance, "on_start", (entity, ))?;
+        }
+
+        self.call(instance, "on_update", (entity, delta as FLOAT))
+    }
+
+    /// Calls the hook if the script defines it.
+    fn call(&self, instance: &mut ScriptInstance, hook: &str, args: impl FuncArgs) -> ScriptResult<()> {
+        if !instance.ast.iter_functions().any(|function| function.name == hook) {
+            return Ok(());
+        }
+
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut instance.this);
+
+        // Whatever the hook returns is ignored
+        self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &instance.ast, hook, args).map(drop)
+    }
+}
+
+fn parse_error(path: &Path, err: ParseError) -> Error {
+    Error::Script {
+        path: path.to_owned(),
+        line: err.1.line(),
+        message: err.0.to_string(),
+    }
+}
+
+fn eval_error(path: &Path, mut err: EvalAltResult) -> Error {
+    // Errors raised inside a function are wrapped in its call, the innermost one is where things went wrong
+    while let EvalAltResult::ErrorInFunctionCall(_, _, inner, _) = err {
+        err = *inner;
+    }
+
+    let position = err.take_position();
+
+    Error::Script {
+        path: path.to_owned(),
+        line: position.line(),
+        message: err.to_string(),
+    }
+}
+
+fn missing(entity: Entity, component: &'static str) -> Box<EvalAltResult> {
+    Error::MissingComponent { entity, component }.to_string().into()
+}
+
+/// Any number, scripts shouldn't have to write `1.0` instead of `1`.
+fn number(value: Dynamic) -> ScriptResult<f32> {
+    match value.as_int() {
+        Ok(int) => Ok(int as f32),
+        Err(_) => value.as_float().map(|float| float as f32).map_err(|type_name| format!("expected a number, got {}", type_name).into()),
+    }
+}
+
+fn register_math(engine: &mut Engine) {
+    engine.register_type_with_name::<Vec3>("Vec3")
+        .register_fn("vec3", |x: Dynamic, y: Dynamic, z: Dynamic| -> ScriptResult<Vec3> {
+            Ok(Vec3::new(number(x)?, number(y)?, number(z)?))
+        })
+        .register_get_set("x", |v: &mut Vec3| v.x as FLOAT, |v: &mut Vec3, x: FLOAT| v.x = x as f32)
+        .register_get_set("y", |v: &mut Vec3| v.y as FLOAT, |v: &mut Vec3, y: FLOAT| v.y = y as f32)
+        .register_get_set("z", |v: &mut Vec3| v.z as FLOAT, |v: &mut Vec3, z: FLOAT| v.z = z as f32)
+        .register_fn("+", |a: Vec3, b: Vec3| a + b)
+        .register_fn("-", |a: Vec3, b: Vec3| a - b)
+        .register_fn("-", |v: Vec3| -v)
+        .register_fn("*", |v: Vec3, s: FLOAT| v * s as f32)
+        .register_fn("*", |s: FLOAT, v: Vec3| v * s as f32)
+        .register_fn("/", |v: Vec3, s: FLOAT| v / s as f32)
+        .register_fn("==", |a: Vec3, b: Vec3| a == b)
+        .register_fn("length", |v: &mut Vec3| v.length() as FLOAT)
+        .register_fn("normalize", |v: &mut Vec3| v.normalize_or_zero())
+        .register_fn("dot", |a: &mut Vec3, b: Vec3| a.dot(b) as FLOAT)
+        .register_fn("cross", |a: &mut Vec3, b: Vec3| a.cross(b))
+        .register_fn("to_string", |v: &mut Vec3| format!("{}", v))
+        .register_fn("to_debug", |v: &mut Vec3| format!("{}", v));
+
+    engine.register_type_with_name::<Vec2>("Vec2")
+        .register_get("x", |v: &mut Vec2| v.x as FLOAT)
+        .register_get("y", |v: &mut Vec2| v.y as FLOAT)
+        .register_fn("to_string", |v: &mut Vec2| format!("{}", v))
+        .register_fn("to_debug", |v: &mut Vec2| format!("{}", v));
+}
+
+fn transform(scene: &Scene, entity: Entity) -> ScriptResult<(Vec3, Quat, Vec3)> {
+    let transform = scene.get_one::<TransformComponent>(entity).ok_or_else(|| missing(entity, "TransformComponent"))?;
+    Ok(transform.0.to_scale_rotation_translation())
+}
+
+/// Lets `edit` change the scale, rotation and translation of the entity's transform.
+fn edit_transform<F: FnOnce(&mut Vec3, &mut Quat, &mut Vec3)>(scene: &mut Scene, entity: Entity, edit: F) -> ScriptResult<()> {
+    let mut transform = scene.get_one_mut::<TransformComponent>(entity).ok_or_else(|| missing(entity, "TransformComponent"))?;
+
+    let (mut scale, mut rotation, mut translation) = transform.0.to_scale_rotation_translation();
+    edit(&mut scale, &mut rotation, &mut translation);
+
+    transform.0 = Mat4::from_scale_rotation_translation(scale, rotation, translation);
+
+    Ok(())
+}
+
+fn register_entity(engine: &mut Engine, context: &Rc<RefCell<Context>>) {
+    engine.register_type_with_name::<Entity>("Entity")
+        .register_fn("==", |a: Entity, b: Entity| a == b)
+        .register_fn("to_string", |entity: &mut Entity| format!("{:?}", entity))
+        .register_fn("to_debug", |entity: &mut Entity| format!("{:?}", entity));
+
+    let ctx = context.clone();
+    engine.register_get("name", move |entity: &mut Entity| -> ScriptResult<String> {
+        let context = ctx.borrow();
+        let name = context.scene.get_one::<NameComponent>(*entity).ok_or_else(|| missing(*entity, "NameComponent"))?;
+        Ok(name.0.clone())
+    });
+
+    let ctx = context.clone();
+    engine.register_set("name", move |entity: &mut Entity, name: String| {
+        let mut context = ctx.borrow_mut();
+
+        if let Some(mut name_comp) = context.scene.get_one_mut::<NameComponent>(*entity) {
+            name_comp.0 = name;
+            return;
+        }
+
+        context.scene.attach(*entity, NameComponent(name));
+    });
+
+    let ctx = context.clone();
+    engine.register_get("position", move |entity: &mut Entity| -> ScriptResult<Vec3> {
+        Ok(transform(&ctx.borrow().scene, *entity)?.2)
+    });
+
+    let ctx = context.clone();
+    engine.register_set("position", move |entity: &mut Entity, position: Vec3| {
+        edit_transform(&mut ctx.borrow_mut().scene, *entity, |_, _, translation| *translation = position)
+    });
+
+    let ctx = context.clone();
+    engine.register_get("rotation", move |entity: &mut Entity| -> ScriptResult<Vec3> {
+        let (x, y, z) = transform(&ctx.borrow().scene, *entity)?.1.to_euler(EulerRot::XYZ);
+        Ok(Vec3::new(x.to_degrees(), y.to_degrees(), z.to_degrees()))
+    });
+
+    let ctx = context.clone();
+    engine.register_set("rotation", move |entity: &mut Entity, euler: Vec3| {
+        edit_transform(&mut ctx.borrow_mut().scene, *entity, |_, rotation, _| {
+            *rotation = Quat::from_euler(EulerRot::XYZ, euler.x.to_radians(), euler.y.to_radians(), euler.z.to_radians());
+        })
+    });
+
+    let ctx = context.clone();
+    engine.register_get("scale", move |entity: &mut Entity| -> ScriptResult<Vec3> {
+        Ok(transform(&ctx.borrow().scene, *entity)?.0)
+    });
+
+    let ctx = context.clone();
+    engine.register_set("scale", move |entity: &mut Entity, new_scale: Vec3| {
+        edit_transform(&mut ctx.borrow_mut().scene, *entity, |scale, _, _| *scale = new_scale)
+    });
+
+    let ctx = context.clone();
+    engine.register_get("parent", move |entity: &mut Entity| -> ScriptResult<Dynamic> {
+        let context = ctx.borrow();
+        let hierarchy = context.scene.get_one::<HierarchyComponent>(*entity).ok_or_else(|| missing(*entity, "HierarchyComponent"))?;
+        Ok(hierarchy.parent.map_or(Dynamic::UNIT, Dynamic::from))
+    });
+
+    let ctx = context.clone();
+    engine.register_get("children", move |entity: &mut Entity| -> ScriptResult<Array> {
+        let context = ctx.borrow();
+        let hierarchy = context.scene.get_one::<HierarchyComponent>(*entity).ok_or_else(|| missing(*entity, "HierarchyComponent"))?;
+        Ok(hierarchy.children.iter().copied().map(Dynamic::from).collect())
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("find", move |name: &str| -> Dynamic {
+        <(NameComponent, )>::query_shallow(&ctx.borrow().scene)
+            .find(|(_, (name_comp, ), _)| name_comp.0 == name)
+            .map_or(Dynamic::UNIT, |(entity, _, _)| Dynamic::from(entity))
+    });
+}
+
+fn mesh_comp(scene: &Scene, entity: Entity) -> ScriptResult<impl Deref<Target=MeshComponent> + '_> {
+    scene.get_one::<MeshComponent>(entity).ok_or_else(|| missing(entity, "MeshComponent"))
+}
+
+fn mesh_comp_mut(scene: &mut Scene, entity: Entity) -> ScriptResult<impl DerefMut<Target=MeshComponent> + '_> {
+    scene.get_one_mut::<MeshComponent>(entity).ok_or_else(|| missing(entity, "MeshComponent"))
+}
+
+fn register_components(engine: &mut Engine, context: &Rc<RefCell<Context>>) {
+    let ctx = context.clone();
+    engine.register_get("mesh", move |entity: &mut Entity| -> ScriptResult<String> {
+        let context = ctx.borrow();
+        let mesh_comp = mesh_comp(&context.scene, *entity)?;
+        Ok(context.db.resolve(&mesh_comp.mesh).to_string_lossy().into_owned())
+    });
+
+    let ctx = context.clone();
+    engine.register_set("mesh", move |entity: &mut Entity, mesh: &str| -> ScriptResult<()> {
+        let mut context = ctx.borrow_mut();
+        let mesh = context.db.reference(mesh);
+        let mut mesh_comp = mesh_comp_mut(&mut context.scene, *entity)?;
+
+        // Loaded again before it's drawn
+        mesh_comp.mesh = mesh;
+        mesh_comp.vao = None;
+
+        Ok(())
+    });
+
+    let ctx = context.clone();
+    engine.register_get("material", move |entity: &mut Entity| -> ScriptResult<String> {
+        let context = ctx.borrow();
+        let mesh_comp = mesh_comp(&context.scene, *entity)?;
+        Ok(context.db.resolve(&mesh_comp.mat).to_string_lossy().into_owned())
+    });
+
+    let ctx = context.clone();
+    engine.register_set("material", move |entity: &mut Entity, mat: &str| -> ScriptResult<()> {
+        let mut context = ctx.borrow_mut();
+        let mat = context.db.reference(mat);
+        let mut mesh_comp = mesh_comp_mut(&mut context.scene, *entity)?;
+
+        mesh_comp.mat = mat;
+        mesh_comp.material = None;
+        mesh_comp.tex = None;
+
+        Ok(())
+    });
+
+    register_component::<CameraComponent>(engine, context, "camera", "CameraComponent");
+    register_component::<LightComponent>(engine, context, "light", "LightComponent");
+
+    // Setters take any number, like `vec3`, so they're registered apart from their getters
+    engine.register_type_with_name::<CameraComponent>("Camera")
+        .register_get("fov", |camera: &mut CameraComponent| -> ScriptResult<FLOAT> { Ok(*fov(camera)? as FLOAT) })
+        .register_set("fov", |camera: &mut CameraComponent, new_fov: Dynamic| -> ScriptResult<()> {
+            *fov(camera)? = number(new_fov)?;
+            Ok(())
+        })
+        .register_get("near", |camera: &mut CameraComponent| *planes(camera).0 as FLOAT)
+        .register_set("near", |camera: &mut CameraComponent, near: Dynamic| -> ScriptResult<()> {
+            *planes(camera).0 = number(near)?;
+            Ok(())
+        })
+        .register_get("far", |camera: &mut CameraComponent| *planes(camera).1 as FLOAT)
+        .register_set("far", |camera: &mut CameraComponent, far: Dynamic| -> ScriptResult<()> {
+            *planes(camera).1 = number(far)?;
+            Ok(())
+        })
+        .register_get("order", |camera: &mut CameraComponent| camera.order as INT)
+        .register_set("order", |camera: &mut CameraComponent, order: INT| camera.order = order as i32)
+        .register_get("active", |camera: &mut CameraComponent| camera.active)
+        .register_set("active", |camera: &mut CameraComponent, active: bool| camera.active = active);
+
+    engine.register_type_with_name::<LightComponent>("Light")
+        .register_get("color", |light: &mut LightComponent| Vec3::from(light.color))
+        .register_set("color", |light: &mut LightComponent, color: Vec3| light.color = color.to_array())
+        .register_get("intensity", |light: &mut LightComponent| light.intensity as FLOAT)
+        .register_set("intensity", |light: &mut LightComponent, intensity: Dynamic| -> ScriptResult<()> {
+            light.intensity = number(intensity)?;
+            Ok(())
+        })
+        .register_get("range", |light: &mut LightComponent| {
+            light.range.map_or(Dynamic::UNIT, |range| Dynamic::from(range as FLOAT))
+        })
+        .register_set("range", |light: &mut LightComponent, range: Dynamic| -> ScriptResult<()> {
+            light.range = if range.is_unit() { None } else { Some(number(range)?) };
+            Ok(())
+        });
+}
+
+fn fov(camera: &mut CameraComponent) -> ScriptResult<&mut f32> {
+    match &mut camera.projection {
+        Projection::Perspective { fov, .. } => Ok(fov),
+        Projection::Orthographic { .. } => Err("an orthographic camera has no field of view".into()),
+    }
+}
+
+/// The near and far planes of the camera, whatever its projection.
+fn planes(camera: &mut CameraComponent) -> (&mut f32, &mut f32) {
+    match &mut camera.projection {
+        Projection::Perspective { near, far, .. } | Projection::Orthographic { near, far, .. } => (near, far),
+    }
+}
+
+/// Makes the component a property of the entities, read as a copy and written back as a whole.
+fn register_component<C: Component + Clone>(
+    engine: &mut Engine,
+    context: &Rc<RefCell<Context>>,
+    property: &str,
+    component: &'static str,
+) {
+    let ctx = context.clone();
+    engine.register_get(property, move |entity: &mut Entity| -> ScriptResult<C> {
+        let context = ctx.borrow();
+        let comp = context.scene.get_one::<C>(*entity).ok_or_else(|| missing(*entity, component))?;
+        Ok(comp.clone())
+    });
+
+    let ctx = context.clone();
+    engine.register_set(property, move |entity: &mut Entity, comp: C| {
+        let mut context = ctx.borrow_mut();
+
+        if let Some(mut current) = context.scene.get_one_mut::<C>(*entity) {
+            *current = comp;
+            return;
+        }
+
+        context.scene.attach(*entity, comp);
+    });
+}
+
+fn register_input(engine: &mut Engine, context: &Rc<RefCell<Context>>) {
+    let ctx = context.clone();
+    engine.register_fn("is_held", move |action: &str| ctx.borrow().input.is_action_held(action));
+
+    let ctx = context.clone();
+    engine.register_fn("was_pressed", move |action: &str| ctx.borrow().input.was_action_pressed(action));
+
+    let ctx = context.clone();
+    engine.register_fn("was_released", move |action: &str| ctx.borrow().input.was_action_released(action));
+
+    let ctx = context.clone();
+    engine.register_fn("is_key_held", move |key: &str| -> ScriptResult<bool> {
+        // Keys are saved by name in the action map, so they're parsed the same way
+        let key: Key = serde_json::from_value(serde_json::Value::String(key.to_owned()))
+            .map_err(|_| format!("there's no key named {:?}", key))?;
+
+        Ok(ctx.borrow().input.is_held(Button::Key(key)))
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("mouse_delta", move || ctx.borrow().input.mouse_delta());
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::rc::Rc;
+
+    use glam::{Mat4, Vec3};
+
+    use crate::component::{
+        CameraComponent, LightComponent, LightKind, MeshComponent, NameComponent, Projection, ScriptComponent,
+        TransformComponent,
+    };
+    use crate::database::AssetRef;
+    use crate::database::AssetDatabase;
+    use crate::ecs::Entity;
+    use crate::error::Error;
+    use crate::input::Input;
+    use crate::resource::{Scene, Script};
+
+    use super::ScriptHost;
+
+    fn scene_with_script(source: &str) -> (Scene, Entity) {
+        let mut scene = Scene::default();
+        let entity = scene.create();
+
+        let mut script_comp = ScriptComponent::new("$/test.rhai");
+        script_comp.loaded = Some(Rc::new(Script { source: source.to_owned() }));
+
+        scene.attach(entity, TransformComponent(Mat4::IDENTITY));
+        scene.attach(entity, script_comp);
+
+        (scene, entity)
+    }
+
+    fn run(host: &ScriptHost, scene: &mut Scene, delta: f32) -> Vec<Error> {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut errors = Vec::new();
+        host.run(scene, &mut Input::default(), &mut AssetDatabase::open(dir.path()).unwrap(), delta, &mut errors);
+
+        errors
+    }
+
+    #[test]
+    fn moves_entities_and_keeps_state() {
+        let host = ScriptHost::new();
+
+        let (mut scene, entity) = scene_with_script(r#"
+            fn on_start(entity) {
+                this.speed = 2;
+                let target = find("Target");
+                target.name = "Moved";
+            }
+
+            fn on_update(entity, delta) {
+                entity.position += vec3(this.speed * delta, 0, 0);
+            }
+        "#);
+
+        let target = scene.create();
+        scene.attach(target, NameComponent("Target".to_owned()));
+
+        for _ in 0..2 {
+            assert!(run(&host, &mut scene, 0.5).is_empty());
+        }
+
+        let translation = scene.get_one::<TransformComponent>(entity).unwrap().0.w_axis.truncate();
+        assert!((translation - Vec3::new(2.0, 0.0, 0.0)).length() < 1e-5);
+
+        assert_eq!(scene.get_one::<NameComponent>(target).unwrap().0, "Moved");
+    }
+
+    #[test]
+    fn edits_meshes_cameras_and_lights() {
+        let host = ScriptHost::new();
+
+        let (mut scene, entity) = scene_with_script(r#"
+            fn on_start(entity) {
+                entity.name = entity.material;
+                entity.mesh = "$/sphere.mesh";
+
+                entity.camera.fov = 60;
+                entity.camera.order += 1;
+
+                let light = entity.light;
+                light.intensity *= 2;
+                light.range = ();
+                entity.light = light;
+            }
+        "#);
+
+        scene.attach(entity, MeshComponent::new(AssetRef::from("$/cube.mesh"), AssetRef::from("$/red.mat")));
+        scene.attach(entity, CameraComponent::default());
+        scene.attach(entity, LightComponent {
+            kind: LightKind::Point,
+            color: [1.0; 3],
+            intensity: 1.5,
+            range: Some(10.0),
+        });
+
+        assert!(run(&host, &mut scene, 0.1).is_empty());
+
+        assert_eq!(scene.get_one::<NameComponent>(entity).unwrap().0, "$/red.mat");
+        assert_eq!(scene.get_one::<MeshComponent>(entity).unwrap().mesh.path, Path::new("$/sphere.mesh"));
+
+        let camera = scene.get_one::<CameraComponent>(entity).unwrap();
+        assert!(matches!(camera.projection, Projection::Perspective { fov, .. } if fov == 60.0));
+        assert_eq!(camera.order, 1);
+
+        let light = scene.get_one::<LightComponent>(entity).unwrap();
+        assert_eq!(light.intensity, 3.0);
+        assert_eq!(light.range, None);
+    }
+
+    #[test]
+    fn reports_errors_with_their_line_once() {
+        let host = ScriptHost::new();
+
+        let (mut scene, _) = scene_with_script("fn on_update(entity, delta) {\n    let name = entity.name;\n}\n");
+
+        let errors = run(&host, &mut scene, 0.1);
+        assert_eq!(errors.len(), 1);
+
+        match &errors[0] {
+            Error::Script { path, line, .. } => {
+                assert_eq!(path, Path::new("$/test.rhai"));
+                assert_eq!(*line, Some(2));
+            }
+            err => panic!("unexpected error {}", err),
+        }
+
+        // Stopped until its file changes
+        assert!(run(&host, &mut scene, 0.1).is_empty());
+
+        let (mut scene, _) = scene_with_script("fn on_update(entity, delta) {\n    let = 1;\n}\n");
+
+        let errors = run(&host, &mut scene, 0.1);
+        assert!(matches!(errors.as_slice(), [Error::Script { line: Some(2), .. }]));
+    }
+}
@@ -2,9 +2,9 @@ use std::ffi::CString;
 use std::ptr;
 
 use gl;
-use glam::Mat4;
+use glam::{Mat4, Vec2, Vec4};
 
-use std::error::Error;
+use crate::error::{Error, Result};
 
 pub struct Shader {
     id: u32,
@@ -27,7 +27,7 @@ impl Shader {
         self
     }
 
-    pub fn build(self) -> Result<Shader, Box<dyn Error>> {
+    pub fn build(self) -> Result<Shader> {
         use gl::types::{GLint, GLchar};
 
         unsafe {
@@ -50,7 +50,7 @@ impl Shader {
                     buf.as_mut_ptr() as *mut GLchar,
                 );
 
-                return Err(Box::from(std::str::from_utf8(&buf)?));
+                return Err(Error::Shader { name: None, log: String::from_utf8_lossy(&buf).into_owned() });
             }
         }
 
@@ -77,12 +77,48 @@ impl Shader {
         }
     }
 
+    pub fn set_int<T: AsRef<str>>(&mut self, name: T, val: i32) {
+        unsafe {
+            let loc = self.get_loc(name.as_ref());
+            gl::Uniform1i(loc, val);
+        }
+    }
+
+    pub fn set_vec2<T: AsRef<str>>(&mut self, name: T, val: Vec2) {
+        unsafe {
+            let loc = self.get_loc(name.as_ref());
+            gl::Uniform2f(loc, val.x, val.y);
+        }
+    }
+
+    pub fn set_vec4<T: AsRef<str>>(&mut self, name: T, val: Vec4) {
+        unsafe {
+            let loc = self.get_loc(name.as_ref());
+            gl::Uniform4f(loc, val.x, val.y, val.z, val.w);
+        }
+    }
+
+    pub fn set_float<T: AsRef<str>>(&mut self, name: T, val: f32) {
+        unsafe {
+            let loc = self.get_loc(name.as_ref());
+            gl::Uniform1f(loc, val);
+        }
+    }
+
     pub fn set_mat4<T: AsRef<str>>(&mut self, name: T, val: &Mat4) {
         unsafe {
             let loc = self.get_loc(name.as_ref());
             gl::UniformMatrix4fv(loc, 1, gl::FALSE, val.as_ref() as _);
         }
     }
+
+    /// Sets the first elements of an array of matrices, as many as given.
+    pub fn set_mat4_array<T: AsRef<str>>(&mut self, name: T, vals: &[Mat4]) {
+        unsafe {
+            let loc = self.get_loc(name.as_ref());
+            gl::UniformMatrix4fv(loc, vals.len() as _, gl::FALSE, vals.as_ptr() as _);
+        }
+    }
 }
 
 impl Drop for Shader {
@@ -112,7 +148,7 @@ pub struct ShaderComponent {
 }
 
 impl ShaderComponent {
-    pub fn new<P: AsRef<str>>(source: P, t: ShaderComponentType) -> Result<ShaderComponent, Box<dyn Error>> {
+    pub fn new<P: AsRef<str>>(source: P, t: ShaderComponentType) -> Result<ShaderComponent> {
         use gl::types::{GLint, GLchar};
 
         // Convert to C string
@@ -143,7 +179,7 @@ impl ShaderComponent {
                     buf.as_mut_ptr() as *mut GLchar,
                 );
 
-                return Err(Box::from(std::str::from_utf8(&buf)?));
+                return Err(Error::Shader { name: None, log: String::from_utf8_lossy(&buf).into_owned() });
             }
 
             id
@@ -1,7 +1,7 @@
-use std::error::Error;
 use std::mem;
 use std::ptr;
 
+use crate::error::Result;
 use crate::resource::Texture;
 use crate::shader::{Shader, ShaderComponent, ShaderComponentType};
 use crate::CameraMats;
@@ -68,7 +68,7 @@ pub(crate) struct Skybox {
 }
 
 impl Skybox {
-    pub(crate) fn load() -> Result<Skybox, Box<dyn Error>> {
+    pub(crate) fn load() -> Result<Skybox> {
         let mut vao_id: u32 = 0;
 
         // Setup VAO
@@ -116,12 +116,12 @@ impl Skybox {
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
 
-        let right = bincode::deserialize::<Texture>(SKY_RIGHT)?;
-        let left = bincode::deserialize::<Texture>(SKY_LEFT)?;
-        let top = bincode::deserialize::<Texture>(SKY_TOP)?;
-        let bottom = bincode::deserialize::<Texture>(SKY_BOTTOM)?;
-        let front = bincode::deserialize::<Texture>(SKY_FRONT)?;
-        let back = bincode::deserialize::<Texture>(SKY_BACK)?;
+        let right = Texture::from_bytes(SKY_RIGHT)?;
+        let left = Texture::from_bytes(SKY_LEFT)?;
+        let top = Texture::from_bytes(SKY_TOP)?;
+        let bottom = Texture::from_bytes(SKY_BOTTOM)?;
+        let front = Texture::from_bytes(SKY_FRONT)?;
+        let back = Texture::from_bytes(SKY_BACK)?;
 
         let mut texture_id = 0;
 
@@ -198,7 +198,7 @@ impl Drop for Skybox {
     }
 }
 
-fn get_skybox_shader() -> Result<Shader, Box<dyn Error>> {
+fn get_skybox_shader() -> Result<Shader> {
     Shader::new()
         .with_component(ShaderComponent::new(SKYBOX_VERT_SHADER, ShaderComponentType::VERTEX)?)
         .with_component(ShaderComponent::new(SKYBOX_FRAG_SHADER, ShaderComponentType::FRAGMENT)?)
@@ -1,26 +1,53 @@
+use crate::error::Result;
 use crate::shader::{Shader, ShaderComponent, ShaderComponentType};
-use std::error::Error;
 
 const STANDARD_VERT_SHADER: &'static str = r"
 #version 330 core
 layout (location = 0) in vec3 pos_in;
 layout (location = 1) in vec3 normal_in;
 layout (location = 2) in vec2 uv_in;
+// Per instance, takes up locations 3 to 6
+layout (location = 3) in mat4 model;
+// Only for skinned meshes
+layout (location = 7) in vec4 joints_in;
+layout (location = 8) in vec4 weights_in;
+// White for meshes without vertex colours
+layout (location = 11) in vec4 color_in;
+
+// Skeleton::MAX_JOINTS
+#define MAX_JOINTS 60
 
-uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
 
+uniform bool skinned;
+// From the space of the mesh to the one of the model matrix, through each joint as posed
+uniform mat4 joints[MAX_JOINTS];
+
 out vec3 frag_pos;
 out vec3 normal;
 out vec2 uv;
+out vec4 vertex_color;
 
 void main() {
-    gl_Position = projection * view * model * vec4(pos_in, 1.0);
+    mat4 skin = mat4(1.0);
+
+    // Vertices of skinned meshes that aren't bound to any joint, with all their weights at 0, stay where they are
+    if (skinned && dot(weights_in, vec4(1.0)) > 0.0) {
+        skin = weights_in.x * joints[int(joints_in.x)]
+             + weights_in.y * joints[int(joints_in.y)]
+             + weights_in.z * joints[int(joints_in.z)]
+             + weights_in.w * joints[int(joints_in.w)];
+    }
 
-    frag_pos = vec3(model * vec4(pos_in, 1.0));
-    normal = transpose(inverse(mat3(model))) * normal_in;
+    mat4 skinned_model = model * skin;
+
+    gl_Position = projection * view * skinned_model * vec4(pos_in, 1.0);
+
+    frag_pos = vec3(skinned_model * vec4(pos_in, 1.0));
+    normal = transpose(inverse(mat3(skinned_model))) * normal_in;
     uv = uv_in;
+    vertex_color = color_in;
 }
 ";
 
@@ -30,22 +57,41 @@ const STANDARD_FRAG_SHADER: &'static str = r"
 in vec3 frag_pos;
 in vec3 normal;
 in vec2 uv;
+in vec4 vertex_color;
 
 out vec4 color;
 
 uniform bool useSampler;
 uniform sampler2D sampler;
 
+// Multiplies the colour of the texture, or is used instead of it when there's none
+uniform vec4 baseColor;
+
+// Fragments less opaque than this are discarded, 0 disables the test
+uniform float alphaCutoff;
+// Whether to ignore the alpha of the texture altogether
+uniform bool opaque;
+
 void main() {
     if (useSampler) {
-        color = texture(sampler, uv);
+        color = texture(sampler, uv) * baseColor;
     } else {
-        color = vec4(0.4, 0.4, 0.4, 1.0);
+        color = baseColor;
+    }
+
+    color *= vertex_color;
+
+    if (color.a < alphaCutoff) {
+        discard;
+    }
+
+    if (opaque) {
+        color.a = 1.0;
     }
 }
 ";
 
-pub fn get_standard_shader() -> Result<Shader, Box<dyn Error>> {
+pub fn get_standard_shader() -> Result<Shader> {
     Shader::new()
         .with_component(ShaderComponent::new(STANDARD_VERT_SHADER, ShaderComponentType::VERTEX)?)
         .with_component(ShaderComponent::new(STANDARD_FRAG_SHADER, ShaderComponentType::FRAGMENT)?)
@@ -1,37 +1,115 @@
-use crate::resource::Texture;
+use crate::resource::{ColorSpace, Filter, Texture, TextureFormat, Wrap};
 use crate::shader::Shader;
 
+// From EXT_texture_compression_s3tc and EXT_texture_sRGB, which aren't part of core OpenGL
+const COMPRESSED_RGB_S3TC_DXT1_EXT: u32 = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: u32 = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1_EXT: u32 = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT: u32 = 0x8C4F;
+
 impl Texture {
+    /// Uploads the texture to the GPU with its sampler settings. Unless the texture is readable, its pixels are
+    /// dropped afterwards.
     pub fn load_gl(&mut self) {
         let mut id: u32 = 0;
 
+        let srgb = self.color_space == ColorSpace::Srgb;
+        let internal_format = match (self.format, srgb) {
+            (TextureFormat::Rgba8, false) => gl::RGBA8,
+            (TextureFormat::Rgba8, true) => gl::SRGB8_ALPHA8,
+            (TextureFormat::Bc1, false) => COMPRESSED_RGB_S3TC_DXT1_EXT,
+            (TextureFormat::Bc1, true) => COMPRESSED_SRGB_S3TC_DXT1_EXT,
+            (TextureFormat::Bc3, false) => COMPRESSED_RGBA_S3TC_DXT5_EXT,
+            (TextureFormat::Bc3, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+            (TextureFormat::Etc2Rgb8, false) => gl::COMPRESSED_RGB8_ETC2,
+            (TextureFormat::Etc2Rgb8, true) => gl::COMPRESSED_SRGB8_ETC2,
+            (TextureFormat::Etc2Rgba8, false) => gl::COMPRESSED_RGBA8_ETC2_EAC,
+            (TextureFormat::Etc2Rgba8, true) => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+        };
+
+        // Compressed textures can't have their mipmaps generated by the driver
+        let generate_mips = self.sampler.mipmaps && self.mips.is_empty() && !self.format.is_compressed();
+        let mipmaps = self.sampler.mipmaps && (generate_mips || !self.mips.is_empty());
+
         unsafe {
             gl::GenTextures(1, &mut id);
             gl::BindTexture(gl::TEXTURE_2D, id);
 
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as _);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as _);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+            let wrap = match self.sampler.wrap {
+                Wrap::Repeat => gl::REPEAT,
+                Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+                Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            };
+
+            let min_filter = match (self.sampler.min_filter, mipmaps) {
+                (Filter::Nearest, false) => gl::NEAREST,
+                (Filter::Linear, false) => gl::LINEAR,
+                (Filter::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST,
+                (Filter::Linear, true) => gl::LINEAR_MIPMAP_LINEAR,
+            };
+
+            let mag_filter = match self.sampler.mag_filter {
+                Filter::Nearest => gl::NEAREST,
+                Filter::Linear => gl::LINEAR,
+            };
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag_filter as _);
+
+            // Rows of tightly packed RGBA pixels are always 4 byte aligned, compressed ones don't care
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
 
-            let [width, height] = self.size;
+            let mut size = self.size;
 
-            gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as _, width as _, height as _, 0, gl::RGBA, gl::UNSIGNED_BYTE, self.raw.as_ptr() as _);
-            gl::GenerateMipmap(gl::TEXTURE_2D);
+            for (level, data) in std::iter::once(&self.raw).chain(self.mips.iter()).enumerate() {
+                let [width, height] = size;
+
+                if self.format.is_compressed() {
+                    gl::CompressedTexImage2D(gl::TEXTURE_2D, level as _, internal_format, width as _, height as _, 0,
+                                             data.len() as _, data.as_ptr() as _);
+                } else {
+                    gl::TexImage2D(gl::TEXTURE_2D, level as _, internal_format as _, width as _, height as _, 0,
+                                   gl::RGBA, gl::UNSIGNED_BYTE, data.as_ptr() as _);
+                }
+
+                self.gpu_bytes += self.format.level_bytes(size);
+                size = [(width / 2).max(1), (height / 2).max(1)];
+            }
+
+            if generate_mips {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+                self.gpu_bytes = mip_chain_bytes(self.format, self.size);
+            } else {
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, self.mips.len() as _);
+            }
 
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
 
         self.id = Some(id);
 
-        // Save some memory, it's already loaded in the GPU
-        self.raw.clear();
+        if !self.readable {
+            // Save some memory, it's already loaded in the GPU
+            self.raw = Vec::new();
+            self.mips = Vec::new();
+        }
+    }
+
+    /// Estimated GPU memory taken up by the texture once loaded.
+    pub(crate) fn gpu_bytes(&self) -> usize {
+        self.gpu_bytes
     }
 
     pub fn use_tex(self_: Option<&Self>, shader: &mut Shader) {
-        if let Some(tex) = self_ {
+        Texture::use_tex_id(self_.map(|tex| tex.id.expect("texture not loaded")), shader);
+    }
+
+    pub(crate) fn use_tex_id(id: Option<u32>, shader: &mut Shader) {
+        if let Some(id) = id {
             unsafe {
-                gl::BindTexture(gl::TEXTURE_2D, tex.id.expect("texture not loaded"));
+                gl::BindTexture(gl::TEXTURE_2D, id);
             }
 
             shader.set_bool("useSampler", true);
@@ -54,3 +132,22 @@ impl Drop for Texture {
         }
     }
 }
+
+/// Bytes taken up by an image of the given size along with all its mipmaps, down to 1x1.
+pub(crate) fn mip_chain_bytes(format: TextureFormat, size: [u32; 2]) -> usize {
+    let [mut width, mut height] = size;
+    let mut bytes = 0;
+
+    loop {
+        bytes += format.level_bytes([width, height]);
+
+        if width == 1 && height == 1 {
+            break;
+        }
+
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+    }
+
+    bytes
+}
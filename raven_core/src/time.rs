@@ -21,6 +21,14 @@ pub struct Stats {
     time_per_frame: Duration,
 }
 
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CullingStats {
+    /// Meshes that were at least partially inside a camera's frustum
+    pub drawn: u32,
+    /// Meshes that were skipped because they were entirely outside of a camera's frustum
+    pub culled: u32,
+}
+
 impl FpsCounter {
     pub fn on_frame(&mut self) -> Option<Stats> {
         self.frames += 1;
@@ -1,22 +1,118 @@
 use std::mem;
 
 use gl;
+use glam::Mat4;
+
+use crate::error::Result;
+use crate::resource::{Aabb, Mesh, Vertex, VertexLayout};
+
+const POSITION_ATTR_LOCATION: u32 = 0;
+const NORMAL_ATTR_LOCATION: u32 = 1;
+const UV_ATTR_LOCATION: u32 = 2;
+
+// First of the four locations taken up by the per-instance model matrix, one per column
+const MODEL_ATTR_LOCATION: u32 = 3;
+
+const JOINTS_ATTR_LOCATION: u32 = 7;
+const WEIGHTS_ATTR_LOCATION: u32 = 8;
+const TANGENT_ATTR_LOCATION: u32 = 9;
+const BITANGENT_ATTR_LOCATION: u32 = 10;
+const COLOR_ATTR_LOCATION: u32 = 11;
+const UV2_ATTR_LOCATION: u32 = 12;
+
+/// A per-vertex attribute, interleaved with the others in the vertex buffer.
+struct Attribute {
+    location: u32,
+    floats: usize,
+    write: fn(&Vertex, &mut Vec<f32>),
+}
+
+/// The attributes the vertices of a mesh with the given layout have, in the order they're laid out in.
+fn attributes(layout: &VertexLayout) -> Vec<Attribute> {
+    let mut attributes = vec![
+        Attribute {
+            location: POSITION_ATTR_LOCATION,
+            floats: 3,
+            write: |vert, buf| buf.extend(vert.position.to_array()),
+        },
+        Attribute {
+            location: NORMAL_ATTR_LOCATION,
+            floats: 3,
+            write: |vert, buf| buf.extend(vert.normal.to_array()),
+        },
+    ];
+
+    if layout.uv {
+        attributes.push(Attribute {
+            location: UV_ATTR_LOCATION,
+            floats: 2,
+            write: |vert, buf| buf.extend(vert.uv.to_array()),
+        });
+    }
+
+    if layout.skin {
+        attributes.push(Attribute {
+            location: JOINTS_ATTR_LOCATION,
+            floats: 4,
+            // Exact as floats, joints are far fewer than 2^24
+            write: |vert, buf| buf.extend(vert.joints.iter().map(|&joint| joint as f32)),
+        });
+        attributes.push(Attribute {
+            location: WEIGHTS_ATTR_LOCATION,
+            floats: 4,
+            write: |vert, buf| buf.extend(vert.weights),
+        });
+    }
+
+    if layout.tangents {
+        attributes.push(Attribute {
+            location: TANGENT_ATTR_LOCATION,
+            floats: 3,
+            write: |vert, buf| buf.extend(vert.tangent.to_array()),
+        });
+        attributes.push(Attribute {
+            location: BITANGENT_ATTR_LOCATION,
+            floats: 3,
+            write: |vert, buf| buf.extend(vert.bitangent.to_array()),
+        });
+    }
+
+    if layout.colors {
+        attributes.push(Attribute {
+            location: COLOR_ATTR_LOCATION,
+            floats: 4,
+            write: |vert, buf| buf.extend(vert.color.to_array()),
+        });
+    }
 
-use crate::resource::Mesh;
-use std::error::Error;
+    if layout.uv2 {
+        attributes.push(Attribute {
+            location: UV2_ATTR_LOCATION,
+            floats: 2,
+            write: |vert, buf| buf.extend(vert.uv2.to_array()),
+        });
+    }
 
-// 3 for the position
-// 3 for the normal
-// 2 for the UV
-const FLOATS_PER_VERT: usize = 3 + 3 + 2;
+    attributes
+}
 
 #[derive(Debug)]
 pub(crate) struct Vao {
     vao_id: u32,
     vbo_id: u32,
     ebo_id: u32,
+    instance_vbo_id: u32,
 
     n_indices: usize,
+    // Size of the vertex and index buffers
+    gpu_bytes: usize,
+
+    // Model space bounding box of the mesh, for culling
+    pub(crate) aabb: Aabb,
+    // Whether the vertices have joints and weights
+    pub(crate) skinned: bool,
+    // Vertices without colours are drawn white
+    colors: bool,
 }
 
 impl Drop for Vao {
@@ -25,12 +121,13 @@ impl Drop for Vao {
             gl::DeleteVertexArrays(1, &self.vao_id);
             gl::DeleteBuffers(1, &self.vbo_id);
             gl::DeleteBuffers(1, &self.ebo_id);
+            gl::DeleteBuffers(1, &self.instance_vbo_id);
         }
     }
 }
 
 impl Vao {
-    pub(crate) fn from(mesh: &Mesh) -> Result<Vao, Box<dyn Error>> {
+    pub(crate) fn from(mesh: &Mesh) -> Result<Vao> {
         let mut vao_id: u32 = 0;
 
         // Setup VAO
@@ -47,54 +144,78 @@ impl Vao {
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
         }
 
+        let attributes = attributes(&mesh.layout);
+
         // Loads vertex data
-        let attr_vec = build_vert_attr_vec(mesh);
+        let attr_vec = build_vert_attr_vec(mesh, &attributes);
         unsafe {
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (FLOATS_PER_VERT * attr_vec.len() * mem::size_of::<f32>()) as _,
+                (attr_vec.len() * mem::size_of::<f32>()) as _,
                 attr_vec.as_ptr() as _,
                 gl::STATIC_DRAW,
             );
         }
 
         // How many bytes each vertex takes up
-        let stride = FLOATS_PER_VERT * mem::size_of::<f32>();
+        let floats_per_vert: usize = attributes.iter().map(|attribute| attribute.floats).sum();
+        let stride = floats_per_vert * mem::size_of::<f32>();
+
+        // Define and enable a vertex attribute pointer for each of the attributes of the layout:
+        //    0 => [f32; 3]: Position
+        //    1 => [f32; 3]: Normal
+        //    2 => [f32; 2]: UV coordinates
+        //    7 => [f32; 4]: Joint indices
+        //    8 => [f32; 4]: Joint weights
+        //    9 => [f32; 3]: Tangent
+        //   10 => [f32; 3]: Bitangent
+        //   11 => [f32; 4]: Colour
+        //   12 => [f32; 2]: Second UV coordinates
+        let mut offset = 0;
+
+        for attribute in &attributes {
+            unsafe {
+                gl::VertexAttribPointer(
+                    attribute.location,
+                    attribute.floats as _,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride as _,
+                    (offset * mem::size_of::<f32>()) as _,
+                );
+                gl::EnableVertexArrayAttrib(vao_id, attribute.location);
+            }
+
+            offset += attribute.floats;
+        }
+
+        let mut instance_vbo_id: u32 = 0;
 
-        // Define and enable the following vertex attribute pointers:
-        //   0 => [f32; 3]: Position
-        //   1 => [f32; 3]: Normal
-        //   2 => [f32; 2]: UV coordinates
+        // Setup instance VBO, filled with model matrices on every draw
         unsafe {
-            gl::VertexAttribPointer(
-                0,
-                3,
-                gl::FLOAT,
-                gl::FALSE,
-                stride as _,
-                (0 * mem::size_of::<f32>()) as _,
-            );
-            gl::EnableVertexArrayAttrib(vao_id, 0);
-
-            gl::VertexAttribPointer(
-                1,
-                3,
-                gl::FLOAT,
-                gl::FALSE,
-                stride as _,
-                (3 * mem::size_of::<f32>()) as _,
-            );
-            gl::EnableVertexArrayAttrib(vao_id, 1);
-
-            gl::VertexAttribPointer(
-                2,
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                stride as _,
-                (6 * mem::size_of::<f32>()) as _,
-            );
-            gl::EnableVertexArrayAttrib(vao_id, 2);
+            gl::GenBuffers(1, &mut instance_vbo_id);
+            gl::BindBuffer(gl::ARRAY_BUFFER, instance_vbo_id);
+        }
+
+        // A mat4 attribute takes up 4 locations, one per column:
+        //   3..=6 => [f32; 4]: Model matrix column, advanced once per instance
+        unsafe {
+            let stride = 16 * mem::size_of::<f32>();
+
+            for col in 0..4 {
+                let location = MODEL_ATTR_LOCATION + col;
+
+                gl::VertexAttribPointer(
+                    location,
+                    4,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride as _,
+                    (col as usize * 4 * mem::size_of::<f32>()) as _,
+                );
+                gl::EnableVertexArrayAttrib(vao_id, location);
+                gl::VertexAttribDivisor(location, 1);
+            }
         }
 
         let mut ebo_id: u32 = 0;
@@ -127,41 +248,93 @@ impl Vao {
             vao_id,
             vbo_id,
             ebo_id,
+            instance_vbo_id,
 
             n_indices: indices_vec.len(),
+            gpu_bytes: attr_vec.len() * mem::size_of::<f32>() + indices_vec.len() * mem::size_of::<u32>(),
+
+            aabb: mesh.aabb,
+            skinned: mesh.layout.skin,
+            colors: mesh.layout.colors,
         })
     }
 
-    pub(crate) fn draw(&self) {
+    /// GPU memory taken up by the vertex and index buffers. The instance buffer isn't counted since it changes size
+    /// with every draw.
+    pub(crate) fn gpu_bytes(&self) -> usize {
+        self.gpu_bytes
+    }
+
+    /// Draws the mesh once for every model matrix, in a single draw call.
+    pub(crate) fn draw_instanced(&self, models: &[Mat4]) {
+        if models.is_empty() {
+            return;
+        }
+
         unsafe {
             gl::BindVertexArray(self.vao_id);
 
-            gl::DrawElements(
+            // Attributes without a buffer take the current value, which isn't part of the vertex array
+            if !self.colors {
+                gl::VertexAttrib4f(COLOR_ATTR_LOCATION, 1.0, 1.0, 1.0, 1.0);
+            }
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_vbo_id);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                mem::size_of_val(models) as _,
+                models.as_ptr() as _,
+                gl::STREAM_DRAW,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::DrawElementsInstanced(
                 gl::TRIANGLES,
                 self.n_indices as _,
                 gl::UNSIGNED_INT,
                 0 as _,
+                models.len() as _,
             );
 
             gl::BindVertexArray(0);
         }
     }
-}
 
-fn build_vert_attr_vec(mesh: &Mesh) -> Vec<f32> {
-    let mut buf: Vec<f32> = Vec::with_capacity(FLOATS_PER_VERT * mesh.vertices.len());
+    /// Draws the mesh once with a plain draw call, without going through the instance buffer. This is how every mesh
+    /// was drawn before instancing, it's kept around to compare the two.
+    pub(crate) fn draw(&self, model: &Mat4) {
+        unsafe {
+            gl::BindVertexArray(self.vao_id);
+
+            if !self.colors {
+                gl::VertexAttrib4f(COLOR_ATTR_LOCATION, 1.0, 1.0, 1.0, 1.0);
+            }
 
-    for vert in &mesh.vertices {
-        buf.push(vert.position.x);
-        buf.push(vert.position.y);
-        buf.push(vert.position.z);
+            // With the instance attributes turned off, the model matrix is the same for every vertex
+            for col in 0..4 {
+                gl::DisableVertexAttribArray(MODEL_ATTR_LOCATION + col);
+                gl::VertexAttrib4fv(MODEL_ATTR_LOCATION + col, model.col(col as usize).as_ref().as_ptr());
+            }
+
+            gl::DrawElements(gl::TRIANGLES, self.n_indices as _, gl::UNSIGNED_INT, 0 as _);
 
-        buf.push(vert.normal.x);
-        buf.push(vert.normal.y);
-        buf.push(vert.normal.z);
+            for col in 0..4 {
+                gl::EnableVertexAttribArray(MODEL_ATTR_LOCATION + col);
+            }
 
-        buf.push(vert.uv.x);
-        buf.push(vert.uv.y);
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+fn build_vert_attr_vec(mesh: &Mesh, attributes: &[Attribute]) -> Vec<f32> {
+    let floats_per_vert: usize = attributes.iter().map(|attribute| attribute.floats).sum();
+    let mut buf: Vec<f32> = Vec::with_capacity(floats_per_vert * mesh.vertices.len());
+
+    for vert in &mesh.vertices {
+        for attribute in attributes {
+            (attribute.write)(vert, &mut buf);
+        }
     }
 
     // Should be full now
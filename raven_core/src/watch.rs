@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::Result;
+use crate::path;
+
+// Editors and exporters often write a file in several steps, this waits for them to be done
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watches the files of a project, to reload the assets that were changed while it's open.
+pub struct ProjectWatcher {
+    project_root: PathBuf,
+    // Never read but events stop coming once it's dropped
+    #[allow(dead_code)]
+    watcher: RecommendedWatcher,
+    events: Receiver<DebouncedEvent>,
+}
+
+impl ProjectWatcher {
+    pub fn new<R: AsRef<Path>>(project_root: R) -> Result<ProjectWatcher> {
+        // Events come with absolute paths, which have to be turned back into `$/` ones
+        let project_root = project_root.as_ref().canonicalize()?;
+
+        let (sender, events) = mpsc::channel();
+
+        let mut watcher = notify::watcher(sender, DEBOUNCE)?;
+        watcher.watch(&project_root, RecursiveMode::Recursive)?;
+
+        Ok(ProjectWatcher {
+            project_root,
+            watcher,
+            events,
+        })
+    }
+
+    /// Returns the `$/` paths of the files that were created, written to or moved since the last call, without waiting.
+    /// Removed files are left out, since there's nothing to reload.
+    pub fn poll(&self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+
+        for event in self.events.try_iter() {
+            let fs_path = match event {
+                DebouncedEvent::Create(fs_path) | DebouncedEvent::Write(fs_path) | DebouncedEvent::Rename(_, fs_path) => fs_path,
+                _ => continue,
+            };
+
+            if let Some(path) = path::from_fs_abs(&self.project_root, &fs_path) {
+                if !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+
+        changed
+    }
+}
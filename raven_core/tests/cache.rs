@@ -0,0 +1,51 @@
+//! Checks that assets are shared between the components using them and freed once they're not used anymore.
+
+use raven_core::Processor;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn assets_are_shared_and_freed() {
+    let _context = headless_context(32, 32);
+
+    let root = fresh_project("cache").unwrap();
+    save_cubes(&root).unwrap();
+
+    let mut processor = Processor::new(&root).unwrap();
+    processor.set_canvas_size(32, 32);
+
+    processor.load_scene("$/cubes.scn").unwrap();
+    render_loaded(&mut processor).unwrap();
+
+    // Five cubes with two materials, but only one mesh and one texture
+    let stats = processor.get_memory_stats();
+    assert_eq!(stats.meshes, 1);
+    assert_eq!(stats.textures, 1);
+    // 24 vertices of 8 floats and 36 indices
+    assert_eq!(stats.mesh_bytes, (24 * 8 + 36) * 4);
+    // 8x8, without mipmaps since the texture doesn't ask for them
+    assert_eq!(stats.texture_bytes, 64 * 4);
+    // The camera draws into an HDR framebuffer the size of the canvas, 8 bytes per pixel plus 4 for depth and stencil
+    assert_eq!(stats.post_targets, 1);
+    assert_eq!(stats.post_target_bytes, 32 * 32 * 12);
+    assert_eq!(stats.total_bytes(), stats.mesh_bytes + stats.texture_bytes + stats.post_target_bytes);
+
+    // Nothing in the new scene uses the mesh or the texture, they're freed along with the previous scene
+    processor.load_scene("$/empty.scn").unwrap();
+    render_loaded(&mut processor).unwrap();
+
+    let stats = processor.get_memory_stats();
+    assert_eq!(stats.meshes, 0);
+    assert_eq!(stats.textures, 0);
+    assert_eq!(stats.total_bytes(), stats.post_target_bytes);
+
+    // And loaded again when they're needed
+    processor.load_scene("$/cubes.scn").unwrap();
+    render_loaded(&mut processor).unwrap();
+
+    let stats = processor.get_memory_stats();
+    assert_eq!(stats.meshes, 1);
+    assert_eq!(stats.textures, 1);
+}
@@ -0,0 +1,155 @@
+//! Fixtures shared by the integration tests and the benches, which build their projects in code rather than checking
+//! them in.
+
+// Not every test uses every fixture
+#![allow(dead_code)]
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use raven_core::component::{CameraComponent, HierarchyComponent, MeshComponent, TransformComponent};
+use raven_core::database::AssetRef;
+use raven_core::glam::{Mat4, Vec2, Vec3};
+use raven_core::headless::HeadlessContext;
+use raven_core::io::Serializable;
+use raven_core::resource::{Material, Mesh, Scene, Texture, Vertex};
+use raven_core::Processor;
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Makes the context current for the rest of the test. A test needing one runs all its cases in a single test function,
+/// because an OpenGL context can only be current on one thread at a time, while tests run in parallel.
+pub fn headless_context(width: u32, height: u32) -> HeadlessContext {
+    HeadlessContext::new(width, height).expect("couldn't create a headless context")
+}
+
+/// Returns an empty directory for the project of the test, removing what an earlier run left in it.
+pub fn fresh_project(name: &str) -> Result<PathBuf> {
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    if root.exists() {
+        fs::remove_dir_all(&root)?;
+    }
+    fs::create_dir_all(&root)?;
+
+    Ok(root)
+}
+
+/// The skybox isn't part of the fixtures so that they don't depend on its textures.
+pub fn opaque_camera() -> CameraComponent {
+    CameraComponent {
+        clear_color: [0.1, 0.1, 0.1, 1.0],
+        skybox: false,
+        ..CameraComponent::default()
+    }
+}
+
+/// Returns the transform for a camera at `position` looking at the origin.
+pub fn look_from(position: Vec3) -> Mat4 {
+    Mat4::look_at_rh(position, Vec3::ZERO, Vec3::Y).inverse()
+}
+
+pub fn spawn_camera(scene: &mut Scene, camera: CameraComponent, transform: Mat4) {
+    let entity = scene.create();
+    scene.attach(entity, TransformComponent(transform));
+    scene.attach(entity, HierarchyComponent::default());
+    scene.attach(entity, camera);
+}
+
+pub fn spawn_mesh<M: Into<AssetRef>, T: Into<AssetRef>>(scene: &mut Scene, mesh: M, mat: T, transform: Mat4) {
+    let entity = scene.create();
+    scene.attach(entity, TransformComponent(transform));
+    scene.attach(entity, HierarchyComponent::default());
+    scene.attach(entity, MeshComponent::new(mesh, mat));
+}
+
+/// Saves a unit cube centered in the origin, with every face mapped to the whole texture.
+pub fn save_cube(root: &Path) -> Result<()> {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for normal in [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y, Vec3::Z, -Vec3::Z] {
+        // Two axes perpendicular to the normal, forming a right handed basis with it
+        let tangent = if normal.y.abs() > 0.5 { Vec3::X } else { Vec3::Y.cross(normal) };
+        let bitangent = normal.cross(tangent);
+
+        let base = vertices.len() as u32;
+
+        for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            vertices.push(Vertex::new(
+                (normal + tangent * (u * 2.0 - 1.0) + bitangent * (v * 2.0 - 1.0)) * 0.5,
+                normal,
+                Vec2::new(u, v),
+            ));
+        }
+
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    Ok(Mesh::new(vertices, indices).save(root.join("cube.mesh"))?)
+}
+
+/// Saves `cubes.scn`, a row of five cubes sharing one mesh, alternating between a plain and a checkerboard material,
+/// and `empty.scn`, only a camera, which unloads them.
+pub fn save_cubes(root: &Path) -> Result<()> {
+    save_cube(root)?;
+    save_checkerboard(root)?;
+    Material::default().save(root.join("plain.mat"))?;
+    Material { tex: Some(AssetRef::from("$/checkerboard.tex")), ..Material::default() }.save(root.join("checkerboard.mat"))?;
+
+    let mut scene = Scene::default();
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 0.0, 8.0)));
+
+    for i in 0..5 {
+        let mat = if i % 2 == 0 { "$/plain.mat" } else { "$/checkerboard.mat" };
+        spawn_mesh(&mut scene, "$/cube.mesh", mat, Mat4::from_translation(Vec3::new(i as f32 - 2.0, 0.0, 0.0)));
+    }
+
+    scene.save(root.join("cubes.scn"))?;
+
+    let mut empty = Scene::default();
+    spawn_camera(&mut empty, opaque_camera(), Mat4::IDENTITY);
+    empty.save(root.join("empty.scn"))?;
+
+    Ok(())
+}
+
+pub fn save_checkerboard(root: &Path) -> Result<()> {
+    save_texture(&root.join("checkerboard.tex"), 8, |x, y| {
+        if (x + y) % 2 == 0 { [230, 180, 40, 255] } else { [40, 60, 160, 255] }
+    })
+}
+
+/// Saves a square texture, with the color of every pixel given by `pixel(x, y)`.
+pub fn save_texture<F: Fn(u32, u32) -> [u8; 4]>(path: &Path, size: u32, pixel: F) -> Result<()> {
+    let mut raw = Vec::new();
+
+    for y in 0..size {
+        for x in 0..size {
+            raw.extend_from_slice(&pixel(x, y));
+        }
+    }
+
+    Ok(Texture::new(raw, [size, size]).save(path)?)
+}
+
+/// Draws frames until every asset used by the scene has been streamed in, so that the last one has all of them.
+pub fn render_loaded(processor: &mut Processor) -> Result<()> {
+    let start = Instant::now();
+
+    loop {
+        processor.do_frame().map_err(|err| format!("{:?}", err))?;
+
+        if processor.get_load_progress().is_done() {
+            return Ok(());
+        }
+
+        if start.elapsed() > Duration::from_secs(10) {
+            return Err(Box::from(format!("assets still loading after 10s: {:?}", processor.get_load_progress())));
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,100 @@
+//! Checks that assets keep their GUIDs when they're moved and that references by GUID follow them.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use raven_core::database::{AssetDatabase, AssetRef, Meta};
+use raven_core::glam::{Mat4, Vec3};
+use raven_core::io::Serializable;
+use raven_core::resource::{Material, Scene};
+use raven_core::Processor;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn references_follow_moved_assets() {
+    let _context = headless_context(32, 32);
+
+    let root = build_project().unwrap();
+
+    let mut db = AssetDatabase::open(&root).unwrap();
+
+    // Hidden directories aren't part of the project
+    assert!(db.get_guid("$/.git/HEAD").is_none());
+    assert!(!root.join(".git/HEAD.meta").exists());
+
+    // Neither are files that aren't assets
+    assert!(db.get_guid("$/notes.txt").is_none());
+    assert!(!root.join("notes.txt.meta").exists());
+
+    let mesh_guid = db.get_guid("$/cube.mesh").unwrap();
+    let tex_guid = db.get_guid("$/checkerboard.tex").unwrap();
+
+    db.move_asset("$/cube.mesh", "$/meshes/box.mesh").unwrap();
+    db.move_asset("$/checkerboard.tex", "$/textures/board.tex").unwrap();
+
+    assert!(!root.join("cube.mesh").exists());
+    assert!(!root.join("cube.mesh.meta").exists());
+    assert_eq!(db.get_guid("$/meshes/box.mesh"), Some(mesh_guid));
+    assert_eq!(db.get_path(tex_guid), Some(Path::new("$/textures/board.tex")));
+
+    // Opening it again finds them from their meta files
+    let db = AssetDatabase::open(&root).unwrap();
+    assert_eq!(db.get_path(mesh_guid), Some(Path::new("$/meshes/box.mesh")));
+
+    // References still have the old paths, but are drawn anyway
+    let mut processor = Processor::new(&root).unwrap();
+    processor.set_canvas_size(32, 32);
+    processor.load_scene("$/main.scn").unwrap();
+    render_loaded(&mut processor).unwrap();
+
+    assert_eq!(processor.get_culling_stats().drawn, 1);
+    assert_eq!(processor.get_memory_stats().textures, 1);
+
+    // A copy gets a GUID of its own
+    fs::copy(root.join("meshes/box.mesh"), root.join("meshes/copy.mesh")).unwrap();
+    fs::copy(root.join("meshes/box.mesh.meta"), root.join("meshes/copy.mesh.meta")).unwrap();
+
+    let mut db = AssetDatabase::open(&root).unwrap();
+    assert_eq!(db.assign_guids().unwrap(), vec![PathBuf::from("$/meshes/copy.mesh")]);
+    assert_eq!(db.get_guid("$/meshes/box.mesh"), Some(mesh_guid));
+    assert_ne!(db.get_guid("$/meshes/copy.mesh"), Some(mesh_guid));
+
+    // Saved again after being removed, like imported assets are, it keeps the GUID of the meta file left behind
+    fs::remove_file(root.join("meshes/box.mesh")).unwrap();
+    save_cube(&root.join("meshes")).unwrap();
+    fs::rename(root.join("meshes/cube.mesh"), root.join("meshes/box.mesh")).unwrap();
+
+    let mut db = AssetDatabase::open(&root).unwrap();
+    assert_eq!(db.assign_guid("$/meshes/box.mesh").unwrap(), mesh_guid);
+    assert_eq!(db.reference("$/meshes/box.mesh").guid, Some(mesh_guid));
+}
+
+fn build_project() -> Result<PathBuf> {
+    let root = fresh_project("database")?;
+    fs::create_dir(root.join(".git"))?;
+    fs::write(root.join(".git/HEAD"), "ref: refs/heads/master")?;
+    fs::write(root.join("notes.txt"), "Not an asset")?;
+
+    save_cube(&root)?;
+    save_checkerboard(&root)?;
+
+    let mut db = AssetDatabase::open(&root)?;
+    db.assign_guids()?;
+
+    assert!(root.join("cube.mesh.meta").is_file());
+    assert_eq!(Meta::load(root.join("cube.mesh.meta"))?.guid, db.get_guid("$/cube.mesh").unwrap());
+
+    Material { tex: Some(db.reference("$/checkerboard.tex")), ..Material::default() }.save(root.join("checkerboard.mat"))?;
+
+    let mut scene = Scene::default();
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 0.0, 8.0)));
+    spawn_mesh(&mut scene, db.reference("$/cube.mesh"), AssetRef::from("$/checkerboard.mat"), Mat4::IDENTITY);
+    scene.save(root.join("main.scn"))?;
+
+    db.assign_guids()?;
+
+    Ok(root)
+}
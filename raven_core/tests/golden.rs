@@ -0,0 +1,603 @@
+//! Golden image tests for the renderer.
+//!
+//! Every case builds a small project in a temporary directory, renders its `main.scn` with a headless context at a
+//! fixed resolution and compares the frame against the reference PNG stored in `tests/golden`. When a case doesn't
+//! match, the rendered frame and an image highlighting the differing pixels are written next to the test binary.
+//!
+//! Run with `RAVEN_BLESS=1` to overwrite the references with the rendered frames, after checking that the changes are
+//! intended.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use raven_core::animation::{Interpolation, Track};
+use raven_core::bake::{bake_texture, Compression, TextureSettings};
+use raven_core::component::{AnimationLayer, AnimatorComponent, CameraComponent, HierarchyComponent, LodGroupComponent, MeshComponent, Projection, TransformComponent};
+use raven_core::database::AssetRef;
+use raven_core::glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use raven_core::headless::HeadlessContext;
+use raven_core::image::{Rgba, RgbaImage};
+use raven_core::io::Serializable;
+use raven_core::primitive::Primitive;
+use raven_core::postprocess::{Bloom, Fxaa, GammaCorrection, ToneMapOperator, ToneMapping, Vignette};
+use raven_core::resource::{AnimationClip, BlendMode, Filter, Joint, JointChannel, JointTransform, Material, Mesh, Sampler, Scene, Skeleton, Vertex, VertexLayout, Wrap};
+use raven_core::Processor;
+
+use common::*;
+
+mod common;
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 96;
+
+/// Two pixels are considered different when their CIE76 distance is greater than this. A distance of about 2.3 is the
+/// smallest difference the human eye can notice.
+const MAX_DELTA_E: f32 = 5.0;
+/// Fraction of the pixels allowed to differ, to tolerate rasterizers disagreeing on triangle edges.
+const MAX_DIFFERING_PIXELS: f32 = 0.005;
+
+struct Case {
+    name: &'static str,
+    build: fn(&Path) -> Result<()>,
+}
+
+const CASES: &[Case] = &[
+    Case { name: "clear_color", build: build_clear_color },
+    Case { name: "untextured_cube", build: build_untextured_cube },
+    Case { name: "textured_cube", build: build_textured_cube },
+    Case { name: "orthographic", build: build_orthographic },
+    Case { name: "split_screen", build: build_split_screen },
+    Case { name: "instanced_cubes", build: build_instanced_cubes },
+    Case { name: "transparency", build: build_transparency },
+    Case { name: "post_processing", build: build_post_processing },
+    Case { name: "compressed_textures", build: build_compressed_textures },
+    Case { name: "material_colors", build: build_material_colors },
+    Case { name: "skinned_mesh", build: build_skinned_mesh },
+    Case { name: "vertex_colors", build: build_vertex_colors },
+    Case { name: "lod_group", build: build_lod_group },
+    Case { name: "primitives", build: build_primitives },
+];
+
+// A single test because an OpenGL context can only be current on one thread at a time, while tests run in parallel
+#[test]
+fn golden_images() {
+    let _context = HeadlessContext::new(WIDTH, HEIGHT).expect("couldn't create a headless context");
+
+    let bless = env::var_os("RAVEN_BLESS").is_some();
+
+    let mut failures = Vec::new();
+
+    for case in CASES {
+        if let Err(err) = run_case(case, bless) {
+            failures.push(format!("{}: {}", case.name, err));
+        }
+    }
+
+    assert!(failures.is_empty(), "golden image mismatches:\n{}", failures.join("\n"));
+}
+
+fn run_case(case: &Case, bless: bool) -> Result<()> {
+    let project_root = output_dir().join("projects").join(case.name);
+    if project_root.exists() {
+        fs::remove_dir_all(&project_root)?;
+    }
+    fs::create_dir_all(&project_root)?;
+
+    (case.build)(&project_root)?;
+
+    let mut processor = Processor::new(&project_root)?;
+    processor.set_canvas_size(WIDTH, HEIGHT);
+    processor.load_scene("$/main.scn")?;
+    render_loaded(&mut processor)?;
+
+    let frame = processor.capture_frame();
+
+    let reference_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", case.name));
+
+    if bless {
+        frame.save(&reference_path)?;
+        return Ok(());
+    }
+
+    let reference = raven_core::image::open(&reference_path)
+        .map_err(|err| format!("couldn't open {:?}: {}, run with RAVEN_BLESS=1 to create it", reference_path, err))?
+        .into_rgba8();
+
+    if reference.dimensions() != frame.dimensions() {
+        return Err(Box::from(format!("expected a {:?} frame, got {:?}", reference.dimensions(), frame.dimensions())));
+    }
+
+    let (diff, differing) = diff(&reference, &frame);
+    let differing_fraction = differing as f32 / (WIDTH * HEIGHT) as f32;
+
+    if differing_fraction > MAX_DIFFERING_PIXELS {
+        let actual_path = output_dir().join(format!("{}.actual.png", case.name));
+        let diff_path = output_dir().join(format!("{}.diff.png", case.name));
+
+        frame.save(&actual_path)?;
+        diff.save(&diff_path)?;
+
+        return Err(Box::from(format!(
+            "{:.2}% of the pixels differ, see {:?} and {:?}",
+            differing_fraction * 100.0, actual_path, diff_path,
+        )));
+    }
+
+    Ok(())
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+/// Returns an image with the differing pixels in red over a faded copy of the reference, and how many pixels differ.
+fn diff(reference: &RgbaImage, frame: &RgbaImage) -> (RgbaImage, u32) {
+    let mut out = RgbaImage::new(reference.width(), reference.height());
+    let mut differing = 0;
+
+    for (x, y, want) in reference.enumerate_pixels() {
+        let got = frame.get_pixel(x, y);
+
+        if delta_e(want, got) > MAX_DELTA_E {
+            differing += 1;
+            out.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            let [red, green, blue, _] = want.0;
+            let luma = (0.299 * red as f32 + 0.587 * green as f32 + 0.114 * blue as f32) as u8;
+            let faded = 128 + luma / 2;
+            out.put_pixel(x, y, Rgba([faded, faded, faded, 255]));
+        }
+    }
+
+    (out, differing)
+}
+
+/// CIE76 color difference, the euclidean distance between two colors in the CIELAB color space.
+fn delta_e(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let [l_a, a_a, b_a] = to_lab(a);
+    let [l_b, a_b, b_b] = to_lab(b);
+
+    ((l_a - l_b).powi(2) + (a_a - a_b).powi(2) + (b_a - b_b).powi(2)).sqrt()
+}
+
+fn to_lab(color: &Rgba<u8>) -> [f32; 3] {
+    fn to_linear(c: u8) -> f32 {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    }
+
+    fn f(t: f32) -> f32 {
+        if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 }
+    }
+
+    let [red, green, blue, _] = color.0;
+    let (red, green, blue) = (to_linear(red), to_linear(green), to_linear(blue));
+
+    // Linear sRGB to XYZ, relative to the D65 white point
+    let x = (0.4124 * red + 0.3576 * green + 0.1805 * blue) / 0.95047;
+    let y = 0.2126 * red + 0.7152 * green + 0.0722 * blue;
+    let z = (0.0193 * red + 0.1192 * green + 0.9505 * blue) / 1.08883;
+
+    let (f_x, f_y, f_z) = (f(x), f(y), f(z));
+
+    [116.0 * f_y - 16.0, 500.0 * (f_x - f_y), 200.0 * (f_y - f_z)]
+}
+
+fn build_clear_color(root: &Path) -> Result<()> {
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, CameraComponent {
+        clear_color: [0.2, 0.5, 0.8, 1.0],
+        skybox: false,
+        ..CameraComponent::default()
+    }, Mat4::IDENTITY);
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_untextured_cube(root: &Path) -> Result<()> {
+    save_cube(root)?;
+    Material::default().save(root.join("cube.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(1.2, 1.2, 1.8)));
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/cube.mat", Mat4::IDENTITY);
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_textured_cube(root: &Path) -> Result<()> {
+    save_cube(root)?;
+    save_checkerboard(root)?;
+    Material { tex: Some(AssetRef::from("$/checkerboard.tex")), ..Material::default() }.save(root.join("cube.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(-1.2, 0.9, 1.8)));
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/cube.mat", Mat4::from_rotation_y(0.3));
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_orthographic(root: &Path) -> Result<()> {
+    save_cube(root)?;
+    save_checkerboard(root)?;
+    Material { tex: Some(AssetRef::from("$/checkerboard.tex")), ..Material::default() }.save(root.join("cube.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, CameraComponent {
+        projection: Projection::Orthographic { height: 4.0, near: 0.1, far: 100.0 },
+        ..opaque_camera()
+    }, look_from(Vec3::new(3.0, 3.0, 3.0)));
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/cube.mat", Mat4::from_translation(Vec3::new(-1.0, 0.0, 0.0)));
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/cube.mat", Mat4::from_translation(Vec3::new(1.0, 0.0, 0.0)));
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_split_screen(root: &Path) -> Result<()> {
+    save_cube(root)?;
+    save_checkerboard(root)?;
+    Material { tex: Some(AssetRef::from("$/checkerboard.tex")), ..Material::default() }.save(root.join("cube.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, CameraComponent {
+        viewport: [0.0, 0.0, 0.5, 1.0],
+        ..opaque_camera()
+    }, look_from(Vec3::new(0.0, 0.0, 3.0)));
+
+    spawn_camera(&mut scene, CameraComponent {
+        clear_color: [0.8, 0.3, 0.3, 1.0],
+        viewport: [0.5, 0.0, 0.5, 1.0],
+        ..opaque_camera()
+    }, look_from(Vec3::new(0.0, 3.0, 0.1)));
+
+    // Picture in picture, drawn last because of its order
+    spawn_camera(&mut scene, CameraComponent {
+        clear_color: [0.3, 0.8, 0.3, 1.0],
+        viewport: [0.75, 0.75, 0.25, 0.25],
+        order: 1,
+        ..opaque_camera()
+    }, look_from(Vec3::new(3.0, 0.0, 0.0)));
+
+    // Inactive, would cover everything else if it was drawn
+    spawn_camera(&mut scene, CameraComponent {
+        clear_color: [1.0, 1.0, 1.0, 1.0],
+        order: 2,
+        active: false,
+        ..opaque_camera()
+    }, Mat4::IDENTITY);
+
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/cube.mat", Mat4::IDENTITY);
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_instanced_cubes(root: &Path) -> Result<()> {
+    save_cube(root)?;
+    save_checkerboard(root)?;
+    Material::default().save(root.join("plain.mat"))?;
+    Material { tex: Some(AssetRef::from("$/checkerboard.tex")), ..Material::default() }.save(root.join("checkerboard.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 4.0, 6.0)));
+
+    // Interleaved materials, so that batching has to reorder the cubes
+    for i in 0..9 {
+        let position = Vec3::new((i % 3) as f32 - 1.0, 0.0, (i / 3) as f32 - 1.0) * 1.5;
+        let mat = if i % 2 == 0 { "$/plain.mat" } else { "$/checkerboard.mat" };
+
+        spawn_mesh(&mut scene, "$/cube.mesh", mat, Mat4::from_translation(position));
+    }
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_transparency(root: &Path) -> Result<()> {
+    save_cube(root)?;
+    Material::default().save(root.join("plain.mat"))?;
+
+    // Opaque checkerboard with holes, which would be drawn black if the alpha test didn't discard them
+    save_texture(&root.join("holes.tex"), 4, |x, y| {
+        if (x + y) % 2 == 0 { [230, 180, 40, 255] } else { [0, 0, 0, 0] }
+    })?;
+    Material {
+        tex: Some(AssetRef::from("$/holes.tex")),
+        blend: BlendMode::AlphaTest { cutoff: 0.5 },
+        ..Material::default()
+    }.save(root.join("holes.mat"))?;
+
+    for (name, color) in [("red", [220, 40, 40, 128]), ("blue", [40, 40, 220, 128]), ("green", [40, 200, 40, 255])] {
+        save_texture(&root.join(format!("{}.tex", name)), 1, |_, _| color)?;
+    }
+    for (name, blend) in [("red", BlendMode::AlphaBlend), ("blue", BlendMode::AlphaBlend), ("green", BlendMode::Additive)] {
+        Material {
+            tex: Some(AssetRef::from(format!("$/{}.tex", name))),
+            blend,
+            ..Material::default()
+        }.save(root.join(format!("{}.mat", name)))?;
+    }
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 0.0, 3.0)));
+
+    // Wall behind everything
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/plain.mat", Mat4::from_scale_rotation_translation(
+        Vec3::new(6.0, 4.0, 0.2), Quat::IDENTITY, Vec3::new(0.0, 0.0, -1.5),
+    ));
+
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/holes.mat", Mat4::from_translation(Vec3::new(-1.6, 0.0, 0.0)));
+
+    // The closest one is spawned first, so that sorting them back to front is needed for the red to be on top
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/red.mat", Mat4::from_translation(Vec3::new(0.8, -0.3, 1.0)));
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/blue.mat", Mat4::from_translation(Vec3::new(1.3, 0.2, 0.0)));
+
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/green.mat", Mat4::from_translation(Vec3::new(-0.4, 0.9, 0.5)));
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_post_processing(root: &Path) -> Result<()> {
+    save_cube(root)?;
+    save_checkerboard(root)?;
+    Material { tex: Some(AssetRef::from("$/checkerboard.tex")), ..Material::default() }.save(root.join("cube.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, CameraComponent {
+        clear_color: [0.05, 0.05, 0.1, 1.0],
+        post: vec![
+            // Low threshold, nothing is brighter than 1 without lighting
+            Box::new(Bloom { threshold: 0.6, intensity: 2.0, iterations: 4 }),
+            Box::new(ToneMapping { operator: ToneMapOperator::Reinhard, exposure: 1.5 }),
+            Box::new(GammaCorrection::default()),
+            Box::new(Fxaa::default()),
+            Box::new(Vignette { intensity: 0.8, ..Vignette::default() }),
+        ],
+        ..opaque_camera()
+    }, look_from(Vec3::new(1.2, 1.2, 1.8)));
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/cube.mat", Mat4::IDENTITY);
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_compressed_textures(root: &Path) -> Result<()> {
+    save_cube(root)?;
+
+    // Colourful quadrants with a smooth gradient across them, which block compression can't reproduce exactly
+    let opaque = RgbaImage::from_fn(16, 16, |x, y| {
+        let shade = (x * 8 + y * 4) as u8;
+        Rgba(match (x < 8, y < 8) {
+            (true, true) => [230, 60 + shade, 40, 255],
+            (false, true) => [40, 200, 60 + shade, 255],
+            (true, false) => [60 + shade, 60, 220, 255],
+            (false, false) => [240, 240, 240 - shade, 255],
+        })
+    });
+    let transparent = RgbaImage::from_fn(16, 16, |x, y| {
+        let Rgba([r, g, b, _]) = *opaque.get_pixel(x, y);
+        Rgba([r, g, b, (x * 16) as u8])
+    });
+
+    let cases = [
+        ("bc", &opaque, Compression::Bc, BlendMode::Opaque),
+        ("etc2", &opaque, Compression::Etc2, BlendMode::Opaque),
+        ("bc_alpha", &transparent, Compression::Bc, BlendMode::AlphaBlend),
+        ("etc2_alpha", &transparent, Compression::Etc2, BlendMode::AlphaBlend),
+    ];
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 0.0, 2.6)));
+
+    for (i, (name, image, compression, blend)) in cases.iter().enumerate() {
+        bake_texture(image, &TextureSettings {
+            compression: *compression,
+            // Pixelated and not repeated, so that the blocks are easy to tell apart
+            sampler: Sampler {
+                wrap: Wrap::ClampToEdge,
+                mag_filter: Filter::Nearest,
+                mipmaps: true,
+                ..Sampler::default()
+            },
+            ..TextureSettings::default()
+        }).save(root.join(format!("{}.tex", name)))?;
+
+        Material {
+            tex: Some(AssetRef::from(format!("$/{}.tex", name))),
+            blend: *blend,
+            ..Material::default()
+        }.save(root.join(format!("{}.mat", name)))?;
+
+        let position = Vec3::new((i % 2) as f32 * 1.4 - 0.7, 0.6 - (i / 2) as f32 * 1.2, 0.0);
+        spawn_mesh(&mut scene, "$/cube.mesh", format!("$/{}.mat", name), Mat4::from_translation(position));
+    }
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_material_colors(root: &Path) -> Result<()> {
+    save_cube(root)?;
+    save_checkerboard(root)?;
+
+    // A colour on its own and one tinting a texture
+    Material { color: Some([0.9, 0.3, 0.1, 1.0]), ..Material::default() }.save(root.join("orange.mat"))?;
+    Material {
+        tex: Some(AssetRef::from("$/checkerboard.tex")),
+        color: Some([0.2, 0.5, 1.0, 1.0]),
+        ..Material::default()
+    }.save(root.join("tinted.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 1.5, 3.0)));
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/orange.mat", Mat4::from_translation(Vec3::new(-0.8, 0.0, 0.0)));
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/tinted.mat", Mat4::from_translation(Vec3::new(0.8, 0.0, 0.0)));
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_skinned_mesh(root: &Path) -> Result<()> {
+    // A strip two units tall, the lower half following the first joint and the upper half the second one, which sits
+    // halfway up. Vertices around the middle are shared between the two, so that the strip bends smoothly
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for row in 0..=8 {
+        let y = row as f32 * 0.25;
+        let upper = ((y - 0.75) / 0.5).clamp(0.0, 1.0);
+
+        for x in [-0.2, 0.2] {
+            vertices.push(Vertex {
+                joints: [0, 1, 0, 0],
+                weights: [1.0 - upper, upper, 0.0, 0.0],
+                ..Vertex::new(Vec3::new(x, y, 0.0), Vec3::Z, Vec2::new(x + 0.5, y / 2.0))
+            });
+        }
+
+        if row > 0 {
+            let base = (row - 1) * 2;
+            indices.extend_from_slice(&[base, base + 1, base + 3, base, base + 3, base + 2]);
+        }
+    }
+
+    Mesh::new(vertices, indices).save(root.join("strip.mesh"))?;
+    Material { color: Some([0.3, 0.8, 0.4, 1.0]), ..Material::default() }.save(root.join("strip.mat"))?;
+
+    let elbow = JointTransform { translation: Vec3::Y, ..JointTransform::default() };
+
+    Skeleton {
+        joints: vec![
+            Joint { name: "root".to_owned(), parent: None, rest: JointTransform::default(), inverse_bind: Mat4::IDENTITY },
+            Joint { name: "elbow".to_owned(), parent: Some(0), rest: elbow, inverse_bind: elbow.to_mat4().inverse() },
+        ],
+    }.save(root.join("strip.skel"))?;
+
+    AnimationClip {
+        duration: 1.0,
+        channels: vec![JointChannel {
+            joint: "elbow".to_owned(),
+            rotation: Track::new(
+                vec![0.0, 1.0],
+                vec![Quat::IDENTITY, Quat::from_rotation_z(-std::f32::consts::FRAC_PI_2)],
+                Interpolation::Linear,
+            ),
+            ..JointChannel::default()
+        }],
+    }.save(root.join("bend.anim"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 1.0, 3.5)));
+
+    // Two thirds of the way through the bend
+    let mut layer = AnimationLayer::new("$/bend.anim");
+    layer.time = 2.0 / 3.0;
+
+    let mut animator = AnimatorComponent::new("$/strip.skel");
+    animator.layers.push(layer);
+
+    let animated = scene.create();
+    scene.attach(animated, TransformComponent(Mat4::from_translation(Vec3::new(-0.5, 0.0, 0.0))));
+    scene.attach(animated, animator);
+
+    // The mesh is a child of the animator, its own transform is ignored
+    let mesh = scene.create();
+    scene.attach(mesh, TransformComponent(Mat4::from_translation(Vec3::new(10.0, 0.0, 0.0))));
+    scene.attach(mesh, HierarchyComponent { parent: Some(animated), children: Vec::new() });
+    scene.attach(mesh, MeshComponent::new("$/strip.mesh", "$/strip.mat"));
+
+    scene.attach(animated, HierarchyComponent { parent: None, children: vec![mesh] });
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_vertex_colors(root: &Path) -> Result<()> {
+    // A square without UVs, its colours blended from one corner to the next
+    let corners = [
+        (Vec3::new(-1.0, -1.0, 0.0), Vec4::new(1.0, 0.0, 0.0, 1.0)),
+        (Vec3::new(1.0, -1.0, 0.0), Vec4::new(0.0, 1.0, 0.0, 1.0)),
+        (Vec3::new(1.0, 1.0, 0.0), Vec4::new(0.0, 0.0, 1.0, 1.0)),
+        (Vec3::new(-1.0, 1.0, 0.0), Vec4::new(1.0, 1.0, 1.0, 1.0)),
+    ];
+
+    let vertices = corners
+        .iter()
+        .map(|&(position, color)| Vertex { color, ..Vertex::new(position, Vec3::Z, Vec2::ZERO) })
+        .collect();
+
+    let layout = VertexLayout { uv: false, colors: true, ..VertexLayout::default() };
+    Mesh::with_layout(vertices, vec![0, 1, 2, 0, 2, 3], layout).save(root.join("square.mesh"))?;
+
+    // The material tints the vertex colours
+    Material { color: Some([1.0, 1.0, 0.5, 1.0]), ..Material::default() }.save(root.join("square.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 0.0, 2.0)));
+    spawn_mesh(&mut scene, "$/square.mesh", "$/square.mat", Mat4::IDENTITY);
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_lod_group(root: &Path) -> Result<()> {
+    // A square whose only LOD is half of it, so that it's clear which one is drawn
+    let corners = [Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(-1.0, 1.0, 0.0)];
+    let vertices: Vec<_> = corners.iter().map(|&position| Vertex::new(position, Vec3::Z, Vec2::ZERO)).collect();
+
+    Mesh::new(vertices.clone(), vec![0, 1, 2, 0, 2, 3]).save(root.join("square.mesh"))?;
+    Mesh::new(vertices, vec![0, 1, 2]).save(root.join("square_lod1.mesh"))?;
+    Material { color: Some([0.9, 0.6, 0.2, 1.0]), ..Material::default() }.save(root.join("square.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 0.0, 2.0)));
+
+    // Covering most of the viewport, halfway down it and too small to be drawn
+    for &position in &[Vec3::new(-1.5, 0.0, 0.0), Vec3::new(2.5, 0.0, -2.0), Vec3::new(0.0, 0.0, -10.0)] {
+        let entity = scene.create();
+
+        let mut mesh_comp = MeshComponent::new("$/square.mesh", "$/square.mat");
+        mesh_comp.lods.push(AssetRef::from("$/square_lod1.mesh"));
+
+        scene.attach(entity, TransformComponent(Mat4::from_translation(position)));
+        scene.attach(entity, HierarchyComponent::default());
+        scene.attach(entity, mesh_comp);
+        scene.attach(entity, LodGroupComponent { cull_size: 0.2, ..LodGroupComponent::with_levels(1) });
+    }
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
+
+fn build_primitives(root: &Path) -> Result<()> {
+    save_checkerboard(root)?;
+    Material { tex: Some(AssetRef::from("$/checkerboard.tex")), ..Material::default() }.save(root.join("checkerboard.mat"))?;
+
+    let mut scene = Scene::default();
+
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 3.0, 3.6)));
+
+    // Two rows of three, the plane shrunk to fit
+    for (i, primitive) in Primitive::ALL.iter().enumerate() {
+        let mesh_path = root.join(format!("{}.mesh", primitive.name()));
+        primitive.mesh().save(&mesh_path)?;
+
+        let position = Vec3::new((i % 3) as f32 * 2.0 - 2.0, 0.0, (i / 3) as f32 * 2.5 - 1.25);
+        let scale = if *primitive == Primitive::Plane { 0.1 } else { 1.0 };
+
+        spawn_mesh(
+            &mut scene,
+            format!("$/{}.mesh", primitive.name()),
+            "$/checkerboard.mat",
+            Mat4::from_scale_rotation_translation(Vec3::splat(scale), Quat::from_rotation_y(0.4), position),
+        );
+    }
+
+    Ok(scene.save(root.join("main.scn"))?)
+}
@@ -0,0 +1,118 @@
+//! Checks that assets changed on disk while a scene is open are picked up by the watcher and reloaded.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use raven_core::component::{HierarchyComponent, SceneComponent, TransformComponent};
+use raven_core::database::AssetRef;
+use raven_core::glam::{Mat4, Vec3};
+use raven_core::input::{ActionMap, Button, Key};
+use raven_core::io::Serializable;
+use raven_core::resource::{Material, Scene};
+use raven_core::watch::ProjectWatcher;
+use raven_core::Processor;
+
+use common::*;
+
+mod common;
+
+#[test]
+fn changed_assets_are_reloaded() {
+    let _context = headless_context(32, 32);
+
+    let root = build_project().unwrap();
+
+    let watcher = ProjectWatcher::new(&root).unwrap();
+
+    let mut processor = Processor::new(&root).unwrap();
+    processor.set_canvas_size(32, 32);
+    processor.load_scene("$/main.scn").unwrap();
+    render_loaded(&mut processor).unwrap();
+
+    assert_eq!(processor.get_culling_stats().drawn, 2);
+    // 8x8, without mipmaps
+    assert_eq!(processor.get_memory_stats().texture_bytes, 64 * 4);
+
+    // A bigger texture
+    save_texture(&root.join("checkerboard.tex"), 16, |x, y| {
+        if (x + y) % 2 == 0 { [255, 255, 255, 255] } else { [0, 0, 0, 255] }
+    }).unwrap();
+
+    processor.reload_assets(&wait_for_change(&watcher, "$/checkerboard.tex")).unwrap();
+    render_loaded(&mut processor).unwrap();
+
+    // The old one is freed as soon as the components let go of it
+    let stats = processor.get_memory_stats();
+    assert_eq!(stats.textures, 1);
+    assert_eq!(stats.texture_bytes, 256 * 4);
+
+    // A nested scene gets a second cube
+    save_nested_scene(&root, 2).unwrap();
+
+    processor.reload_assets(&wait_for_change(&watcher, "$/nested.scn")).unwrap();
+    render_loaded(&mut processor).unwrap();
+
+    assert_eq!(processor.get_culling_stats().drawn, 3);
+
+    // A broken action map, caught halfway through being saved, doesn't unbind anything
+    fs::write(root.join(ActionMap::FILE_NAME), "{\"actions\": {").unwrap();
+
+    processor.reload_assets(&wait_for_change(&watcher, "$/input.json")).unwrap();
+
+    assert!(processor.take_actions_error().is_some());
+    assert_eq!(processor.get_input().actions().buttons("jump"), [Button::Key(Key::Space)]);
+}
+
+/// Waits for the watcher to report the file at `path` as changed, returning everything it reported.
+fn wait_for_change(watcher: &ProjectWatcher, path: &str) -> Vec<PathBuf> {
+    let start = Instant::now();
+    let mut changed = Vec::new();
+
+    while !changed.contains(&PathBuf::from(path)) {
+        assert!(start.elapsed() < Duration::from_secs(10), "{} never changed, got {:?}", path, changed);
+
+        thread::sleep(Duration::from_millis(10));
+        changed.extend(watcher.poll());
+    }
+
+    changed
+}
+
+fn build_project() -> Result<PathBuf> {
+    let root = fresh_project("hot_reload")?;
+
+    save_cube(&root)?;
+    save_checkerboard(&root)?;
+    Material { tex: Some(AssetRef::from("$/checkerboard.tex")), ..Material::default() }.save(root.join("checkerboard.mat"))?;
+
+    save_nested_scene(&root, 1)?;
+
+    let mut actions = ActionMap::default();
+    actions.bind("jump", Button::Key(Key::Space));
+    actions.save_to(&root)?;
+
+    let mut scene = Scene::default();
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 0.0, 8.0)));
+    spawn_mesh(&mut scene, "$/cube.mesh", "$/checkerboard.mat", Mat4::from_translation(Vec3::new(-1.5, 0.0, 0.0)));
+
+    let nested = scene.create();
+    scene.attach(nested, TransformComponent(Mat4::from_translation(Vec3::new(1.5, 0.0, 0.0))));
+    scene.attach(nested, HierarchyComponent::default());
+    scene.attach(nested, SceneComponent { scene: Some(AssetRef::from("$/nested.scn")), loaded: None });
+
+    scene.save(root.join("main.scn"))?;
+
+    Ok(root)
+}
+
+fn save_nested_scene(root: &Path, cubes: u32) -> Result<()> {
+    let mut scene = Scene::default();
+
+    for i in 0..cubes {
+        spawn_mesh(&mut scene, "$/cube.mesh", "$/checkerboard.mat", Mat4::from_translation(Vec3::new(0.0, i as f32 * 1.5, 0.0)));
+    }
+
+    Ok(scene.save(root.join("nested.scn"))?)
+}
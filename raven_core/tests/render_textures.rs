@@ -0,0 +1,89 @@
+//! Checks that cameras drawing to render textures are drawn before the cameras that see them, whatever their order.
+
+use std::path::PathBuf;
+
+use raven_core::component::CameraComponent;
+use raven_core::database::AssetRef;
+use raven_core::glam::{Mat4, Vec3};
+use raven_core::io::Serializable;
+use raven_core::resource::{Material, RenderTexture, Scene};
+use raven_core::Processor;
+
+use common::*;
+
+mod common;
+
+const SIZE: u32 = 32;
+
+#[test]
+fn render_textures_are_drawn_before_they_are_seen() {
+    let _context = headless_context(SIZE, SIZE);
+
+    let root = build_project().unwrap();
+
+    let mut processor = Processor::new(&root).unwrap();
+    processor.set_canvas_size(SIZE, SIZE);
+    processor.set_streaming(false);
+
+    // The red cube is seen through two render textures, on the very first frame
+    processor.load_scene("$/chain.scn").unwrap();
+    processor.do_frame().unwrap();
+
+    let frame = processor.capture_frame();
+    let [red, green, blue, _] = frame.get_pixel(SIZE / 2, SIZE / 2).0;
+    assert!(red > 200 && green < 50 && blue < 50, "expected red, got {:?}", [red, green, blue]);
+
+    assert!(processor.get_render_texture_cycles().is_empty());
+
+    // Two cameras that see each other's texture can't both go first
+    processor.load_scene("$/cycle.scn").unwrap();
+    processor.do_frame().unwrap();
+
+    assert_eq!(processor.get_render_texture_cycles().len(), 1);
+}
+
+/// Every station is far away from the others, so that its camera only sees its own cube.
+fn spawn_station(scene: &mut Scene, x: f32, mat: &str, camera: CameraComponent) {
+    let center = Vec3::new(x, 0.0, 0.0);
+
+    spawn_mesh(scene, "$/cube.mesh", mat, Mat4::from_translation(center));
+    spawn_camera(scene, camera, Mat4::look_at_rh(center + Vec3::new(0.0, 0.0, 1.5), center, Vec3::Y).inverse());
+}
+
+fn textured(tex: &str) -> Material {
+    Material { tex: Some(AssetRef::from(tex)), ..Material::default() }
+}
+
+fn to_texture(tex: &str, order: i32) -> CameraComponent {
+    CameraComponent {
+        target: Some(AssetRef::from(tex)),
+        order,
+        ..opaque_camera()
+    }
+}
+
+fn build_project() -> Result<PathBuf> {
+    let root = fresh_project("render_textures")?;
+
+    save_cube(&root)?;
+    Material { color: Some([1.0, 0.0, 0.0, 1.0]), ..Material::default() }.save(root.join("red.mat"))?;
+    textured("$/a.rtex").save(root.join("a.mat"))?;
+    textured("$/b.rtex").save(root.join("b.mat"))?;
+    RenderTexture { size: [SIZE, SIZE] }.save(root.join("a.rtex"))?;
+    RenderTexture { size: [SIZE, SIZE] }.save(root.join("b.rtex"))?;
+
+    // The camera drawing the red cube into a.rtex has the greatest order, yet the one seeing a.rtex needs it first
+    let mut chain = Scene::default();
+    spawn_station(&mut chain, 0.0, "$/red.mat", to_texture("$/a.rtex", 5));
+    spawn_station(&mut chain, 100.0, "$/a.mat", to_texture("$/b.rtex", 0));
+    spawn_station(&mut chain, 200.0, "$/b.mat", opaque_camera());
+    chain.save(root.join("chain.scn"))?;
+
+    let mut cycle = Scene::default();
+    spawn_station(&mut cycle, 0.0, "$/b.mat", to_texture("$/a.rtex", 0));
+    spawn_station(&mut cycle, 100.0, "$/a.mat", to_texture("$/b.rtex", 0));
+    spawn_station(&mut cycle, 200.0, "$/a.mat", opaque_camera());
+    cycle.save(root.join("cycle.scn"))?;
+
+    Ok(root)
+}
@@ -0,0 +1,94 @@
+//! Checks that assets are loaded in the background and that the scene is drawn with placeholders until they're
+//! ready.
+
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use raven_core::glam::{Mat4, Vec3};
+use raven_core::io::Serializable;
+use raven_core::resource::Scene;
+use raven_core::{Error, FrameError, Processor};
+
+use common::*;
+
+mod common;
+
+#[test]
+fn assets_are_streamed() {
+    let _context = headless_context(32, 32);
+
+    let root = build_project().unwrap();
+
+    let mut processor = Processor::new(&root).unwrap();
+    processor.set_canvas_size(32, 32);
+
+    processor.load_scene("$/cubes.scn").unwrap();
+    processor.do_frame().unwrap();
+
+    // The first frame only asks for the assets, so every mesh is drawn as a placeholder
+    assert_eq!(processor.get_culling_stats().drawn, 5);
+
+    let frame = processor.capture_frame();
+    assert_ne!(frame.get_pixel(16, 16), frame.get_pixel(0, 0), "the placeholder wasn't drawn");
+
+    let progress = processor.get_load_progress();
+    // The mesh and the two materials, the texture is only asked for once its material is loaded
+    assert_eq!(progress.pending, 3);
+    assert_eq!(progress.loaded, 0);
+    assert!(!progress.is_done());
+
+    render_loaded(&mut processor).unwrap();
+
+    assert_eq!(processor.get_culling_stats().drawn, 5);
+    let progress = processor.get_load_progress();
+    assert_eq!(progress.pending, 0);
+    assert_eq!(progress.loaded, 4);
+    assert_eq!(progress.fraction(), 1.0);
+
+    // Without streaming, everything is loaded by the first frame
+    processor.load_scene("$/empty.scn").unwrap();
+    render_loaded(&mut processor).unwrap();
+
+    processor.set_streaming(false);
+    processor.load_scene("$/cubes.scn").unwrap();
+    processor.do_frame().unwrap();
+
+    assert_eq!(processor.get_culling_stats().drawn, 5);
+    assert!(processor.get_load_progress().is_done());
+
+    // Assets that can't be loaded fail the frame once the loader gets to them
+    processor.set_streaming(true);
+    processor.load_scene("$/missing.scn").unwrap();
+
+    let err = wait_for_error(&mut processor);
+    assert!(matches!(err, Error::Io { .. }), "unexpected error: {}", err);
+    assert_eq!(err.path(), Some(Path::new("$/missing.mesh")));
+}
+
+fn wait_for_error(processor: &mut Processor) -> Error {
+    let start = Instant::now();
+
+    loop {
+        match processor.do_frame() {
+            Ok(()) => assert!(start.elapsed() < Duration::from_secs(10), "the frame never failed"),
+            Err(FrameError::Generic(err)) => return err,
+            Err(err) => panic!("unexpected error: {:?}", err),
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn build_project() -> Result<PathBuf> {
+    let root = fresh_project("streaming")?;
+
+    save_cubes(&root)?;
+
+    let mut scene = Scene::default();
+    spawn_camera(&mut scene, opaque_camera(), look_from(Vec3::new(0.0, 0.0, 8.0)));
+    spawn_mesh(&mut scene, "$/missing.mesh", "$/plain.mat", Mat4::IDENTITY);
+    scene.save(root.join("missing.scn"))?;
+
+    Ok(root)
+}
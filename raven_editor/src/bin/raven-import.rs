@@ -0,0 +1,120 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde::Serialize;
+
+use raven_core::database::AssetDatabase;
+use raven_core::path;
+use raven_core::{Error, Result};
+use raven_editor::import;
+
+const USAGE: &'static str = "usage: raven-import <project root> <path or glob>...";
+
+#[derive(Serialize, Default)]
+struct Report {
+    /// The files that were imported
+    imported: Vec<PathBuf>,
+    /// The files that were already imported and haven't changed since
+    up_to_date: Vec<PathBuf>,
+    errors: Vec<ReportedError>,
+}
+
+#[derive(Serialize)]
+struct ReportedError {
+    path: Option<PathBuf>,
+    kind: &'static str,
+    message: String,
+}
+
+impl From<Error> for ReportedError {
+    fn from(err: Error) -> ReportedError {
+        ReportedError {
+            path: err.path().map(Path::to_owned),
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Imports external files into a project without opening a window, for instance in CI. Prints a JSON report of what was
+/// imported and of the errors, and exits with 1 if there were any.
+///
+/// For instance:
+/// `raven-import /project '$/models/**/*.fbx' '$/logo.png'`
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let (project_root, patterns) = match args.as_slice() {
+        [project_root, patterns @ ..] if !patterns.is_empty() => (Path::new(project_root), patterns),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut report = Report::default();
+
+    for pattern in patterns {
+        let paths = match expand(project_root, pattern) {
+            Ok(paths) => paths,
+            Err(err) => {
+                report.errors.push(err.into());
+                continue;
+            }
+        };
+
+        for path in paths {
+            match import::import(&path, project_root) {
+                Ok(true) => report.imported.push(path),
+                Ok(false) => report.up_to_date.push(path),
+                Err(err) => report.errors.push(err.into()),
+            }
+        }
+    }
+
+    // Like the editor does when it finds new assets, so that references to them can be made by GUID
+    let assigned = AssetDatabase::open(project_root).and_then(|mut db| db.assign_guids());
+    if let Err(err) = assigned {
+        report.errors.push(err.into());
+    }
+
+    println!("{}", serde_json::to_string_pretty(&report).expect("couldn't serialize report"));
+
+    if !report.errors.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// The `$/` paths of the external files matched by a `$/` path or glob. A glob only matches the files that can be
+/// imported, a path is returned as it is.
+fn expand(project_root: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let is_glob = pattern.contains(|c: char| matches!(c, '*' | '?' | '['));
+
+    if !is_glob {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+
+    let fs_pattern = path::as_fs_abs(project_root, pattern)?;
+
+    let fs_pattern = fs_pattern.to_str().ok_or_else(|| Error::import(pattern, "not a utf-8 path"))?;
+
+    let matches = glob::glob(fs_pattern).map_err(|err| Error::import(pattern, err))?;
+
+    let mut paths = Vec::new();
+
+    for fs_path in matches {
+        let fs_path = fs_path.map_err(|err| Error::from(err.into_error()))?;
+
+        match path::from_fs_abs(project_root, &fs_path) {
+            Some(path) if import::is_importable(&path) => paths.push(path),
+            _ => (),
+        }
+    }
+
+    if paths.is_empty() {
+        return Err(Error::import(pattern, "matches no file that can be imported"));
+    }
+
+    Ok(paths)
+}
@@ -0,0 +1,873 @@
+//! Imports glTF 2.0 files, `.gltf` or `.glb`, without Assimp.
+//!
+//! Nodes become entities, meshes become `.mesh` files, materials `.mat` files and the images they use `.tex` files, all
+//! saved in the import root. The joints of the skins become a single `.skel` and the animations of the joints `.anim`
+//! clips, played by an `AnimatorComponent` on the root entity. The animations of the other nodes become `.kanim` clips,
+//! played by `KeyframeAnimatorComponent`s on the root entity. Of the scene settings only the scale, the up axis and the
+//! normals apply, the others are steps of Assimp.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use gltf::animation::util::ReadOutputs;
+use gltf::animation::Interpolation as GltfInterpolation;
+use gltf::image::Format;
+use gltf::khr_lights_punctual::Kind;
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+use raven_core::animation::{Interpolate, Interpolation, Track};
+use raven_core::bake::{bake_texture, generate_lods, Normals, SceneSettings, TextureSettings};
+use raven_core::component::{CameraComponent, HierarchyComponent, LightComponent, LightKind, LodGroupComponent, MeshComponent, NameComponent, Projection, TransformComponent};
+use raven_core::database::{AssetDatabase, AssetRef};
+use raven_core::ecs::Entity;
+use raven_core::glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use raven_core::image::RgbaImage;
+use raven_core::io::Serializable;
+use raven_core::path as path_pkg;
+use raven_core::resource::{AnimatedProperty, AnimationClip, BlendMode, Filter, JointChannel, JointTransform, KeyframeChannel, KeyframeClip, Material, Mesh, PbrFactors, Scene, Skeleton, Vertex, VertexLayout, Wrap};
+use raven_core::{Error, Result};
+
+use super::{add_joints, collect_node_paths, ImportedNode};
+
+/// Used for perspective cameras with an infinite far plane, which cameras can't have.
+const FAR_PLANE: f32 = 1000.0;
+
+pub(super) fn import(
+    path: &Path,
+    project_root: &Path,
+    import_root: &Path,
+    settings: &SceneSettings,
+    db: &mut AssetDatabase,
+) -> Result<()> {
+    let fs_path = path_pkg::as_fs_abs(project_root, path)?;
+
+    let (document, buffers, images) = gltf::import(&fs_path).map_err(|err| Error::import(path, err))?;
+
+    let gltf_scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| Error::import(path, "no scene"))?;
+
+    let mut importer = GltfImporter {
+        path,
+        project_root,
+        import_root,
+        settings,
+        buffers: &buffers,
+        images: &images,
+        importing_scene: Scene::default(),
+        meshes: HashMap::new(),
+        materials: HashMap::new(),
+        textures: HashMap::new(),
+        joints: HashMap::new(),
+        db,
+    };
+
+    let skeleton = importer.import_skeleton(&document, &gltf_scene)?;
+
+    let mut node_paths = HashMap::new();
+    for node in gltf_scene.nodes() {
+        collect_node_paths(&node, &mut Vec::new(), &mut node_paths);
+    }
+
+    let mut clips = Vec::new();
+    let mut keyframe_clips = Vec::new();
+
+    for animation in document.animations() {
+        clips.extend(importer.import_clip(&animation)?);
+        keyframe_clips.extend(importer.import_keyframe_clip(&animation, &node_paths)?);
+    }
+
+    // A glTF scene can have many root nodes, they're put under a single entity that the settings are applied to
+    let root = importer.importing_scene.create();
+
+    let root_name = path.file_stem().map_or_else(|| "Root".to_owned(), |stem| stem.to_string_lossy().into_owned());
+    importer.importing_scene.attach(root, NameComponent(root_name));
+    importer.importing_scene.attach(root, TransformComponent(settings.root_transform()));
+
+    let mut children = Vec::new();
+    for node in gltf_scene.nodes() {
+        children.push(importer.import_node(&node, root)?);
+    }
+
+    importer.importing_scene.attach(root, HierarchyComponent { parent: None, children });
+
+    super::attach_animators(&mut importer.importing_scene, root, skeleton, clips, keyframe_clips);
+
+    let scene = std::mem::take(&mut importer.importing_scene);
+    importer.save_asset(&scene, import_root.join("main.scn"))?;
+
+    Ok(())
+}
+
+struct GltfImporter<'me> {
+    /// The external file being imported
+    path: &'me Path,
+    project_root: &'me Path,
+    import_root: &'me Path,
+    settings: &'me SceneSettings,
+    buffers: &'me [gltf::buffer::Data],
+    images: &'me [gltf::image::Data],
+    importing_scene: Scene,
+    // Assets already saved, because nodes can share meshes, and meshes materials and textures. Indexed by the glTF
+    // index of what they were made from
+    meshes: HashMap<(usize, usize, Option<usize>), (AssetRef, Vec<AssetRef>)>,
+    materials: HashMap<Option<usize>, AssetRef>,
+    textures: HashMap<usize, AssetRef>,
+    /// Index in the skeleton of the nodes that are joints
+    joints: HashMap<usize, usize>,
+    db: &'me mut AssetDatabase,
+}
+
+impl<'me> GltfImporter<'me> {
+    fn import_node(&mut self, node: &gltf::Node, parent: Entity) -> Result<Entity> {
+        let entity = self.importing_scene.create();
+
+        let name = node_name(node);
+
+        self.importing_scene.attach(entity, NameComponent(name.clone()));
+        self.importing_scene.attach(entity, TransformComponent(Mat4::from_cols_array_2d(&node.transform().matrix())));
+
+        let mut children = Vec::new();
+
+        if let Some(mesh) = node.mesh() {
+            let primitive_count = mesh.primitives().len();
+
+            for primitive in mesh.primitives() {
+                let (mesh_ref, lods) = self.import_primitive(&mesh, &primitive, node.skin().as_ref())?;
+                let mat = self.import_material(&primitive.material())?;
+
+                let mut mesh_comp = MeshComponent::new(mesh_ref, mat);
+                mesh_comp.lods = lods;
+
+                // An entity has a single mesh, primitives with their own material get an entity each
+                let mesh_entity = if primitive_count == 1 {
+                    entity
+                } else {
+                    let child = self.importing_scene.create();
+
+                    self.importing_scene.attach(child, NameComponent(format!("{} {}", name, primitive.index())));
+                    self.importing_scene.attach(child, TransformComponent::default());
+                    self.importing_scene.attach(child, HierarchyComponent { parent: Some(entity), children: Vec::new() });
+
+                    children.push(child);
+                    child
+                };
+
+                if !mesh_comp.lods.is_empty() {
+                    self.importing_scene.attach(mesh_entity, LodGroupComponent::with_levels(mesh_comp.lods.len()));
+                }
+
+                self.importing_scene.attach(mesh_entity, mesh_comp);
+            }
+        }
+
+        if let Some(camera) = node.camera() {
+            let projection = match camera.projection() {
+                gltf::camera::Projection::Perspective(perspective) => Projection::Perspective {
+                    fov: perspective.yfov().to_degrees(),
+                    near: perspective.znear(),
+                    far: perspective.zfar().unwrap_or(FAR_PLANE),
+                },
+                gltf::camera::Projection::Orthographic(orthographic) => Projection::Orthographic {
+                    height: orthographic.ymag() * 2.0,
+                    near: orthographic.znear(),
+                    far: orthographic.zfar(),
+                },
+            };
+
+            self.importing_scene.attach(entity, CameraComponent {
+                projection,
+                // Otherwise every scene the imported one is nested in would get another camera. They can be turned on
+                // from the inspector
+                active: false,
+                ..CameraComponent::default()
+            });
+        }
+
+        if let Some(light) = node.light() {
+            let kind = match light.kind() {
+                Kind::Directional => LightKind::Directional,
+                Kind::Point => LightKind::Point,
+                Kind::Spot { inner_cone_angle, outer_cone_angle } => LightKind::Spot {
+                    inner_cone: inner_cone_angle,
+                    outer_cone: outer_cone_angle,
+                },
+            };
+
+            self.importing_scene.attach(entity, LightComponent {
+                kind,
+                color: light.color(),
+                intensity: light.intensity(),
+                range: light.range(),
+            });
+        }
+
+        for child in node.children() {
+            children.push(self.import_node(&child, entity)?);
+        }
+
+        self.importing_scene.attach(entity, HierarchyComponent { parent: Some(parent), children });
+
+        Ok(entity)
+    }
+
+    /// Gathers the joints of every skin into a single skeleton, along with their ancestors so that they're posed relative
+    /// to the root of the scene.
+    fn import_skeleton(&mut self, document: &gltf::Document, gltf_scene: &gltf::Scene) -> Result<Option<AssetRef>> {
+        let buffers = self.buffers;
+
+        let mut inverse_binds = HashMap::new();
+
+        for skin in document.skins() {
+            let reader = skin.reader(|buffer| Some(&*buffers[buffer.index()]));
+
+            // Identities when missing
+            let mats: Vec<Mat4> = reader
+                .read_inverse_bind_matrices()
+                .map_or_else(Vec::new, |mats| mats.map(|mat| Mat4::from_cols_array_2d(&mat)).collect());
+
+            // A node that is a joint of many skins is usually bound the same way in all of them
+            for (i, joint) in skin.joints().enumerate() {
+                inverse_binds.entry(joint.index()).or_insert_with(|| mats.get(i).copied().unwrap_or(Mat4::IDENTITY));
+            }
+        }
+
+        if inverse_binds.is_empty() {
+            return Ok(None);
+        }
+
+        let mut parents = HashMap::new();
+        for node in document.nodes() {
+            for child in node.children() {
+                parents.insert(child.index(), node.index());
+            }
+        }
+
+        let mut included = HashSet::new();
+        for &joint in inverse_binds.keys() {
+            let mut node = Some(joint);
+
+            while let Some(index) = node {
+                // Its ancestors are already in
+                if !included.insert(index) {
+                    break;
+                }
+
+                node = parents.get(&index).copied();
+            }
+        }
+
+        let mut skeleton = Skeleton::default();
+
+        for node in gltf_scene.nodes() {
+            add_joints(&node, None, &included, &inverse_binds, &mut skeleton, &mut self.joints);
+        }
+
+        Ok(Some(super::save_skeleton(self.path, &skeleton, self.import_root, self.project_root, self.db)?))
+    }
+
+    /// The animations of the joints become a skeletal clip, the ones of the other nodes a keyframe clip.
+    fn import_clip(&mut self, animation: &gltf::Animation) -> Result<Option<AssetRef>> {
+        let buffers = self.buffers;
+
+        // By joint, so that clips list them in the order of the skeleton
+        let mut channels: BTreeMap<usize, JointChannel> = BTreeMap::new();
+        let mut duration = 0.0_f32;
+
+        for channel in animation.channels() {
+            let node = channel.target().node();
+
+            let joint = match self.joints.get(&node.index()) {
+                Some(&joint) => joint,
+                None => continue,
+            };
+
+            let (end, property) = match read_channel(&channel, buffers) {
+                Some(read) => read,
+                None => continue,
+            };
+
+            duration = duration.max(end);
+
+            let joint_channel = channels.entry(joint).or_insert_with(|| JointChannel {
+                joint: node_name(&node),
+                ..JointChannel::default()
+            });
+
+            match property {
+                AnimatedProperty::Translation(track) => joint_channel.translation = track,
+                AnimatedProperty::Rotation(track) => joint_channel.rotation = track,
+                AnimatedProperty::Scale(track) => joint_channel.scale = track,
+                AnimatedProperty::Field(..) => (),
+            }
+        }
+
+        if channels.is_empty() {
+            return Ok(None);
+        }
+
+        let clip = AnimationClip {
+            duration,
+            channels: channels.into_iter().map(|(_, channel)| channel).collect(),
+        };
+
+        let clip_path = self.import_root.join(format!("animation{}.{}", animation.index(), AnimationClip::EXTENSION));
+
+        Ok(Some(self.save_asset(&clip, clip_path)?))
+    }
+
+    /// Rigid-body animation of the nodes that aren't joints, played on the root entity.
+    fn import_keyframe_clip(
+        &mut self,
+        animation: &gltf::Animation,
+        node_paths: &HashMap<usize, Vec<String>>,
+    ) -> Result<Option<AssetRef>> {
+        let mut channels = Vec::new();
+        let mut duration = 0.0_f32;
+
+        for channel in animation.channels() {
+            let node = channel.target().node();
+
+            let target = match node_paths.get(&node.index()) {
+                Some(target) if !self.joints.contains_key(&node.index()) => target,
+                _ => continue,
+            };
+
+            if let Some((end, property)) = read_channel(&channel, self.buffers) {
+                duration = duration.max(end);
+                channels.push(KeyframeChannel { target: target.clone(), property });
+            }
+        }
+
+        if channels.is_empty() {
+            return Ok(None);
+        }
+
+        let clip = KeyframeClip { duration, channels };
+
+        let clip_path = self.import_root.join(format!("animation{}.{}", animation.index(), KeyframeClip::EXTENSION));
+
+        Ok(Some(self.save_asset(&clip, clip_path)?))
+    }
+
+    /// Saves the mesh made from a primitive, returning a reference to it and to the LODs generated for it.
+    fn import_primitive(
+        &mut self,
+        mesh: &gltf::Mesh,
+        primitive: &gltf::Primitive,
+        skin: Option<&gltf::Skin>,
+    ) -> Result<(AssetRef, Vec<AssetRef>)> {
+        let key = (mesh.index(), primitive.index(), skin.map(gltf::Skin::index));
+
+        if let Some(refs) = self.meshes.get(&key) {
+            return Ok(refs.clone());
+        }
+
+        if primitive.mode() != Mode::Triangles {
+            return Err(Error::import(self.path, format!("mesh {} isn't made of triangles", mesh.index())));
+        }
+
+        let buffers = self.buffers;
+        let reader = primitive.reader(|buffer| Some(&*buffers[buffer.index()]));
+
+        let positions: Vec<Vec3> = reader
+            .read_positions()
+            .ok_or_else(|| Error::import(self.path, format!("mesh {} has no positions", mesh.index())))?
+            .map(Vec3::from)
+            .collect();
+
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        if let Some(&index) = indices.iter().find(|&&index| index as usize >= positions.len()) {
+            return Err(Error::import(self.path, format!(
+                "mesh {} has the index {}, but only {} vertices",
+                mesh.index(),
+                index,
+                positions.len(),
+            )));
+        }
+
+        let mut vertices: Vec<Vertex> = positions
+            .iter()
+            .map(|&position| Vertex::new(position, Vec3::ZERO, Vec2::ZERO))
+            .collect();
+
+        // Every attribute but the position is optional, meshes without UVs can only be drawn untextured
+        let mut layout = VertexLayout { uv: false, ..VertexLayout::default() };
+
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            layout.uv = true;
+
+            for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                vertex.uv = Vec2::from(uv);
+            }
+        }
+
+        if let Some(uvs2) = reader.read_tex_coords(1) {
+            layout.uv2 = true;
+
+            for (vertex, uv2) in vertices.iter_mut().zip(uvs2.into_f32()) {
+                vertex.uv2 = Vec2::from(uv2);
+            }
+        }
+
+        if let Some(colors) = reader.read_colors(0) {
+            layout.colors = true;
+
+            for (vertex, color) in vertices.iter_mut().zip(colors.into_rgba_f32()) {
+                vertex.color = Vec4::from(color);
+            }
+        }
+
+        let tangents: Option<Vec<Vec4>> = reader.read_tangents().map(|tangents| tangents.map(Vec4::from).collect());
+
+        let joints = reader.read_joints(0).map(|joints| joints.into_u16().collect::<Vec<_>>());
+        let weights = reader.read_weights(0).map(|weights| weights.into_f32().collect::<Vec<_>>());
+
+        if let (Some(skin), Some(joints), Some(weights)) = (skin, joints, weights) {
+            // Vertices refer to the joints of their skin, which are somewhere else in the skeleton
+            let skin_joints = skin
+                .joints()
+                .map(|joint| self.joints.get(&joint.index()).map(|&joint| joint as u16))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| Error::import(self.path, format!("skin {} has joints outside of the scene", skin.index())))?;
+
+            for ((vertex, joints), weights) in vertices.iter_mut().zip(joints).zip(weights) {
+                let total: f32 = weights.iter().sum();
+
+                if total <= 0.0 {
+                    continue;
+                }
+
+                vertex.joints = joints.map(|joint| skin_joints.get(joint as usize).copied().unwrap_or(0));
+                vertex.weights = weights.map(|weight| weight / total);
+            }
+
+            layout.skin = true;
+        }
+
+        let normals: Option<Vec<Vec3>> = reader.read_normals().map(|normals| normals.map(Vec3::from).collect());
+
+        let normals = match (self.settings.normals, normals) {
+            (Normals::Import, Some(normals)) => Some(normals),
+            (Normals::Import, None) | (Normals::Smooth, _) => Some(smooth_normals(&positions, &indices)),
+            (Normals::Flat, _) => None,
+        };
+
+        let imported_mesh = match normals {
+            Some(normals) => {
+                for (vertex, &normal) in vertices.iter_mut().zip(&normals) {
+                    vertex.normal = normal;
+                }
+
+                // They're only meaningful along the normals they were made for
+                if let (Normals::Import, Some(tangents)) = (self.settings.normals, tangents) {
+                    layout.tangents = true;
+
+                    for (vertex, tangent) in vertices.iter_mut().zip(tangents) {
+                        vertex.tangent = tangent.truncate();
+                        // The sign tells which way the bitangent points
+                        vertex.bitangent = vertex.normal.cross(vertex.tangent) * tangent.w;
+                    }
+                }
+
+                Mesh::with_layout(vertices, indices, layout)
+            }
+            None => flat_mesh(&vertices, &indices, layout),
+        };
+
+        let mesh_name = match skin {
+            Some(skin) => format!("mesh{}_{}_skin{}", mesh.index(), primitive.index(), skin.index()),
+            None => format!("mesh{}_{}", mesh.index(), primitive.index()),
+        };
+
+        let mesh_ref = self.save_asset(&imported_mesh, self.import_root.join(format!("{}.mesh", mesh_name)))?;
+
+        let mut lods = Vec::new();
+
+        for (i, lod) in generate_lods(&imported_mesh, self.settings.lod_levels).into_iter().enumerate() {
+            lods.push(self.save_asset(&lod, self.import_root.join(format!("{}_lod{}.mesh", mesh_name, i + 1)))?);
+        }
+
+        self.meshes.insert(key, (mesh_ref.clone(), lods.clone()));
+
+        Ok((mesh_ref, lods))
+    }
+
+    fn import_material(&mut self, material: &gltf::Material) -> Result<AssetRef> {
+        if let Some(mat) = self.materials.get(&material.index()) {
+            return Ok(mat.clone());
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+
+        let tex = match pbr.base_color_texture() {
+            Some(info) => Some(self.import_texture(&info.texture())?),
+            None => None,
+        };
+
+        let blend = match material.alpha_mode() {
+            AlphaMode::Opaque => BlendMode::Opaque,
+            AlphaMode::Mask => BlendMode::AlphaTest { cutoff: material.alpha_cutoff().unwrap_or(0.5) },
+            AlphaMode::Blend => BlendMode::AlphaBlend,
+        };
+
+        let imported_mat = Material {
+            tex,
+            blend,
+            color: Some(pbr.base_color_factor()),
+            pbr: Some(PbrFactors {
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                emissive: material.emissive_factor(),
+            }),
+        };
+
+        let mat_file = match material.index() {
+            Some(index) => format!("material{}.mat", index),
+            None => "default.mat".to_owned(),
+        };
+
+        let mat = self.save_asset(&imported_mat, self.import_root.join(mat_file))?;
+
+        self.materials.insert(material.index(), mat.clone());
+
+        Ok(mat)
+    }
+
+    fn import_texture(&mut self, texture: &gltf::Texture) -> Result<AssetRef> {
+        if let Some(tex) = self.textures.get(&texture.index()) {
+            return Ok(tex.clone());
+        }
+
+        let image = to_rgba8(&self.images[texture.source().index()])
+            .ok_or_else(|| Error::import(self.path, format!("image {} has an invalid size", texture.source().index())))?;
+
+        let mut settings = TextureSettings::default();
+
+        let sampler = texture.sampler();
+
+        settings.sampler.wrap = match sampler.wrap_s() {
+            WrappingMode::Repeat => Wrap::Repeat,
+            WrappingMode::MirroredRepeat => Wrap::MirroredRepeat,
+            WrappingMode::ClampToEdge => Wrap::ClampToEdge,
+        };
+
+        if let Some(mag_filter) = sampler.mag_filter() {
+            settings.sampler.mag_filter = match mag_filter {
+                MagFilter::Nearest => Filter::Nearest,
+                MagFilter::Linear => Filter::Linear,
+            };
+        }
+
+        if let Some(min_filter) = sampler.min_filter() {
+            let (filter, mipmaps) = match min_filter {
+                MinFilter::Nearest => (Filter::Nearest, false),
+                MinFilter::Linear => (Filter::Linear, false),
+                MinFilter::NearestMipmapNearest | MinFilter::NearestMipmapLinear => (Filter::Nearest, true),
+                MinFilter::LinearMipmapNearest | MinFilter::LinearMipmapLinear => (Filter::Linear, true),
+            };
+
+            settings.sampler.min_filter = filter;
+            settings.sampler.mipmaps = mipmaps;
+        }
+
+        let tex_path = self.import_root.join(format!("texture{}.tex", texture.index()));
+        let tex = self.save_asset(&bake_texture(&image, &settings), tex_path)?;
+
+        self.textures.insert(texture.index(), tex.clone());
+
+        Ok(tex)
+    }
+
+    fn save_asset<T: Serializable>(&mut self, asset: &T, asset_path: PathBuf) -> Result<AssetRef> {
+        super::save_asset(asset, asset_path, self.project_root, self.db)
+    }
+}
+
+fn node_name(node: &gltf::Node) -> String {
+    node.name().map_or_else(|| format!("Node {}", node.index()), str::to_owned)
+}
+
+impl ImportedNode for gltf::Node<'_> {
+    // Nodes are referred to by index, from skins and animation channels
+    type Key = usize;
+
+    fn key(&self) -> usize {
+        self.index()
+    }
+
+    fn name(&self) -> String {
+        node_name(self)
+    }
+
+    fn rest(&self) -> JointTransform {
+        let (translation, [x, y, z, w], scale) = self.transform().decomposed();
+
+        JointTransform {
+            translation: Vec3::from(translation),
+            rotation: Quat::from_xyzw(x, y, z, w),
+            scale: Vec3::from(scale),
+        }
+    }
+
+    fn for_each_child(&self, f: &mut dyn FnMut(&Self)) {
+        for child in self.children() {
+            f(&child);
+        }
+    }
+}
+
+/// The keyframes of a channel and the time of its last one. `None` for morph target weights, which aren't supported.
+fn read_channel(channel: &gltf::animation::Channel, buffers: &[gltf::buffer::Data]) -> Option<(f32, AnimatedProperty)> {
+    let reader = channel.reader(|buffer| Some(&*buffers[buffer.index()]));
+
+    let times: Vec<f32> = reader.read_inputs()?.collect();
+    let end = times.iter().copied().fold(0.0, f32::max);
+
+    let interpolation = channel.sampler().interpolation();
+
+    let property = match reader.read_outputs()? {
+        ReadOutputs::Translations(values) => {
+            AnimatedProperty::Translation(to_track(times, values.map(Vec3::from).collect(), interpolation))
+        }
+        ReadOutputs::Rotations(values) => {
+            let values = values.into_f32().map(|[x, y, z, w]| Quat::from_xyzw(x, y, z, w)).collect();
+            AnimatedProperty::Rotation(to_track(times, values, interpolation))
+        }
+        ReadOutputs::Scales(values) => {
+            AnimatedProperty::Scale(to_track(times, values.map(Vec3::from).collect(), interpolation))
+        }
+        ReadOutputs::MorphTargetWeights(_) => return None,
+    };
+
+    Some((end, property))
+}
+
+/// Cubic spline keyframes have tangents around every value, only the values are kept and the tangents are worked out
+/// from the keyframes around them.
+fn to_track<T: Interpolate>(times: Vec<f32>, values: Vec<T>, interpolation: GltfInterpolation) -> Track<T> {
+    match interpolation {
+        GltfInterpolation::Linear => Track::new(times, values, Interpolation::Linear),
+        GltfInterpolation::Step => Track::new(times, values, Interpolation::Step),
+        GltfInterpolation::CubicSpline => {
+            Track::new(times, values.into_iter().skip(1).step_by(3).collect(), Interpolation::Cubic)
+        }
+    }
+}
+
+/// Normals averaged between the triangles sharing a vertex, weighted by their area.
+fn smooth_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+
+        // Not normalized, so that bigger triangles count more
+        let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+
+        normals[a] += normal;
+        normals[b] += normal;
+        normals[c] += normal;
+    }
+
+    normals.into_iter().map(Vec3::normalize_or_zero).collect()
+}
+
+/// A mesh where every triangle has its own vertices, with the normal of the triangle.
+fn flat_mesh(shared: &[Vertex], indices: &[u32], layout: VertexLayout) -> Mesh {
+    let mut vertices = Vec::with_capacity(indices.len());
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [&shared[triangle[0] as usize], &shared[triangle[1] as usize], &shared[triangle[2] as usize]];
+
+        let normal = (b.position - a.position).cross(c.position - a.position).normalize_or_zero();
+
+        for vertex in [a, b, c] {
+            vertices.push(Vertex { normal, ..*vertex });
+        }
+    }
+
+    let indices = (0..vertices.len() as u32).collect();
+
+    Mesh::with_layout(vertices, indices, layout)
+}
+
+/// Converts the decoded pixels of an image to 8 bits RGBA. 16 bits channels keep their most significant byte.
+fn to_rgba8(image: &gltf::image::Data) -> Option<RgbaImage> {
+    let pixels = &image.pixels;
+
+    let rgba: Vec<u8> = match image.format {
+        Format::R8 => pixels.iter().flat_map(|&r| vec![r, r, r, 255]).collect(),
+        Format::R8G8 => pixels.chunks_exact(2).flat_map(|p| vec![p[0], p[1], 0, 255]).collect(),
+        Format::R8G8B8 => pixels.chunks_exact(3).flat_map(|p| vec![p[0], p[1], p[2], 255]).collect(),
+        Format::R8G8B8A8 => pixels.clone(),
+        Format::B8G8R8 => pixels.chunks_exact(3).flat_map(|p| vec![p[2], p[1], p[0], 255]).collect(),
+        Format::B8G8R8A8 => pixels.chunks_exact(4).flat_map(|p| vec![p[2], p[1], p[0], p[3]]).collect(),
+        // Little endian, the most significant byte comes second
+        Format::R16 => pixels.chunks_exact(2).flat_map(|p| vec![p[1], p[1], p[1], 255]).collect(),
+        Format::R16G16 => pixels.chunks_exact(4).flat_map(|p| vec![p[1], p[3], 0, 255]).collect(),
+        Format::R16G16B16 => pixels.chunks_exact(6).flat_map(|p| vec![p[1], p[3], p[5], 255]).collect(),
+        Format::R16G16B16A16 => pixels.chunks_exact(8).flat_map(|p| vec![p[1], p[3], p[5], p[7]]).collect(),
+    };
+
+    RgbaImage::from_raw(image.width, image.height, rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use raven_core::component::{HierarchyComponent, MeshComponent, NameComponent};
+    use raven_core::ecs::{Entity, Query};
+    use raven_core::io::Serializable;
+    use raven_core::path as path_pkg;
+    use raven_core::resource::{Material, Mesh, Scene, Skeleton, Texture};
+
+    // A triangle skinned to a chain of two joints, with a 2x2 texture. The buffer holds the positions, the UVs, the
+    // joints as bytes and the weights of the three vertices, which don't add up to 1 for the last two
+    const RIG_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 1] }],
+        "nodes": [
+            { "name": "Body", "mesh": 0, "skin": 0 },
+            { "name": "Root", "children": [2] },
+            { "name": "Tip", "translation": [0, 1, 0] }
+        ],
+        "skins": [{ "joints": [1, 2] }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "TEXCOORD_0": 1, "JOINTS_0": 2, "WEIGHTS_0": 3 },
+                "material": 0
+            }]
+        }],
+        "materials": [{ "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } } }],
+        "textures": [{ "source": 0 }],
+        "images": [{
+            "uri": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAYAAABytg0kAAAAEklEQVR42mP4z8DwHwyBNBgAAEnICfcD2WTxAAAAAElFTkSuQmCC"
+        }],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+            { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2" },
+            { "bufferView": 2, "componentType": 5121, "count": 3, "type": "VEC4" },
+            { "bufferView": 3, "componentType": 5126, "count": 3, "type": "VEC4" }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 24 },
+            { "buffer": 0, "byteOffset": 60, "byteLength": 12 },
+            { "buffer": 0, "byteOffset": 72, "byteLength": 48 }
+        ],
+        "buffers": [{
+            "byteLength": 120,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/AAEAAAABAAAAAQAAAACAPwAAAAAAAAAAAAAAAAAAAEAAAABAAAAAAAAAAAAAAEBAAACAPwAAAAAAAAAA"
+        }]
+    }"#;
+
+    #[test]
+    fn imports_nodes_skins_and_textures() {
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join("rig.gltf"), RIG_GLTF).unwrap();
+
+        assert!(super::super::import(Path::new("$/rig.gltf"), project.path()).unwrap());
+
+        let load = |asset_path: &Path| path_pkg::as_fs_abs(project.path(), asset_path).unwrap();
+        let scene = Scene::load(load(Path::new("$/.import/rig.gltf/main.scn"))).unwrap();
+
+        // The root nodes of the glTF scene end up under a single entity named after the file
+        let root = find(&scene, "rig");
+        let (body, joint_root, tip) = (find(&scene, "Body"), find(&scene, "Root"), find(&scene, "Tip"));
+
+        assert_eq!(scene.get_one::<HierarchyComponent>(root).unwrap().children, vec![body, joint_root]);
+        assert_eq!(scene.get_one::<HierarchyComponent>(joint_root).unwrap().children, vec![tip]);
+        assert_eq!(scene.get_one::<HierarchyComponent>(tip).unwrap().parent, Some(joint_root));
+
+        let mesh_comp = scene.get_one::<MeshComponent>(body).unwrap();
+        assert!(mesh_comp.mesh.guid.is_some() && mesh_comp.mat.guid.is_some());
+
+        let skeleton = Skeleton::load(load(Path::new("$/.import/rig.gltf/skeleton.skel"))).unwrap();
+        let names: Vec<_> = skeleton.joints.iter().map(|joint| joint.name.as_str()).collect();
+        assert_eq!(names, ["Root", "Tip"]);
+        assert_eq!(skeleton.joints[1].parent, Some(0));
+
+        let mesh = Mesh::load(load(&mesh_comp.mesh.path)).unwrap();
+        assert!(mesh.layout.skin);
+
+        let weights: Vec<_> = mesh.vertices.iter().map(|vertex| vertex.weights).collect();
+        assert_eq!(weights, [[1.0, 0.0, 0.0, 0.0], [0.5, 0.5, 0.0, 0.0], [0.75, 0.25, 0.0, 0.0]]);
+        assert_eq!(mesh.vertices[1].joints, [0, 1, 0, 0]);
+
+        let mat = Material::load(load(&mesh_comp.mat.path)).unwrap();
+        let tex = Texture::load(load(&mat.tex.unwrap().path)).unwrap();
+        assert_eq!(tex.size, [2, 2]);
+    }
+
+    #[test]
+    fn rejects_skeletons_with_too_many_joints() {
+        let count = Skeleton::MAX_JOINTS + 1;
+
+        // A chain of joints, each the child of the one before
+        let nodes: Vec<_> = (0..count)
+            .map(|i| {
+                let children = if i + 1 < count { vec![i + 1] } else { vec![] };
+                format!(r#"{{ "name": "Joint{}", "children": {:?} }}"#, i, children)
+            })
+            .collect();
+
+        let gltf = format!(
+            r#"{{ "asset": {{ "version": "2.0" }}, "scenes": [{{ "nodes": [0] }}], "nodes": [{}], "skins": [{}] }}"#,
+            nodes.join(", "),
+            format!(r#"{{ "joints": {:?} }}"#, (0..count).collect::<Vec<_>>()),
+        );
+
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join("chain.gltf"), gltf).unwrap();
+
+        let err = super::super::import(Path::new("$/chain.gltf"), project.path()).unwrap_err();
+        assert!(err.to_string().contains(&format!("more than {}", Skeleton::MAX_JOINTS)), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn rejects_indices_past_the_vertices() {
+        // A triangle whose last index is past its three vertices, all in the origin
+        let gltf = r#"{
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [0, 0, 0] },
+                { "bufferView": 1, "componentType": 5121, "count": 3, "type": "SCALAR" }
+            ],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 3 }
+            ],
+            "buffers": [{
+                "byteLength": 40,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAEFAA=="
+            }]
+        }"#;
+
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join("broken.gltf"), gltf).unwrap();
+
+        let err = super::super::import(Path::new("$/broken.gltf"), project.path()).unwrap_err();
+        assert!(err.to_string().contains("has the index 5, but only 3 vertices"), "unexpected error: {}", err);
+    }
+
+    fn find(scene: &Scene, name: &str) -> Entity {
+        <(NameComponent, )>::query_shallow(scene)
+            .find(|(_, (name_comp, ), _)| name_comp.0 == name)
+            .map(|(entity, _, _)| entity)
+            .unwrap_or_else(|| panic!("no entity named {}", name))
+    }
+}
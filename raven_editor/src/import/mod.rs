@@ -0,0 +1,1098 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use itertools::izip;
+use md5::{Digest, Md5};
+
+use raven_core::animation::{Interpolation, Track};
+use raven_core::bake::{bake_texture, generate_lods, ImportSettings, Normals, SceneSettings, TextureSettings};
+use raven_core::component::{
+    AnimationLayer, AnimatorComponent, HierarchyComponent, KeyframeAnimatorComponent, LodGroupComponent, MeshComponent,
+    NameComponent, TransformComponent,
+};
+use raven_core::database::{AssetDatabase, AssetRef, Guid, Meta};
+use raven_core::ecs::Entity;
+use raven_core::glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use raven_core::io::Serializable;
+use raven_core::manifest::{ImportManifest, ImportRecord};
+use raven_core::path as path_pkg;
+use raven_core::resource::{
+    AnimatedProperty, AnimationClip, BlendMode, Joint, JointChannel, JointTransform, KeyframeChannel, KeyframeClip,
+    Material, Mesh, Scene, Skeleton, Vertex, VertexLayout,
+};
+use raven_core::{Error, Result};
+
+mod assimp {
+    pub use russimp::animation::{Animation, NodeAnim};
+    pub use russimp::material::Material;
+    pub use russimp::material::PropertyTypeInfo;
+    pub use russimp::mesh::Mesh;
+    pub use russimp::node::Node;
+    pub use russimp::scene::{PostProcess, Scene};
+    pub use russimp::texture::TextureType;
+    pub use russimp::Matrix4x4;
+}
+
+mod gltf;
+
+/// Bumped whenever the importers change what they produce, so that everything imported by an older version is imported
+/// again.
+const IMPORTER_VERSION: u32 = 5;
+
+/// What an external file is imported as.
+#[derive(Clone, Copy)]
+enum ImportKind {
+    Texture,
+    /// Through Assimp
+    Scene,
+    /// glTF 2.0, read natively
+    Gltf,
+}
+
+impl ImportKind {
+    fn of(path: &Path) -> Option<ImportKind> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png" | "jpg" | "jpeg") => Some(ImportKind::Texture),
+            Some("fbx" | "obj") => Some(ImportKind::Scene),
+            Some("gltf" | "glb") => Some(ImportKind::Gltf),
+            _ => None,
+        }
+    }
+
+    fn default_settings(self) -> ImportSettings {
+        match self {
+            ImportKind::Texture => ImportSettings::Texture(TextureSettings::default()),
+            ImportKind::Scene => ImportSettings::Scene(SceneSettings::default()),
+            // glTF is always in meters
+            ImportKind::Gltf => ImportSettings::Scene(SceneSettings { scale: 1.0, ..SceneSettings::default() }),
+        }
+    }
+}
+
+/// The settings the external file at the given path is imported with: the ones in its meta file, or the defaults if it
+/// has none.
+pub fn import_settings(path: &Path, project_root: &Path) -> Result<ImportSettings> {
+    let kind = ImportKind::of(path).ok_or_else(|| Error::import(path, "unknown extension"))?;
+
+    let fs_meta_path = Meta::path_for(path_pkg::as_fs_abs(project_root, path)?);
+
+    let settings = if fs_meta_path.is_file() {
+        Meta::load(&fs_meta_path)?.import
+    } else {
+        None
+    };
+
+    // Settings for another kind of file are left over from a file that was renamed
+    Ok(match (kind, settings) {
+        (ImportKind::Texture, Some(settings @ ImportSettings::Texture(_))) => settings,
+        (ImportKind::Scene | ImportKind::Gltf, Some(settings @ ImportSettings::Scene(_))) => settings,
+        (kind, _) => kind.default_settings(),
+    })
+}
+
+/// Saves the settings of the external file at the given path in its meta file. They're used the next time it's imported.
+pub fn set_import_settings(path: &Path, project_root: &Path, settings: ImportSettings) -> Result<()> {
+    let fs_meta_path = Meta::path_for(path_pkg::as_fs_abs(project_root, path)?);
+
+    let mut meta = if fs_meta_path.is_file() {
+        Meta::load(&fs_meta_path)?
+    } else {
+        Meta { guid: Guid::generate(), import: None }
+    };
+
+    meta.import = Some(settings);
+    meta.save(&fs_meta_path)?;
+
+    Ok(())
+}
+
+/// Imports the external file at the given path, unless it was already imported from the same contents, with the same
+/// settings and by the same version of the importer. Returns whether it was imported.
+pub fn import(path: &Path, project_root: &Path) -> Result<bool> {
+    if !path_pkg::is_valid(path) {
+        return Err(Error::InvalidPath(path.to_owned()));
+    }
+
+    if path.extension().is_none() {
+        return Err(Error::import(path, "no extension"));
+    }
+
+    let kind = ImportKind::of(path).ok_or_else(|| Error::import(path, "unknown extension"))?;
+    let settings = import_settings(path, project_root)?;
+
+    let fs_path = path_pkg::as_fs_abs(project_root, path)?;
+    let record = ImportRecord::new(&fs_path, &settings, IMPORTER_VERSION)?;
+
+    let mut manifest = ImportManifest::open(project_root)?;
+    // Imported assets get GUIDs as they're saved, so that the references between them have them
+    let mut db = AssetDatabase::open(project_root)?;
+
+    // The imported files could have been deleted by hand
+    let fs_import_root = path_pkg::as_fs_abs(project_root, as_import_root(path)?)?;
+
+    if manifest.is_up_to_date(path, &record) && fs_import_root.is_dir() {
+        return Ok(false);
+    }
+
+    match (kind, &settings) {
+        (ImportKind::Gltf, ImportSettings::Scene(settings)) => {
+            let import_root = prepare_import_root_for(path, project_root)?;
+            gltf::import(path, project_root, &import_root, settings, &mut db)
+        }
+        (_, ImportSettings::Texture(settings)) => import_tex(path, project_root, settings, &mut db),
+        (_, ImportSettings::Scene(settings)) => SceneImporter::import(path, project_root, settings, &mut db),
+    }?;
+
+    manifest.insert(path.to_owned(), record);
+    manifest.save_to(project_root)?;
+
+    Ok(true)
+}
+
+/// Imports again the external files that were imported before and changed since, or whose settings or importer did.
+/// Returns the `$/` paths of the files that were imported.
+pub fn reimport_out_of_date(project_root: &Path) -> Result<Vec<PathBuf>> {
+    let mut manifest = ImportManifest::open(project_root)?;
+
+    // Forget about the external files that were removed
+    let removed: Vec<_> = manifest
+        .sources()
+        .filter(|source| !matches!(path_pkg::as_fs_abs(project_root, source), Ok(fs_source) if fs_source.is_file()))
+        .map(Path::to_owned)
+        .collect();
+
+    if !removed.is_empty() {
+        for source in &removed {
+            manifest.remove(source);
+        }
+
+        manifest.save_to(project_root)?;
+    }
+
+    let mut pattern = project_root.to_owned();
+    pattern.push("**");
+    pattern.push("*");
+
+    let mut imported = Vec::new();
+
+    let fs_pattern = pattern.to_str().ok_or_else(|| Error::import(&pattern, "not a utf-8 path"))?;
+
+    for fs_path in glob::glob(fs_pattern).map_err(|err| Error::import(&pattern, err))? {
+        let fs_path = fs_path.map_err(|err| Error::from(err.into_error()))?;
+
+        let path = match path_pkg::from_fs_abs(project_root, &fs_path) {
+            Some(path) => path,
+            None => continue,
+        };
+
+        // Leaves out the import directory and the ones of version control
+        if path.components().any(|comp| comp.as_os_str().to_string_lossy().starts_with('.')) {
+            continue;
+        }
+
+        if was_imported(&path, project_root, &manifest) && import(&path, project_root)? {
+            imported.push(path);
+        }
+    }
+
+    Ok(imported)
+}
+
+/// Whether the file at the given path is an external file that can be imported.
+pub fn is_importable(path: &Path) -> bool {
+    ImportKind::of(path).is_some()
+}
+
+/// Whether the external file at the given path was imported before, in which case it should be imported again when it
+/// changes.
+pub fn is_imported(path: &Path, project_root: &Path) -> bool {
+    matches!(ImportManifest::open(project_root), Ok(manifest) if was_imported(path, project_root, &manifest))
+}
+
+fn was_imported(path: &Path, project_root: &Path, manifest: &ImportManifest) -> bool {
+    if ImportKind::of(path).is_none() {
+        return false;
+    }
+
+    // Files imported before there was a manifest only have their import root
+    let import_root = as_import_root(path).and_then(|import_root| path_pkg::as_fs_abs(project_root, import_root));
+
+    manifest.get(path).is_some() || matches!(import_root, Ok(import_root) if import_root.is_dir())
+}
+
+/// Given the absolute path to an asset, returns the path to the root directory for the imported files.
+///
+/// For instance:
+/// `$/ferris/ferris.fbx` becomes `$/.import/ferris/ferris.fbx`
+fn as_import_root(path: &Path) -> Result<PathBuf> {
+    let mut import_root = PathBuf::default();
+    import_root.push(path_pkg::PROJECT_ROOT_RUNE);
+    import_root.push(path_pkg::IMPORT_DIR);
+    import_root.push(path_pkg::strip_rune(path)?);
+
+    Ok(import_root)
+}
+
+fn prepare_import_root_for(path: &Path, project_root: &Path) -> Result<PathBuf> {
+    let import_root = as_import_root(path)?;
+    let fs_import_root = path_pkg::as_fs_abs(project_root, &import_root)?;
+
+    fs::create_dir_all(&fs_import_root)?;
+
+    // Make sure the import directory contains no asset, but keep their meta files so that reimported assets keep their
+    // GUIDs
+    wipe_assets(&fs_import_root).map_err(|err| Error::import(path, err))?;
+
+    Ok(import_root)
+}
+
+fn wipe_assets(path: &Path) -> Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_type()?.is_dir() {
+            wipe_assets(&path)?;
+        } else if !Meta::is_meta(&path) {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn import_tex(path: &Path, project_root: &Path, settings: &TextureSettings, db: &mut AssetDatabase) -> Result<()> {
+    let import_root = prepare_import_root_for(path, project_root)?;
+
+    let tex = image::open(path_pkg::as_fs_abs(project_root, path)?)
+        .map_err(|err| Error::import(path, err))?;
+
+    let tex = bake_texture(&tex.into_rgba8(), settings);
+
+    save_asset(&tex, import_root.join("main.tex"), project_root, db)?;
+
+    Ok(())
+}
+
+/// Saves an imported asset at the given `$/` path and gives it a GUID, the one it had before if it was imported
+/// already. Returns a reference to it.
+fn save_asset<T: Serializable>(
+    asset: &T,
+    asset_path: PathBuf,
+    project_root: &Path,
+    db: &mut AssetDatabase,
+) -> Result<AssetRef> {
+    asset.save(path_pkg::as_fs_abs(project_root, &asset_path)?)?;
+    db.assign_guid(&asset_path)?;
+
+    Ok(db.reference(asset_path))
+}
+
+struct SceneImporter<'me> {
+    /// The external file being imported
+    path: &'me Path,
+    import_root: &'me Path,
+    settings: &'me SceneSettings,
+    scene: &'me assimp::Scene,
+    importing_scene: Scene,
+    /// Index in the skeleton of the nodes that are joints, by name since that's how bones refer to them
+    joints: HashMap<String, usize>,
+    db: &'me mut AssetDatabase,
+}
+
+struct NodeTraversal(Vec<String>);
+
+impl NodeTraversal {
+    fn start(root: &str) -> NodeTraversal {
+        NodeTraversal(vec![root.to_owned()])
+    }
+
+    fn descend(&self, node: &str) -> NodeTraversal {
+        let mut vec = self.0.clone();
+        vec.push(node.to_owned());
+        NodeTraversal(vec)
+    }
+
+    fn as_bytes(&self) -> Vec<u8> {
+        self.0.join("/").into_bytes()
+    }
+}
+
+impl<'me> SceneImporter<'me> {
+    fn import(path: &Path, project_root: &Path, settings: &SceneSettings, db: &mut AssetDatabase) -> Result<()> {
+        let import_root = prepare_import_root_for(path, project_root)?;
+
+        let fs_abs_path = path_pkg::as_fs_abs(project_root, path)?;
+        let fs_abs_path = fs_abs_path
+            .to_str()
+            .ok_or_else(|| Error::import(path, "assimp requires unicode path"))?;
+
+        // Vertices are moved by four joints at most
+        let mut post_process = vec![assimp::PostProcess::Triangulate, assimp::PostProcess::LimitBoneWeights];
+
+        // Normals in the file are dropped first for them to be computed again
+        post_process.extend(match settings.normals {
+            Normals::Import => vec![assimp::PostProcess::GenerateNormals],
+            Normals::Smooth => vec![assimp::PostProcess::DropNormals, assimp::PostProcess::GenerateSmoothNormals],
+            Normals::Flat => vec![assimp::PostProcess::DropNormals, assimp::PostProcess::GenerateNormals],
+        });
+
+        if settings.generate_tangents {
+            post_process.push(assimp::PostProcess::CalculateTangentSpace);
+        }
+
+        if settings.join_identical_vertices {
+            post_process.push(assimp::PostProcess::JoinIdenticalVertices);
+        }
+
+        if settings.optimize_meshes {
+            post_process.push(assimp::PostProcess::OptimizeMeshes);
+            post_process.push(assimp::PostProcess::ImproveCacheLocality);
+        }
+
+        let scene = assimp::Scene::from_file(fs_abs_path, post_process)
+            .map_err(|err| Error::import(path, err))?;
+
+        let mut importer = SceneImporter {
+            path,
+            import_root: &import_root,
+            settings,
+            scene: &scene,
+            importing_scene: Default::default(),
+            joints: HashMap::new(),
+            db,
+        };
+
+        let root = scene
+            .root
+            .as_ref()
+            .ok_or_else(|| Error::import(path, "no root node"))?;
+        let root = &*RefCell::borrow(Rc::borrow(root));
+
+        let skeleton = importer.import_skeleton(root, project_root)?;
+
+        let mut node_paths = HashMap::new();
+        root.for_each_child(&mut |child| collect_node_paths(child, &mut Vec::new(), &mut node_paths));
+
+        let mut clips = Vec::new();
+        let mut keyframe_clips = Vec::new();
+
+        for (i, animation) in scene.animations.iter().enumerate() {
+            clips.extend(importer.import_clip(i, animation, project_root)?);
+            keyframe_clips.extend(importer.import_keyframe_clip(i, animation, &node_paths, project_root)?);
+        }
+
+        let root_entity = importer.process_node(root, NodeTraversal::start(&root.name), true, project_root)?;
+
+        attach_animators(&mut importer.importing_scene, root_entity, skeleton, clips, keyframe_clips);
+
+        {
+            let w = &mut importer.importing_scene;
+
+            let mut transform = w.get_one_mut::<TransformComponent>(root_entity).unwrap();
+            let mat: &mut Mat4 = &mut transform.0;
+
+            *mat = settings.root_transform() * *mat;
+        }
+
+        save_asset(&importer.importing_scene, import_root.join("main.scn"), project_root, importer.db)?;
+
+        Ok(())
+    }
+
+    /// Gathers the nodes that bones are attached to into a single skeleton, along with their ancestors so that they're
+    /// posed relative to the root node. The root itself is left out, its transform is the one of the root entity, which
+    /// is where the meshes are assumed to be.
+    fn import_skeleton(&mut self, root: &assimp::Node, project_root: &Path) -> Result<Option<AssetRef>> {
+        let mut inverse_binds = HashMap::new();
+
+        for mesh in &self.scene.meshes {
+            for bone in &mesh.bones {
+                inverse_binds.entry(bone.name.clone()).or_insert_with(|| to_mat4(&bone.offset_matrix));
+            }
+        }
+
+        if inverse_binds.is_empty() {
+            return Ok(None);
+        }
+
+        let mut included = HashSet::new();
+        collect_joint_nodes(root, &inverse_binds, &mut included);
+
+        let mut skeleton = Skeleton::default();
+
+        root.for_each_child(&mut |child| {
+            add_joints(child, None, &included, &inverse_binds, &mut skeleton, &mut self.joints);
+        });
+
+        Ok(Some(save_skeleton(self.path, &skeleton, self.import_root, project_root, self.db)?))
+    }
+
+    /// The channels of the joints become a skeletal clip, the ones of the other nodes a keyframe clip.
+    fn import_clip(
+        &mut self,
+        index: usize,
+        animation: &assimp::Animation,
+        project_root: &Path,
+    ) -> Result<Option<AssetRef>> {
+        let seconds = tick_duration(animation);
+
+        let mut channels: Vec<(usize, JointChannel)> = animation
+            .channels
+            .iter()
+            .filter_map(|channel| {
+                let joint = *self.joints.get(&channel.name)?;
+                let (translation, rotation, scale) = to_tracks(channel, seconds);
+
+                Some((joint, JointChannel { joint: channel.name.clone(), translation, rotation, scale }))
+            })
+            .collect();
+
+        if channels.is_empty() {
+            return Ok(None);
+        }
+
+        // In the order of the skeleton
+        channels.sort_by_key(|(joint, _)| *joint);
+
+        let clip = AnimationClip {
+            duration: (animation.duration * seconds) as f32,
+            channels: channels.into_iter().map(|(_, channel)| channel).collect(),
+        };
+
+        let clip_path = self.import_root.join(format!("animation{}.{}", index, AnimationClip::EXTENSION));
+
+        Ok(Some(save_asset(&clip, clip_path, project_root, self.db)?))
+    }
+
+    /// Rigid-body animation of the nodes that aren't joints, played on the root entity. The root node isn't animated,
+    /// its entity carries the transform of the import settings.
+    fn import_keyframe_clip(
+        &mut self,
+        index: usize,
+        animation: &assimp::Animation,
+        node_paths: &HashMap<String, Vec<String>>,
+        project_root: &Path,
+    ) -> Result<Option<AssetRef>> {
+        let seconds = tick_duration(animation);
+
+        let mut channels = Vec::new();
+
+        for channel in &animation.channels {
+            let target = match node_paths.get(&channel.name) {
+                Some(target) if !self.joints.contains_key(&channel.name) => target,
+                _ => continue,
+            };
+
+            let (translation, rotation, scale) = to_tracks(channel, seconds);
+
+            let mut properties = Vec::new();
+
+            if !translation.is_empty() {
+                properties.push(AnimatedProperty::Translation(translation));
+            }
+
+            if !rotation.is_empty() {
+                properties.push(AnimatedProperty::Rotation(rotation));
+            }
+
+            if !scale.is_empty() {
+                properties.push(AnimatedProperty::Scale(scale));
+            }
+
+            for property in properties {
+                channels.push(KeyframeChannel { target: target.clone(), property });
+            }
+        }
+
+        if channels.is_empty() {
+            return Ok(None);
+        }
+
+        let clip = KeyframeClip {
+            duration: (animation.duration * seconds) as f32,
+            channels,
+        };
+
+        let clip_path = self.import_root.join(format!("animation{}.{}", index, KeyframeClip::EXTENSION));
+
+        Ok(Some(save_asset(&clip, clip_path, project_root, self.db)?))
+    }
+
+    /// Turns a node and its descendants into entities. LODs are generated for its meshes if `auto_lods` is set and
+    /// the settings ask for them.
+    fn process_node(
+        &mut self,
+        node: &assimp::Node,
+        traversal: NodeTraversal,
+        auto_lods: bool,
+        project_root: &Path,
+    ) -> Result<Entity> {
+        let entity = self.importing_scene.create();
+
+        self.importing_scene
+            .attach(entity, NameComponent(node.name.clone()));
+        self.importing_scene
+            .attach(entity, TransformComponent(to_mat4(&node.transformation)));
+        self.importing_scene
+            .attach(entity, HierarchyComponent::default());
+
+        let lod_levels = if auto_lods { self.settings.lod_levels } else { 0 };
+
+        if lod_levels > 0 && !node.meshes.is_empty() {
+            self.importing_scene
+                .attach(entity, LodGroupComponent::with_levels(lod_levels as usize));
+        }
+
+        for mesh_idx in &node.meshes {
+            let mesh = &self.scene.meshes[*mesh_idx as usize];
+
+            let imported_mesh = self.extract_mesh(mesh)?;
+            let mesh_ref = self.save_mesh(&imported_mesh, &traversal, &mesh.name, project_root)?;
+
+            let mut lods = Vec::new();
+
+            for (i, lod) in generate_lods(&imported_mesh, lod_levels).iter().enumerate() {
+                lods.push(self.save_mesh(lod, &traversal, &format!("{}/LOD{}", mesh.name, i + 1), project_root)?);
+            }
+
+            let mat = {
+                let mat = &self.scene.materials[mesh.material_index as usize];
+
+                let mat_name = mat
+                    .properties
+                    .iter()
+                    .find(|prop| prop.key == "?mat.name")
+                    .map(|prop| match &prop.data {
+                        assimp::PropertyTypeInfo::String(s) => Ok(s),
+                        _ => Err(Error::import(self.path, "the name of a material isn't a string")),
+                    })
+                    .transpose()?;
+
+                let mut imported_mat = Material::default();
+
+                let opacity = mat
+                    .properties
+                    .iter()
+                    .find(|prop| prop.key == "$mat.opacity")
+                    .and_then(|prop| match &prop.data {
+                        assimp::PropertyTypeInfo::FloatArray(values) => values.first().copied(),
+                        _ => None,
+                    });
+
+                // Alpha testing and additive blending can't be told apart from the source material, they're left to
+                // be set by hand
+                if let Some(opacity) = opacity.filter(|opacity| *opacity < 1.0) {
+                    let diffuse = mat
+                        .properties
+                        .iter()
+                        .find(|prop| prop.key == "$clr.diffuse")
+                        .and_then(|prop| match &prop.data {
+                            assimp::PropertyTypeInfo::FloatArray(values) if values.len() >= 3 => {
+                                Some([values[0], values[1], values[2]])
+                            }
+                            _ => None,
+                        })
+                        .unwrap_or([1.0, 1.0, 1.0]);
+
+                    imported_mat.color = Some([diffuse[0], diffuse[1], diffuse[2], opacity]);
+                    imported_mat.blend = BlendMode::AlphaBlend;
+                }
+
+                match mat
+                    .textures
+                    .get(&assimp::TextureType::Diffuse)
+                    .map(|tex_vec| tex_vec.first())
+                    .flatten()
+                {
+                    Some(tex) => {
+                        let fs_path = PathBuf::from(&tex.path);
+                        if !fs_path.is_relative() {
+                            return Err(Error::import(
+                                self.path,
+                                "textures paths must be relative to the scene file",
+                            ));
+                        }
+
+                        // Let's say the we are importing `cube.fbx` from this filesystem:
+                        // models
+                        //  |_ cube/
+                        //      |_ cube.fbx
+                        //      |_ textures/
+                        //          |_ diffuse.png
+                        // and that `cube.fbx` refers to its texture using a relative path of `./texture/diffuse.png`.
+                        // Then, the import path of `$/models/cube/cube.fbx` will be `$/.import/models/cube/cube.fbx`
+                        // and the import path of `$/models/cube/textures/diffuse.png` will be
+                        // `$/.import/models/cube/textures/diffuse.png`. As you can see to obtain it we can simply
+                        // pop from the import root of the scene and append the relative texture path. That will match
+                        // the import root used when importing the texture
+
+                        let mut raven_path = self.import_root.to_owned();
+                        raven_path.pop();
+                        raven_path.push(&fs_path);
+                        raven_path.push("main.tex");
+
+                        // With its GUID if the texture was imported before the scene
+                        imported_mat.tex = Some(self.db.reference(raven_path));
+                    }
+                    _ => (),
+                }
+
+                let mut hasher = Md5::default();
+
+                if let Some(mat_name) = mat_name {
+                    // If the material has a name, use it as the hash
+                    Digest::update(&mut hasher, mat_name.as_bytes());
+                } else {
+                    // Otherwise use the same string used for the mesh but append "/MATERIAL"
+                    Digest::update(&mut hasher, traversal.as_bytes());
+                    Digest::update(&mut hasher, &mesh.name);
+                    Digest::update(&mut hasher, "/MATERIAL");
+                }
+
+                let mat_path = self.import_root.join(format!("{:x}.mat", hasher.finalize()));
+
+                save_asset(&imported_mat, mat_path, project_root, self.db)?
+            };
+
+            let mut mesh_comp = MeshComponent::new(mesh_ref, mat);
+            mesh_comp.lods = lods;
+
+            self.importing_scene.attach(entity, mesh_comp);
+        }
+
+        // Children named `Name_LOD0`, `Name_LOD1` and so on are versions of the same model with less and less detail.
+        // The first one becomes an entity and the meshes of the others its LODs, in place of generated ones
+        let mut authored_lods: HashMap<String, Vec<(u32, Rc<RefCell<assimp::Node>>)>> = HashMap::new();
+
+        for child in &node.children {
+            if let Some((name, level)) = lod_level(&RefCell::borrow(Rc::borrow(child)).name) {
+                authored_lods.entry(name.to_owned()).or_default().push((level, child.clone()));
+            }
+        }
+
+        authored_lods.retain(|_, lods| lods.iter().any(|(level, _)| *level == 0));
+
+        for lods in authored_lods.values_mut() {
+            lods.retain(|(level, _)| *level != 0);
+            lods.sort_by_key(|(level, _)| *level);
+        }
+
+        // Collects entities of children that we will later insert into the HierarchyComponent for this node
+        let mut children_entities = Vec::new();
+
+        for child in &node.children {
+            let child = &*RefCell::borrow(Rc::borrow(child));
+
+            let lods = match lod_level(&child.name) {
+                Some((name, level)) if authored_lods.contains_key(name) => {
+                    // Part of the LOD0 entity
+                    if level != 0 {
+                        continue;
+                    }
+
+                    Some(&authored_lods[name])
+                }
+                _ => None,
+            };
+
+            let child_entity = self.process_node(child, traversal.descend(&child.name), lods.is_none(), project_root)?;
+
+            if let Some(lods) = lods {
+                self.attach_authored_lods(child_entity, lods, &traversal, project_root)?;
+            }
+
+            let mut hierarchy_component = self
+                .importing_scene
+                .get_one_mut::<HierarchyComponent>(child_entity)
+                .unwrap();
+            hierarchy_component.parent = Some(entity);
+
+            children_entities.push(child_entity);
+        }
+
+        let mut hierarchy_component = self
+            .importing_scene
+            .get_one_mut::<HierarchyComponent>(entity)
+            .unwrap();
+        hierarchy_component.children = children_entities;
+
+        Ok(entity)
+    }
+
+    /// Saves a mesh of the node at `traversal`, named after the node and `name` so that it keeps its path when the
+    /// scene is imported again.
+    fn save_mesh(
+        &mut self,
+        mesh: &Mesh,
+        traversal: &NodeTraversal,
+        name: &str,
+        project_root: &Path,
+    ) -> Result<AssetRef> {
+        let mut hasher = Md5::default();
+        Digest::update(&mut hasher, traversal.as_bytes());
+        Digest::update(&mut hasher, name);
+
+        let mesh_path = self.import_root.join(format!("{:x}.mesh", hasher.finalize()));
+
+        save_asset(mesh, mesh_path, project_root, self.db)
+    }
+
+    /// Saves the meshes of the nodes that are LODs of the one of `entity`, in order. The i-th mesh of each becomes a LOD
+    /// of the i-th mesh of the entity.
+    fn attach_authored_lods(
+        &mut self,
+        entity: Entity,
+        lods: &[(u32, Rc<RefCell<assimp::Node>>)],
+        traversal: &NodeTraversal,
+        project_root: &Path,
+    ) -> Result<()> {
+        for (_, lod) in lods {
+            let lod = &*RefCell::borrow(Rc::borrow(lod));
+            let traversal = traversal.descend(&lod.name);
+
+            let mut lod_refs = Vec::new();
+
+            for mesh_idx in &lod.meshes {
+                let mesh = &self.scene.meshes[*mesh_idx as usize];
+                let extracted = self.extract_mesh(mesh)?;
+                lod_refs.push(self.save_mesh(&extracted, &traversal, &mesh.name, project_root)?);
+            }
+
+            let mesh_comps = self.importing_scene.get_all_mut::<MeshComponent>(entity);
+
+            for (mut mesh_comp, lod_ref) in mesh_comps.into_iter().zip(lod_refs) {
+                mesh_comp.lods.push(lod_ref);
+            }
+        }
+
+        self.importing_scene
+            .attach(entity, LodGroupComponent::with_levels(lods.len()));
+
+        Ok(())
+    }
+
+    fn extract_mesh(&self, mesh: &assimp::Mesh) -> Result<Mesh> {
+        // Every attribute but the position and the normal is optional, meshes without UVs can only be drawn untextured
+        let uvs = mesh.texture_coords.get(0).and_then(Option::as_ref);
+        let uvs2 = mesh.texture_coords.get(1).and_then(Option::as_ref);
+        let colors = mesh.colors.get(0).and_then(Option::as_ref);
+        let has_tangents = !mesh.tangents.is_empty() && !mesh.bitangents.is_empty();
+
+        let layout = VertexLayout {
+            uv: uvs.is_some(),
+            tangents: has_tangents,
+            colors: colors.is_some(),
+            uv2: uvs2.is_some(),
+            skin: !mesh.bones.is_empty(),
+        };
+
+        let mut vertices: Vec<_> = izip!(mesh.vertices.iter(), mesh.normals.iter())
+            .enumerate()
+            .map(|(i, (position, normal))| {
+                let mut vertex = Vertex::new(
+                    Vec3::new(position.x, position.y, position.z),
+                    Vec3::new(normal.x, normal.y, normal.z),
+                    uvs.map_or(Vec2::ZERO, |uvs| Vec2::new(uvs[i].x, uvs[i].y)),
+                );
+
+                if let Some(uvs2) = uvs2 {
+                    vertex.uv2 = Vec2::new(uvs2[i].x, uvs2[i].y);
+                }
+
+                if let Some(colors) = colors {
+                    let color = &colors[i];
+                    vertex.color = Vec4::new(color.r, color.g, color.b, color.a);
+                }
+
+                if has_tangents {
+                    let (tangent, bitangent) = (&mesh.tangents[i], &mesh.bitangents[i]);
+                    vertex.tangent = Vec3::new(tangent.x, tangent.y, tangent.z);
+                    vertex.bitangent = Vec3::new(bitangent.x, bitangent.y, bitangent.z);
+                }
+
+                vertex
+            })
+            .collect();
+
+        // Bones list the vertices they move, Assimp was told to keep at most four per vertex
+        let mut influences = vec![Vec::new(); vertices.len()];
+
+        for bone in &mesh.bones {
+            let joint = match self.joints.get(&bone.name) {
+                Some(&joint) => joint as u16,
+                // The root node isn't part of the skeleton
+                None => continue,
+            };
+
+            for weight in &bone.weights {
+                if let Some(influences) = influences.get_mut(weight.vertex_id as usize) {
+                    influences.push((joint, weight.weight));
+                }
+            }
+        }
+
+        for (vertex, influences) in vertices.iter_mut().zip(influences) {
+            let total: f32 = influences.iter().take(4).map(|(_, weight)| weight).sum();
+
+            if total <= 0.0 {
+                continue;
+            }
+
+            for (i, (joint, weight)) in influences.into_iter().take(4).enumerate() {
+                vertex.joints[i] = joint;
+                vertex.weights[i] = weight / total;
+            }
+        }
+
+        // Triangulating leaves the points and lines, which can't be drawn with the triangles
+        let indices: Vec<_> = mesh
+            .faces
+            .iter()
+            .filter(|face| face.0.len() == 3)
+            .flat_map(|face| face.0.iter().copied())
+            .collect();
+
+        Ok(Mesh::with_layout(vertices, indices, layout))
+    }
+}
+
+/// Splits the name of a node following the `Name_LOD<n>` convention into the name of the model and the level.
+fn lod_level(name: &str) -> Option<(&str, u32)> {
+    let index = name.rfind("_LOD")?;
+    let level = name[index + "_LOD".len()..].parse().ok()?;
+
+    Some((&name[..index], level))
+}
+
+fn to_mat4(t: &assimp::Matrix4x4) -> Mat4 {
+    Mat4::from_cols(
+        Vec4::new(t.a1, t.b1, t.c1, t.d1),
+        Vec4::new(t.a2, t.b2, t.c2, t.d2),
+        Vec4::new(t.a3, t.b3, t.c3, t.d3),
+        Vec4::new(t.a4, t.b4, t.c4, t.d4),
+    )
+}
+
+/// How many seconds a tick of the animation lasts.
+fn tick_duration(animation: &assimp::Animation) -> f64 {
+    // Assimp leaves it at 0 when the file doesn't say
+    if animation.ticks_per_second > 0.0 { 1.0 / animation.ticks_per_second } else { 1.0 / 25.0 }
+}
+
+/// The translation, rotation and scale keys of a node, with their times in seconds.
+fn to_tracks(channel: &assimp::NodeAnim, seconds: f64) -> (Track<Vec3>, Track<Quat>, Track<Vec3>) {
+    let translation = Track::new(
+        channel.position_keys.iter().map(|key| (key.time * seconds) as f32).collect(),
+        channel.position_keys.iter().map(|key| Vec3::new(key.value.x, key.value.y, key.value.z)).collect(),
+        Interpolation::Linear,
+    );
+
+    let rotation = Track::new(
+        channel.rotation_keys.iter().map(|key| (key.time * seconds) as f32).collect(),
+        channel.rotation_keys
+            .iter()
+            .map(|key| Quat::from_xyzw(key.value.x, key.value.y, key.value.z, key.value.w))
+            .collect(),
+        Interpolation::Linear,
+    );
+
+    let scale = Track::new(
+        channel.scaling_keys.iter().map(|key| (key.time * seconds) as f32).collect(),
+        channel.scaling_keys.iter().map(|key| Vec3::new(key.value.x, key.value.y, key.value.z)).collect(),
+        Interpolation::Linear,
+    );
+
+    (translation, rotation, scale)
+}
+
+
+/// Collects the names of the nodes that are bones or ancestors of one. Returns whether the node is.
+fn collect_joint_nodes(node: &assimp::Node, bones: &HashMap<String, Mat4>, included: &mut HashSet<String>) -> bool {
+    let mut any = bones.contains_key(&node.name);
+
+    for child in &node.children {
+        let child = &*RefCell::borrow(Rc::borrow(child));
+        any |= collect_joint_nodes(child, bones, included);
+    }
+
+    if any {
+        included.insert(node.name.clone());
+    }
+
+    any
+}
+
+/// A node of the hierarchy of an imported file, for what's common to the importers.
+trait ImportedNode {
+    /// How the file refers to the node
+    type Key: Clone + Eq + Hash;
+
+    fn key(&self) -> Self::Key;
+    fn name(&self) -> String;
+    /// The transform relative to the parent, which is the rest pose of joints
+    fn rest(&self) -> JointTransform;
+    fn for_each_child(&self, f: &mut dyn FnMut(&Self));
+}
+
+impl ImportedNode for assimp::Node {
+    // Assimp refers to nodes by name, from bones and animation channels
+    type Key = String;
+
+    fn key(&self) -> String {
+        self.name.clone()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn rest(&self) -> JointTransform {
+        JointTransform::from_mat4(&to_mat4(&self.transformation))
+    }
+
+    fn for_each_child(&self, f: &mut dyn FnMut(&Self)) {
+        for child in &self.children {
+            f(&RefCell::borrow(Rc::borrow(child)));
+        }
+    }
+}
+
+/// Maps the node and its descendants to the names from the first node of the path down to them, which is how keyframe
+/// channels find their entity.
+fn collect_node_paths<N: ImportedNode>(node: &N, path: &mut Vec<String>, out: &mut HashMap<N::Key, Vec<String>>) {
+    path.push(node.name());
+    out.entry(node.key()).or_insert_with(|| path.clone());
+
+    node.for_each_child(&mut |child| collect_node_paths(child, path, out));
+
+    path.pop();
+}
+
+/// Adds the node and its descendants that are included to the skeleton, each after its parent.
+fn add_joints<N: ImportedNode>(
+    node: &N,
+    parent: Option<usize>,
+    included: &HashSet<N::Key>,
+    inverse_binds: &HashMap<N::Key, Mat4>,
+    skeleton: &mut Skeleton,
+    joints: &mut HashMap<N::Key, usize>,
+) {
+    // Neither a joint nor an ancestor of one, and so are its descendants
+    let key = node.key();
+
+    if !included.contains(&key) {
+        return;
+    }
+
+    let joint = skeleton.joints.len();
+
+    skeleton.joints.push(Joint {
+        name: node.name(),
+        parent,
+        rest: node.rest(),
+        inverse_bind: inverse_binds.get(&key).copied().unwrap_or(Mat4::IDENTITY),
+    });
+
+    joints.insert(key, joint);
+
+    node.for_each_child(&mut |child| add_joints(child, Some(joint), included, inverse_binds, skeleton, joints));
+}
+
+/// Saves the skeleton gathered from the file at `path`, which can't have more joints than meshes can be skinned with.
+fn save_skeleton(
+    path: &Path,
+    skeleton: &Skeleton,
+    import_root: &Path,
+    project_root: &Path,
+    db: &mut AssetDatabase,
+) -> Result<AssetRef> {
+    if skeleton.joints.len() > Skeleton::MAX_JOINTS {
+        return Err(Error::import(path, format!(
+            "the skeleton has {} joints, more than {}",
+            skeleton.joints.len(),
+            Skeleton::MAX_JOINTS,
+        )));
+    }
+
+    let skeleton_path = import_root.join(format!("skeleton.{}", Skeleton::EXTENSION));
+
+    save_asset(skeleton, skeleton_path, project_root, db)
+}
+
+/// Plays the imported clips on the root entity. The first skeletal clip plays, the others can be blended in by raising
+/// their weight. Keyframe clips get an animator each, only the first one playing.
+fn attach_animators(
+    scene: &mut Scene,
+    root: Entity,
+    skeleton: Option<AssetRef>,
+    clips: Vec<AssetRef>,
+    keyframe_clips: Vec<AssetRef>,
+) {
+    if let Some(skeleton) = skeleton {
+        let mut animator = AnimatorComponent::new(skeleton);
+
+        for (i, clip) in clips.into_iter().enumerate() {
+            let mut layer = AnimationLayer::new(clip);
+            layer.weight = if i == 0 { 1.0 } else { 0.0 };
+
+            animator.layers.push(layer);
+        }
+
+        scene.attach(root, animator);
+    }
+
+    for (i, clip) in keyframe_clips.into_iter().enumerate() {
+        let mut keyframe_animator = KeyframeAnimatorComponent::new(clip);
+        keyframe_animator.playing = i == 0;
+
+        scene.attach(root, keyframe_animator);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLASS_OBJ: &str = "\
+mtllib glass.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+usemtl glass
+f 1//1 2//1 3//1
+";
+
+    const GLASS_MTL: &str = "\
+newmtl glass
+Kd 1 0 0
+d 0.5
+";
+
+    #[test]
+    fn translucent_materials_keep_their_opacity() {
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join("glass.obj"), GLASS_OBJ).unwrap();
+        fs::write(project.path().join("glass.mtl"), GLASS_MTL).unwrap();
+
+        assert!(import(Path::new("$/glass.obj"), project.path()).unwrap());
+
+        let import_root = as_import_root(Path::new("$/glass.obj")).unwrap();
+        let mats: Vec<_> = fs::read_dir(path_pkg::as_fs_abs(project.path(), import_root).unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "mat"))
+            .collect();
+
+        assert_eq!(mats.len(), 1);
+
+        let mat = Material::load(&mats[0]).unwrap();
+        assert_eq!(mat.blend, BlendMode::AlphaBlend);
+        assert_eq!(mat.color, Some([1.0, 0.0, 0.0, 0.5]));
+    }
+}
@@ -0,0 +1,3 @@
+//! The parts of the editor that don't need a window, shared with the command line tools.
+
+pub mod import;
@@ -23,22 +23,26 @@ use itertools::Itertools;
 use palette;
 use palette::{FromColor, Saturate, Shade};
 
+use raven_core::bake::{Compression, ImportSettings, Normals, UpAxis};
 use raven_core::combined_transform;
-use raven_core::component::{CameraComponent, HierarchyComponent, NameComponent, SceneComponent, TransformComponent};
+use raven_core::component::{CameraComponent, HierarchyComponent, MeshComponent, NameComponent, Projection, SceneComponent, ScriptComponent, TransformComponent};
+use raven_core::database::AssetRef;
 use raven_core::ecs::{Entity, Query};
 use raven_core::framebuffer::Framebuffer;
+use raven_core::postprocess::{Bloom, Fxaa, GammaCorrection, PostEffect, ToneMapOperator, ToneMapping, Vignette};
 use raven_core::FrameError;
 use raven_core::glam::{EulerRot, Mat4, Quat, Vec3};
 use raven_core::io::Serializable;
 use raven_core::mat4;
 use raven_core::path;
+use raven_core::primitive::Primitive;
 use raven_core::Processor;
-use raven_core::resource::Scene;
+use raven_core::resource::{ColorSpace, Filter, Material, RenderTexture, Scene, Wrap};
 use raven_core::time::Delta;
+use raven_core::watch::ProjectWatcher;
+use raven_editor::import;
 use std::os::unix::fs::OpenOptionsExt;
 
-mod import;
-
 const RUNTIME_BYTES: &'static [u8] = include_bytes!("../../target/release/raven_runtime");
 
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
@@ -59,27 +63,40 @@ struct OpenProjectState {
     dragging: Option<Entity>,
 
     // Resources known to the editor
-    avail_resources: HashMap<ResourceType, Vec<PathBuf>>,
+    avail_resources: HashMap<ResourceType, Vec<AssetRef>>,
+
+    // None if the project couldn't be watched, then changed files aren't reloaded
+    watcher: Option<ProjectWatcher>,
+
+    // External file whose import settings are being edited, with the edited settings
+    import_settings: Option<(PathBuf, ImportSettings)>,
 }
 
 #[derive(Eq, PartialEq, Debug, Hash, Copy, Clone)]
 enum ResourceType {
-    Scene
+    Scene,
+    RenderTexture,
+    Script,
 }
 
 impl ResourceType {
     fn glob(&self) -> &'static str {
         match self {
-            Self::Scene => "*.scn"
+            Self::Scene => "*.scn",
+            Self::RenderTexture => "*.rtex",
+            Self::Script => "*.rhai",
         }
     }
 }
 
 impl OpenProjectState {
     fn scan_avail_resources(&mut self) -> Result<()> {
+        // New assets get their GUID as soon as the editor knows about them, so that references to them have it
+        self.processor.get_asset_db_mut().assign_guids()?;
+
         self.avail_resources.clear();
 
-        for r_type in vec![ResourceType::Scene] {
+        for r_type in vec![ResourceType::Scene, ResourceType::RenderTexture, ResourceType::Script] {
             let mut path = std::path::PathBuf::new();
             path.push(&self.project_root);
             path.push("**");
@@ -93,16 +110,56 @@ impl OpenProjectState {
                 raven_path.push(path::PROJECT_ROOT_RUNE);
                 raven_path.push(rel_path);
 
+                let asset = self.processor.get_asset_db().reference(raven_path);
+
                 let vec = self.avail_resources.entry(r_type).or_insert_with(|| Vec::new());
-                vec.push(raven_path);
+                vec.push(asset);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Imports again the external files that changed since they were imported and reloads the assets that changed,
+    /// which include the ones just imported as soon as the watcher notices them. The loaded scene isn't reloaded, so
+    /// the selection is kept.
+    fn reload_changed_assets(&mut self) -> Result<()> {
+        let changed = match &self.watcher {
+            Some(watcher) => watcher.poll(),
+            None => return Ok(()),
+        };
+
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        for path in &changed {
+            if import::is_imported(path, &self.project_root) {
+                import::import(path, &self.project_root)?;
             }
         }
 
+        self.scan_avail_resources()?;
+
+        self.processor.reload_assets(&changed)?;
+
         Ok(())
     }
 }
 
 fn main() -> Result<()> {
+    // `raven_editor --reimport <project root>` brings the imported assets up to date without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [flag, project_root] = args.as_slice() {
+        if flag == "--reimport" {
+            for path in import::reimport_out_of_date(Path::new(project_root))? {
+                println!("Reimported {}", path.display());
+            }
+
+            return Ok(());
+        }
+    }
+
     let el = EventLoop::new();
 
     let wb = WindowBuilder::new()
@@ -181,7 +238,10 @@ fn main() -> Result<()> {
 
                 match try {
                     match proj_state.as_mut() {
-                        Some(proj_state) => draw_editor_window(&ui, proj_state)?,
+                        Some(proj_state) => {
+                            proj_state.reload_changed_assets()?;
+                            draw_editor_window(&ui, proj_state)?
+                        }
                         None => match draw_select_project_window(&ui)? {
                             Some(new_proj_state) => proj_state = Some(new_proj_state),
                             None => (),
@@ -260,6 +320,16 @@ fn draw_select_project_window(ui: &imgui::Ui) -> Result<Option<OpenProjectState>
                                 dragging: None,
 
                                 avail_resources: HashMap::new(),
+
+                                watcher: match ProjectWatcher::new(&path) {
+                                    Ok(watcher) => Some(watcher),
+                                    Err(err) => {
+                                        eprintln!("Changes to the project won't be reloaded: {}", err);
+                                        None
+                                    }
+                                },
+
+                                import_settings: None,
                             };
 
                             state.scan_avail_resources()?;
@@ -408,7 +478,7 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
                                 raven_path.push(path::PROJECT_ROOT_RUNE);
                                 raven_path.push(rel_path);
 
-                                import::import(&raven_path, proj_state)?;
+                                import::import(&raven_path, &proj_state.project_root)?;
                             } else {
                                 let file_name = fs_path
                                     .file_name()
@@ -420,12 +490,57 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
 
                                 fs::copy(
                                     fs_path,
-                                    path::as_fs_abs(&proj_state.project_root, &raven_path),
+                                    path::as_fs_abs(&proj_state.project_root, &raven_path)?,
                                 )?;
 
-                                import::import(&raven_path, proj_state)?;
+                                import::import(&raven_path, &proj_state.project_root)?;
+                            }
+
+                            proj_state.scan_avail_resources()?;
+                        }
+                        _ => (),
+                    }
+                }
+
+                if imgui::MenuItem::new("Import settings").build(ui) {
+                    match nfd::open_file_dialog(None, Some(proj_state.project_root.to_str().expect("non utf8 path"))) {
+                        Ok(nfd::Response::Okay(fs_path)) => {
+                            let raven_path = path::from_fs_abs(&proj_state.project_root, &fs_path)
+                                .ok_or_else(|| Box::<dyn Error>::from("non local file"))?;
+
+                            let settings = import::import_settings(&raven_path, &proj_state.project_root)?;
+
+                            proj_state.import_settings = Some((raven_path, settings));
+                        }
+                        _ => (),
+                    }
+                }
+
+                if imgui::MenuItem::new("Reimport out of date").build(ui) {
+                    // The imported files that changed are reloaded once the watcher reports them
+                    import::reimport_out_of_date(&proj_state.project_root)?;
+
+                    proj_state.scan_avail_resources()?;
+                }
+            };
+
+            menu.end();
+        }
+
+        if let Some(menu) = ui.begin_menu("Resources") {
+            res = try {
+                if imgui::MenuItem::new("New render texture").build(ui) {
+                    match nfd::open_save_dialog(Some(RenderTexture::EXTENSION), Some(proj_state.project_root.to_str().expect("non utf8 path"))) {
+                        Ok(nfd::Response::Okay(fs_path)) => {
+                            let fs_path = PathBuf::from(fs_path);
+
+                            if !fs_path.starts_with(&proj_state.project_root) {
+                                Err(Box::<dyn Error>::from("non local render texture"))?
                             }
 
+                            let render_tex = RenderTexture { size: [512, 512] };
+                            render_tex.save(&fs_path)?;
+
                             proj_state.scan_avail_resources()?;
                         }
                         _ => (),
@@ -436,6 +551,42 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
             menu.end();
         }
 
+        if let Some(menu) = ui.begin_menu("Assets") {
+            res = try {
+                if imgui::MenuItem::new("Move asset").build(ui) {
+                    let project_root = proj_state.project_root.to_str().expect("non utf8 path");
+
+                    let from = match nfd::open_file_dialog(None, Some(project_root)) {
+                        Ok(nfd::Response::Okay(fs_path)) => path::from_fs_abs(&proj_state.project_root, fs_path),
+                        _ => None,
+                    };
+
+                    let to = match from {
+                        Some(_) => match nfd::open_save_dialog(None, Some(project_root)) {
+                            Ok(nfd::Response::Okay(fs_path)) => path::from_fs_abs(&proj_state.project_root, fs_path),
+                            _ => None,
+                        },
+                        None => None,
+                    };
+
+                    if let (Some(from), Some(to)) = (from, to) {
+                        // References to the asset find it by its GUID, they don't have to be updated
+                        proj_state.processor.get_asset_db_mut().move_asset(&from, &to)?;
+
+                        let fs_from = path::as_fs_abs(&proj_state.project_root, &from)?;
+                        if proj_state.opened_scene_fs_path.as_ref() == Some(&fs_from) {
+                            proj_state.opened_scene_fs_path = Some(path::as_fs_abs(&proj_state.project_root, &to)?);
+                        }
+
+                        proj_state.processor.reload_assets(&[from, to])?;
+                        proj_state.scan_avail_resources()?;
+                    }
+                }
+            };
+
+            menu.end();
+        }
+
         if let Some(menu) = ui.begin_menu("Export") {
             res = try {
                 if imgui::MenuItem::new("Export project").build(ui) {
@@ -471,7 +622,7 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
         menu_bar.end();
     }
 
-    match res {
+    match res.and_then(|_| draw_import_settings_window(ui, proj_state)) {
         Err(err) => {
             main_window.end();
             return Err(err);
@@ -565,10 +716,13 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
                 Some((current_size, _)) => current_size != &[width as u32, height as u32],
                 None => true,
             } {
-                proj_state.framebuffer = Some((
-                    [width as _, height as _],
-                    Framebuffer::new((width as _, height as _)),
-                ));
+                match Framebuffer::new((width as _, height as _)) {
+                    Ok(framebuffer) => proj_state.framebuffer = Some(([width as _, height as _], framebuffer)),
+                    Err(err) => {
+                        out = Err(err.into());
+                        return;
+                    }
+                }
             }
 
             // Get a reference to the framebuffer contained in the Option
@@ -582,7 +736,7 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
             match res {
                 Ok(_) => (),
                 Err(FrameError::Generic(err)) => {
-                    out = Err(err);
+                    out = Err(err.into());
                     return;
                 }
                 Err(FrameError::NoCamera) => {
@@ -601,18 +755,37 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
     style_stack.pop();
 
     Window::new("Hierarchy").build(&ui, || {
-        let scene = match proj_state.processor.get_scene_mut() {
-            Some(scene) => scene,
-            None => return,
-        };
+        if proj_state.processor.get_scene().is_none() {
+            return;
+        }
 
         if ui.button_with_size("Create new entity", [ui.content_region_avail()[0], 0.0]) {
-            let entity = scene.create();
-            scene.attach(entity, TransformComponent::default());
-            scene.attach(entity, HierarchyComponent::default());
-            scene.attach(entity, NameComponent("New entity".to_owned()));
+            ui.open_popup("New entity");
         }
 
+        ui.popup("New entity", || {
+            if imgui::Selectable::new("Empty").build(ui) {
+                let scene = proj_state.processor.get_scene_mut().unwrap();
+
+                let entity = scene.create();
+                scene.attach(entity, TransformComponent::default());
+                scene.attach(entity, HierarchyComponent::default());
+                scene.attach(entity, NameComponent("New entity".to_owned()));
+            }
+
+            ui.separator();
+
+            for primitive in Primitive::ALL {
+                if imgui::Selectable::new(primitive.name()).build(ui) {
+                    if let Err(err) = create_primitive(proj_state, primitive) {
+                        out = Err(err);
+                    }
+                }
+            }
+        });
+
+        let scene = proj_state.processor.get_scene_mut().unwrap();
+
         ui.spacing();
         ui.separator();
         ui.spacing();
@@ -759,11 +932,15 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
                     // and so
                     // B^-1 * w = y
 
-                    let b = combined_transform(scene, parent);
+                    let (b, w) = match (combined_transform(scene, parent), combined_transform(scene, reattach.child)) {
+                        (Ok(b), Ok(w)) => (b, w),
+                        (Err(err), _) | (_, Err(err)) => {
+                            out = Err(err.into());
+                            return;
+                        }
+                    };
                     let b_inv = b.inverse();
 
-                    let w = combined_transform(scene, reattach.child);
-
                     let y = b_inv * w;
 
                     scene.get_one_mut::<TransformComponent>(reattach.child).unwrap().0 = y;
@@ -785,7 +962,15 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
                     scene.get_one_mut::<HierarchyComponent>(parent).unwrap().children.push(reattach.child);
                 },
                 ReattachTarget::Unroot => {
-                    scene.get_one_mut::<TransformComponent>(reattach.child).unwrap().0 = combined_transform(scene, reattach.child);
+                    let w = match combined_transform(scene, reattach.child) {
+                        Ok(w) => w,
+                        Err(err) => {
+                            out = Err(err.into());
+                            return;
+                        }
+                    };
+
+                    scene.get_one_mut::<TransformComponent>(reattach.child).unwrap().0 = w;
 
                     let mut child_comp = scene.get_one_mut::<HierarchyComponent>(reattach.child).unwrap();
 
@@ -835,6 +1020,14 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
                     scene.attach(selection, CameraComponent::default());
                 }
             }
+
+            if imgui::Selectable::new("ScriptComponent").build(ui) {
+                let scene = proj_state.processor.get_scene_mut().unwrap();
+
+                if scene.get_one::<ScriptComponent>(selection).is_none() {
+                    scene.attach(selection, ScriptComponent::default());
+                }
+            }
         });
 
         ui.separator();
@@ -851,8 +1044,144 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
         let mut has_camera_component = true;
 
         match proj_state.processor.get_scene_mut().unwrap().get_one_mut::<CameraComponent>(selection) {
-            Some(_) => {
-                drop(imgui::CollapsingHeader::new("CameraComponent").leaf(true).build_with_close_button(ui, &mut has_camera_component));
+            Some(mut camera_comp) => {
+                if imgui::CollapsingHeader::new("CameraComponent").default_open(true).build_with_close_button(ui, &mut has_camera_component) {
+                    ui.checkbox("Active", &mut camera_comp.active);
+                    imgui::InputInt::new(ui, "Order", &mut camera_comp.order).build();
+
+                    let mut is_orthographic = matches!(camera_comp.projection, Projection::Orthographic { .. });
+                    let was_orthographic = is_orthographic;
+
+                    let mut projection_idx = is_orthographic as usize;
+                    ui.combo_simple_string("Projection", &mut projection_idx, &["Perspective", "Orthographic"]);
+                    is_orthographic = projection_idx == 1;
+
+                    if is_orthographic != was_orthographic {
+                        camera_comp.projection = match camera_comp.projection {
+                            Projection::Perspective { near, far, .. } => Projection::Orthographic { height: 10.0, near, far },
+                            Projection::Orthographic { near, far, .. } => Projection::Perspective { fov: 90.0, near, far },
+                        };
+                    }
+
+                    match &mut camera_comp.projection {
+                        Projection::Perspective { fov, near, far } => {
+                            imgui::Drag::new("FOV").range(1.0, 179.0).speed(0.5).build(ui, fov);
+                            imgui::Drag::new("Near").range(0.001, f32::MAX).speed(0.01).build(ui, near);
+                            imgui::Drag::new("Far").range(0.001, f32::MAX).speed(0.5).build(ui, far);
+                        }
+                        Projection::Orthographic { height, near, far } => {
+                            imgui::Drag::new("Height").range(0.001, f32::MAX).speed(0.05).build(ui, height);
+                            imgui::Drag::new("Near").speed(0.01).build(ui, near);
+                            imgui::Drag::new("Far").speed(0.5).build(ui, far);
+                        }
+                    }
+
+                    ui.checkbox("Skybox", &mut camera_comp.skybox);
+                    imgui::ColorEdit::new("Clear color", &mut camera_comp.clear_color).build(ui);
+
+                    let targets: Vec<Option<&AssetRef>> = std::iter::once(None)
+                        .chain(proj_state.avail_resources.get(&ResourceType::RenderTexture).into_iter().flatten().map(Some))
+                        .collect();
+
+                    let targets_str: Vec<_> = targets.iter().map(|target| match *target {
+                        Some(target) => target.path.to_str().expect("non utf8 path"),
+                        None => "Canvas",
+                    }).collect();
+
+                    let current_idx = targets.iter().position(|target| match (target, &camera_comp.target) {
+                        (Some(target), Some(current)) => target.is_same_asset(current),
+                        (None, None) => true,
+                        _ => false,
+                    });
+
+                    if let Some(mut idx) = current_idx {
+                        if ui.combo_simple_string("Target", &mut idx, &targets_str) {
+                            camera_comp.target = targets[idx].cloned();
+                        }
+                    }
+
+                    ui.text("Viewport");
+                    imgui::Drag::new("X").range(0.0, 1.0).speed(0.005).build(ui, &mut camera_comp.viewport[0]);
+                    imgui::Drag::new("Y").range(0.0, 1.0).speed(0.005).build(ui, &mut camera_comp.viewport[1]);
+                    imgui::Drag::new("Width").range(0.0, 1.0).speed(0.005).build(ui, &mut camera_comp.viewport[2]);
+                    imgui::Drag::new("Height##Viewport").range(0.0, 1.0).speed(0.005).build(ui, &mut camera_comp.viewport[3]);
+
+                    ui.text("Post-processing");
+
+                    let mut move_up = None;
+                    let mut remove = None;
+
+                    for (i, effect) in camera_comp.post.iter_mut().enumerate() {
+                        let _id = ui.push_id(i as i32);
+
+                        ui.separator();
+
+                        let effect = effect.as_any_mut();
+
+                        if let Some(bloom) = effect.downcast_mut::<Bloom>() {
+                            ui.text("Bloom");
+                            imgui::Drag::new("Threshold").range(0.0, f32::MAX).speed(0.01).build(ui, &mut bloom.threshold);
+                            imgui::Drag::new("Intensity").range(0.0, f32::MAX).speed(0.01).build(ui, &mut bloom.intensity);
+                            imgui::Drag::new("Iterations").range(1, 16).build(ui, &mut bloom.iterations);
+                        } else if let Some(tone_mapping) = effect.downcast_mut::<ToneMapping>() {
+                            ui.text("Tone mapping");
+
+                            let operators = [ToneMapOperator::Reinhard, ToneMapOperator::Aces];
+                            let mut idx = operators.iter().position(|op| *op == tone_mapping.operator).unwrap();
+                            if ui.combo_simple_string("Operator", &mut idx, &["Reinhard", "ACES"]) {
+                                tone_mapping.operator = operators[idx];
+                            }
+
+                            imgui::Drag::new("Exposure").range(0.0, f32::MAX).speed(0.01).build(ui, &mut tone_mapping.exposure);
+                        } else if let Some(gamma_correction) = effect.downcast_mut::<GammaCorrection>() {
+                            ui.text("Gamma correction");
+                            imgui::Drag::new("Gamma").range(0.1, 5.0).speed(0.01).build(ui, &mut gamma_correction.gamma);
+                        } else if let Some(fxaa) = effect.downcast_mut::<Fxaa>() {
+                            ui.text("FXAA");
+                            imgui::Drag::new("Span max").range(1.0, 16.0).speed(0.1).build(ui, &mut fxaa.span_max);
+                        } else if let Some(vignette) = effect.downcast_mut::<Vignette>() {
+                            ui.text("Vignette");
+                            imgui::Drag::new("Intensity").range(0.0, 1.0).speed(0.005).build(ui, &mut vignette.intensity);
+                            imgui::Drag::new("Radius").range(0.0, 1.0).speed(0.005).build(ui, &mut vignette.radius);
+                            imgui::Drag::new("Smoothness").range(0.0, 1.0).speed(0.005).build(ui, &mut vignette.smoothness);
+                        } else {
+                            // Custom effects can't be inspected, but they can still be reordered and removed
+                            ui.text("Custom effect");
+                        }
+
+                        if i > 0 && ui.small_button("Move up") {
+                            move_up = Some(i);
+                        }
+                        ui.same_line();
+                        if ui.small_button("Remove") {
+                            remove = Some(i);
+                        }
+                    }
+
+                    if let Some(i) = move_up {
+                        camera_comp.post.swap(i - 1, i);
+                    }
+
+                    if let Some(i) = remove {
+                        camera_comp.post.remove(i);
+                    }
+
+                    ui.separator();
+
+                    let mut add_idx = 0;
+                    if ui.combo_simple_string("Add effect", &mut add_idx, &["", "Bloom", "Tone mapping", "Gamma correction", "FXAA", "Vignette"]) {
+                        let effect: Option<Box<dyn PostEffect>> = match add_idx {
+                            1 => Some(Box::new(Bloom::default())),
+                            2 => Some(Box::new(ToneMapping::default())),
+                            3 => Some(Box::new(GammaCorrection::default())),
+                            4 => Some(Box::new(Fxaa::default())),
+                            5 => Some(Box::new(Vignette::default())),
+                            _ => None,
+                        };
+
+                        camera_comp.post.extend(effect);
+                    }
+                }
             }
             None => (),
         };
@@ -959,22 +1288,26 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
 
         let mut has_scene_component = true;
 
-        type NewScene = Option<PathBuf>;
+        type NewScene = Option<AssetRef>;
 
-        // Some(Option<PathBuf>) if the scene has changed. None otherwise.
+        // Some(Option<AssetRef>) if the scene has changed. None otherwise.
         let new_scene: Option<NewScene> = match proj_state.processor.get_scene().unwrap().get_one::<SceneComponent>(selection) {
             Some(scene_comp) => {
                 if imgui::CollapsingHeader::new("SceneComponent").default_open(true).build_with_close_button(ui, &mut has_scene_component) {
                     try {
                         let scenes = proj_state.avail_resources.get(&ResourceType::Scene)?;
 
-                        let mut scenes: Vec<Option<&PathBuf>> = scenes.into_iter().map(|scene| Some(scene)).collect::<Vec<_>>();
+                        let mut scenes: Vec<Option<&AssetRef>> = scenes.into_iter().map(|scene| Some(scene)).collect::<Vec<_>>();
                         scenes.insert(0, None);
 
-                        let (mut idx, _) = scenes.iter().find_position(|scene| **scene == scene_comp.scene.as_ref())?;
+                        let (mut idx, _) = scenes.iter().find_position(|scene| match (scene, &scene_comp.scene) {
+                            (Some(scene), Some(current)) => scene.is_same_asset(current),
+                            (None, None) => true,
+                            _ => false,
+                        })?;
 
                         let scenes_str: Vec<_> = scenes.iter().map(|scene| match *scene {
-                            Some(scene) => scene.to_str().expect("non utf8 path"),
+                            Some(scene) => scene.path.to_str().expect("non utf8 path"),
                             None => "",
                         }).collect();
 
@@ -1005,6 +1338,55 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
         if !has_scene_component {
             proj_state.processor.get_scene_mut().unwrap().detach_one::<SceneComponent>(selection);
         }
+
+        let mut has_script_component = true;
+
+        // Some(Option<AssetRef>) if the script has changed. None otherwise.
+        let new_script: Option<Option<AssetRef>> = match proj_state.processor.get_scene().unwrap().get_one::<ScriptComponent>(selection) {
+            Some(script_comp) => {
+                if imgui::CollapsingHeader::new("ScriptComponent").default_open(true).build_with_close_button(ui, &mut has_script_component) {
+                    try {
+                        let scripts = proj_state.avail_resources.get(&ResourceType::Script)?;
+
+                        let mut scripts: Vec<Option<&AssetRef>> = scripts.iter().map(Some).collect();
+                        scripts.insert(0, None);
+
+                        let (mut idx, _) = scripts.iter().find_position(|script| match (script, &script_comp.script) {
+                            (Some(script), Some(current)) => script.is_same_asset(current),
+                            (None, None) => true,
+                            _ => false,
+                        })?;
+
+                        let scripts_str: Vec<_> = scripts.iter().map(|script| match *script {
+                            Some(script) => script.path.to_str().expect("non utf8 path"),
+                            None => "",
+                        }).collect();
+
+                        let old_idx = idx;
+
+                        ui.set_next_item_width(ui.content_region_avail()[0]);
+                        ui.combo_simple_string("##Script", &mut idx, &scripts_str);
+
+                        if old_idx == idx {
+                            None?;
+                        }
+
+                        scripts[idx].cloned()
+                    }
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
+        if let Some(new_script) = new_script {
+            proj_state.processor.get_scene_mut().unwrap().get_one_mut::<ScriptComponent>(selection).unwrap().set_script(new_script);
+        }
+
+        if !has_script_component {
+            proj_state.processor.get_scene_mut().unwrap().detach_one::<ScriptComponent>(selection);
+        }
     });
 
     main_window.end();
@@ -1012,6 +1394,146 @@ fn draw_editor_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Resu
     Ok(())
 }
 
+fn draw_import_settings_window(ui: &imgui::Ui, proj_state: &mut OpenProjectState) -> Result<()> {
+    let (path, settings) = match proj_state.import_settings.as_mut() {
+        Some(import_settings) => import_settings,
+        None => return Ok(()),
+    };
+
+    let mut opened = true;
+    let mut apply = false;
+
+    Window::new("Import settings").opened(&mut opened).build(ui, || {
+        ui.text(path.to_str().expect("non utf8 path"));
+
+        ui.spacing();
+        ui.separator();
+        ui.spacing();
+
+        match settings {
+            ImportSettings::Texture(settings) => {
+                enum_combo(ui, "Color space", &mut settings.color_space, &[
+                    (ColorSpace::Linear, "Linear"),
+                    (ColorSpace::Srgb, "sRGB"),
+                ]);
+
+                let filters = [(Filter::Nearest, "Nearest"), (Filter::Linear, "Linear")];
+
+                enum_combo(ui, "Wrap", &mut settings.sampler.wrap, &[
+                    (Wrap::Repeat, "Repeat"),
+                    (Wrap::MirroredRepeat, "Mirrored repeat"),
+                    (Wrap::ClampToEdge, "Clamp to edge"),
+                ]);
+                enum_combo(ui, "Min filter", &mut settings.sampler.min_filter, &filters);
+                enum_combo(ui, "Mag filter", &mut settings.sampler.mag_filter, &filters);
+                ui.checkbox("Mipmaps", &mut settings.sampler.mipmaps);
+                ui.checkbox("Bake mipmaps", &mut settings.bake_mips);
+
+                let mut limit_size = settings.max_size.is_some();
+                if ui.checkbox("Limit size", &mut limit_size) {
+                    settings.max_size = if limit_size { Some(2048) } else { None };
+                }
+
+                if let Some(max_size) = settings.max_size.as_mut() {
+                    let mut max_size_int = *max_size as i32;
+                    if imgui::InputInt::new(ui, "Max size", &mut max_size_int).build() {
+                        *max_size = max_size_int.max(1) as u32;
+                    }
+                }
+
+                enum_combo(ui, "Compression", &mut settings.compression, &[
+                    (Compression::None, "None"),
+                    (Compression::Bc, "BC1/BC3"),
+                    (Compression::Etc2, "ETC2"),
+                ]);
+                ui.checkbox("Readable", &mut settings.readable);
+            }
+            ImportSettings::Scene(settings) => {
+                imgui::Drag::new("Scale").range(0.0001, f32::MAX).speed(0.001).build(ui, &mut settings.scale);
+                enum_combo(ui, "Up axis", &mut settings.up_axis, &[(UpAxis::Y, "Y"), (UpAxis::Z, "Z")]);
+                enum_combo(ui, "Normals", &mut settings.normals, &[
+                    (Normals::Import, "Import"),
+                    (Normals::Smooth, "Smooth"),
+                    (Normals::Flat, "Flat"),
+                ]);
+                ui.checkbox("Generate tangents", &mut settings.generate_tangents);
+                ui.checkbox("Join identical vertices", &mut settings.join_identical_vertices);
+                ui.checkbox("Optimize meshes", &mut settings.optimize_meshes);
+
+                let mut lod_levels_int = settings.lod_levels as i32;
+                if imgui::InputInt::new(ui, "LOD levels", &mut lod_levels_int).build() {
+                    settings.lod_levels = lod_levels_int.clamp(0, 8) as u32;
+                }
+            }
+        }
+
+        ui.spacing();
+        ui.separator();
+        ui.spacing();
+
+        apply = ui.button_with_size("Apply", [ui.content_region_avail()[0], 0.0]);
+    });
+
+    if apply {
+        import::set_import_settings(path, &proj_state.project_root, settings.clone())?;
+        import::import(path, &proj_state.project_root)?;
+
+        proj_state.scan_avail_resources()?;
+    }
+
+    if !opened {
+        proj_state.import_settings = None;
+    }
+
+    Ok(())
+}
+
+/// Adds an entity with the mesh of a primitive to the scene. The mesh, and a plain material shared by all the
+/// primitives, are saved in the project the first time they're needed.
+fn create_primitive(proj_state: &mut OpenProjectState, primitive: Primitive) -> Result<()> {
+    let mesh_path = PathBuf::from(format!("$/primitives/{}.mesh", primitive.name().to_lowercase()));
+    let mat_path = PathBuf::from("$/primitives/default.mat");
+
+    let fs_mesh_path = path::as_fs_abs(&proj_state.project_root, &mesh_path)?;
+    let fs_mat_path = path::as_fs_abs(&proj_state.project_root, &mat_path)?;
+
+    fs::create_dir_all(fs_mesh_path.parent().unwrap())?;
+
+    if !fs_mesh_path.exists() {
+        primitive.mesh().save(&fs_mesh_path)?;
+    }
+
+    if !fs_mat_path.exists() {
+        Material::default().save(&fs_mat_path)?;
+    }
+
+    // Gives them a GUID, for the references below to have it
+    proj_state.scan_avail_resources()?;
+
+    let db = proj_state.processor.get_asset_db();
+    let mesh_comp = MeshComponent::new(db.reference(&mesh_path), db.reference(&mat_path));
+
+    let scene = proj_state.processor.get_scene_mut().unwrap();
+
+    let entity = scene.create();
+    scene.attach(entity, TransformComponent::default());
+    scene.attach(entity, HierarchyComponent::default());
+    scene.attach(entity, NameComponent(primitive.name().to_owned()));
+    scene.attach(entity, mesh_comp);
+
+    Ok(())
+}
+
+fn enum_combo<T: Copy + PartialEq>(ui: &imgui::Ui, label: &str, value: &mut T, options: &[(T, &str)]) {
+    let labels: Vec<_> = options.iter().map(|(_, label)| *label).collect();
+
+    if let Some(mut idx) = options.iter().position(|(option, _)| option == value) {
+        if ui.combo_simple_string(label, &mut idx, &labels) {
+            *value = options[idx].0;
+        }
+    }
+}
+
 fn wipe_dir(path: &Path) -> Result<()> {
     for entry in fs::read_dir(path)? {
         let entry = entry?;
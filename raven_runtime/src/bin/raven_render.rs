@@ -0,0 +1,53 @@
+use std::env;
+use std::error::Error;
+use std::process;
+
+use raven_core::headless::HeadlessContext;
+use raven_core::{FrameError, Processor};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const USAGE: &'static str = "usage: raven_render <project root> <scene> <output png> [<width> <height>]";
+
+const DEFAULT_SIZE: [u32; 2] = [800, 600];
+
+/// Renders a single frame of a scene without opening a window and saves it as a PNG.
+///
+/// For instance:
+/// `raven_render /project $/main.scn main.png 1920 1080`
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(err) = run(&args) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<()> {
+    let (project_root, scene, output, size) = match args {
+        [project_root, scene, output] => (project_root, scene, output, DEFAULT_SIZE),
+        [project_root, scene, output, width, height] => (project_root, scene, output, [width.parse()?, height.parse()?]),
+        _ => return Err(Box::from(USAGE)),
+    };
+
+    let [width, height] = size;
+
+    let _context = HeadlessContext::new(width, height)?;
+
+    let mut processor = Processor::new(project_root)?;
+    processor.set_canvas_size(width, height);
+    // There's a single frame, it must have everything in it
+    processor.set_streaming(false);
+    processor.load_scene(scene)?;
+
+    match processor.do_frame() {
+        Ok(_) => (),
+        Err(FrameError::NoCamera) => return Err(Box::from("the scene has no active camera")),
+        Err(FrameError::Generic(err)) => return Err(err.into()),
+    }
+
+    processor.capture_frame().save(output)?;
+
+    Ok(())
+}
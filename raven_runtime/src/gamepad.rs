@@ -0,0 +1,67 @@
+use gilrs::{EventType, Gilrs};
+
+use raven_core::input::{Button, GamepadButton, Input};
+
+// The buttons of a gilrs gamepad that have a counterpart among the standard ones. Its triggers are the shoulder
+// buttons, its second triggers the actual triggers
+const BUTTONS: [(gilrs::Button, GamepadButton); 16] = [
+    (gilrs::Button::South, GamepadButton::South),
+    (gilrs::Button::East, GamepadButton::East),
+    (gilrs::Button::West, GamepadButton::West),
+    (gilrs::Button::North, GamepadButton::North),
+    (gilrs::Button::LeftTrigger, GamepadButton::LeftShoulder),
+    (gilrs::Button::RightTrigger, GamepadButton::RightShoulder),
+    (gilrs::Button::LeftTrigger2, GamepadButton::LeftTrigger),
+    (gilrs::Button::RightTrigger2, GamepadButton::RightTrigger),
+    (gilrs::Button::Select, GamepadButton::Select),
+    (gilrs::Button::Start, GamepadButton::Start),
+    (gilrs::Button::LeftThumb, GamepadButton::LeftStick),
+    (gilrs::Button::RightThumb, GamepadButton::RightStick),
+    (gilrs::Button::DPadUp, GamepadButton::DPadUp),
+    (gilrs::Button::DPadDown, GamepadButton::DPadDown),
+    (gilrs::Button::DPadLeft, GamepadButton::DPadLeft),
+    (gilrs::Button::DPadRight, GamepadButton::DPadRight),
+];
+
+/// Reads the gamepads, which windows don't report, and presses their buttons on an `Input`. Buttons held on more than
+/// one gamepad are the same button.
+pub struct Gamepads {
+    gilrs: Gilrs,
+}
+
+impl Gamepads {
+    pub fn new() -> Result<Gamepads, gilrs::Error> {
+        Ok(Gamepads { gilrs: Gilrs::new()? })
+    }
+
+    /// Presses and releases the buttons that went down and up since the last poll.
+    pub fn poll(&mut self, input: &mut Input) {
+        while let Some(event) = self.gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) => {
+                    if let Some(button) = to_button(button) {
+                        input.press(button);
+                    }
+                }
+                EventType::ButtonReleased(button, _) => {
+                    if let Some(button) = to_button(button) {
+                        input.release(button);
+                    }
+                }
+                // A gamepad that's gone doesn't release what was held on it
+                EventType::Disconnected => {
+                    for &(_, button) in &BUTTONS {
+                        input.release(Button::Gamepad(button));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn to_button(button: gilrs::Button) -> Option<Button> {
+    BUTTONS.iter()
+        .find(|&&(gilrs_button, _)| gilrs_button == button)
+        .map(|&(_, button)| Button::Gamepad(button))
+}
@@ -1,4 +1,5 @@
 use std::error::Error;
+use std::path::PathBuf;
 
 use glutin::ContextBuilder;
 use glutin::event::{Event, WindowEvent};
@@ -6,10 +7,17 @@ use glutin::event_loop::{ControlFlow, EventLoop};
 use glutin::window::WindowBuilder;
 
 use raven_core::Processor;
-use raven_core::time::FpsCounter;
+use raven_core::time::{Delta, FpsCounter};
+use raven_core::watch::ProjectWatcher;
+
+use crate::gamepad::Gamepads;
+
+mod gamepad;
 
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
+const MAIN_SCENE: &str = "$/main.scn";
+
 fn main() -> Result<()> {
     let el = EventLoop::new();
     let wb = WindowBuilder::new().with_title("Raven");
@@ -21,30 +29,105 @@ fn main() -> Result<()> {
     gl::load_with(|symbol| windowed_context.get_proc_address(symbol));
 
     let mut processor = Processor::new(".")?;
-    processor.load_scene("$/main.scn")?;
+    processor.load_scene(MAIN_SCENE)?;
+
+    if let Some(err) = processor.take_actions_error() {
+        eprintln!("Couldn't load the action map, nothing is bound: {}", err);
+    }
+
+    let watcher = match ProjectWatcher::new(".") {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            eprintln!("Changes to the project won't be reloaded: {}", err);
+            None
+        }
+    };
+
+    let mut gamepads = match Gamepads::new() {
+        Ok(gamepads) => Some(gamepads),
+        Err(err) => {
+            eprintln!("Gamepads won't be read: {}", err);
+            None
+        }
+    };
 
     let mut fps_counter = FpsCounter::default();
+    let mut delta = Delta::default();
+    // Whether the title is showing the loading progress
+    let mut loading = false;
 
     el.run(move |event, _, control_flow| {
         match event {
             Event::LoopDestroyed => return,
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::Resized(physical_size) => {
-                    windowed_context.resize(physical_size);
-                    processor.set_canvas_size(physical_size.width, physical_size.height);
+            Event::WindowEvent { event, .. } => {
+                processor.get_input_mut().handle_window_event(&event);
+
+                match event {
+                    WindowEvent::Resized(physical_size) => {
+                        windowed_context.resize(physical_size);
+                        processor.set_canvas_size(physical_size.width, physical_size.height);
+                    }
+                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    _ => (),
                 }
-                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                _ => (),
-            },
+            }
+            Event::DeviceEvent { event, .. } => processor.get_input_mut().handle_device_event(&event),
             Event::MainEventsCleared => {
                 windowed_context.window().request_redraw();
             }
             Event::RedrawRequested(_) => {
+                if let Some(watcher) = &watcher {
+                    let changed = watcher.poll();
+
+                    // Nothing changes the scene at runtime, so it can be loaded again as a whole. Files are often
+                    // caught halfway through being saved, so errors keep what was loaded before rather than quitting
+                    if changed.contains(&PathBuf::from(MAIN_SCENE)) {
+                        if let Err(err) = processor.load_scene(MAIN_SCENE) {
+                            eprintln!("Couldn't reload the scene: {}", err);
+                        }
+                    }
+
+                    if let Err(err) = processor.reload_assets(&changed) {
+                        eprintln!("Couldn't reload the assets: {}", err);
+                    }
+
+                    if let Some(err) = processor.take_actions_error() {
+                        eprintln!("Couldn't reload the action map, the previous one is kept: {}", err);
+                    }
+                }
+
+                if let Some(gamepads) = gamepads.as_mut() {
+                    gamepads.poll(processor.get_input_mut());
+                }
+
+                if let Some(delta) = delta.on_frame() {
+                    // Scripts see where the animations left things, and can override them
+                    processor.animate(delta);
+
+                    for err in processor.run_scripts(delta) {
+                        eprintln!("{}", err);
+                    }
+                }
+
                 processor.do_frame().unwrap();
                 windowed_context.swap_buffers().unwrap();
 
+                // What was pressed during this frame has been seen by everything drawn in it
+                processor.get_input_mut().end_frame();
+
+                // Assets are streamed in while the scene is drawn, without the ones that haven't been loaded yet
+                let progress = processor.get_load_progress();
+
+                if !progress.is_done() {
+                    windowed_context.window().set_title(&format!("Raven - Loading {:.0}%", progress.fraction() * 100.0));
+                    loading = true;
+                } else if loading {
+                    windowed_context.window().set_title("Raven");
+                    loading = false;
+                }
+
                 if let Some(stats) = fps_counter.on_frame() {
-                    println!("{:?}", stats);
+                    println!("{:?} {:?} {:?}", stats, processor.get_culling_stats(), processor.get_memory_stats());
                 }
             }
             _ => (),